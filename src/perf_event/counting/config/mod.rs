@@ -15,11 +15,15 @@
 mod extra_config;
 mod new;
 
+#[cfg(feature = "linux-5.13")]
+use crate::infra::WrapOption;
 use crate::perf_event::PerfEventAttr;
 use std::ffi::CString;
 use std::fmt::Debug;
 use std::rc::Rc;
 
+#[cfg(feature = "linux-5.13")]
+use crate::CorePmu;
 use crate::{Event, EventScope};
 pub use extra_config::*;
 
@@ -63,6 +67,19 @@ impl Config {
         &self.perf_event_attr
     }
 
+    /// This config on the given core PMU of hybrid CPUs, see: [`hybrid_pmus`](crate::hybrid_pmus)
+    ///
+    /// `None` if the event is not a hardware or cache event. The result should only be
+    /// counted on `pmu.cpus`, and once per core PMU to cover all CPUs.
+    #[cfg(feature = "linux-5.13")]
+    pub fn on_pmu(&self, pmu: &CorePmu) -> Option<Self> {
+        Self {
+            perf_event_attr: self.perf_event_attr.on_pmu(pmu)?,
+            ..self.clone()
+        }
+        .wrap_some()
+    }
+
     pub fn as_raw_mut(&mut self) -> &mut PerfEventAttr {
         &mut self.perf_event_attr
     }
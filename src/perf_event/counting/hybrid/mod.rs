@@ -0,0 +1,102 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+
+mod stat;
+#[cfg(test)]
+mod tests;
+
+use crate::config;
+use crate::config::{Cpu, Error, Process};
+use crate::counting::{Config, Counter};
+use crate::infra::WrapResult;
+use crate::syscall::bindings::*;
+use crate::{hybrid_pmus, CorePmu};
+pub use stat::HybridCounterStat;
+use std::io;
+
+/// Counter of a hardware or cache event on hybrid CPUs.
+///
+/// On hybrid systems the event is opened once per core PMU, with the extended type
+/// in the upper bits of `config`. On non-hybrid systems, or for other types of event,
+/// there is only one member named `cpu`, i.e. the core PMU of non-hybrid systems.
+pub struct HybridCounter {
+    /// Pairs of PMU name and the counter on it
+    pub(crate) members: Vec<(String, Counter)>,
+}
+
+impl HybridCounter {
+    pub fn new(process: &Process, cpu: &Cpu, cfg: &Config) -> config::Result<Self> {
+        let pmus = hybrid_pmus().map_err(Error::SyscallFailed)?;
+        Self::from_pmus(process, cpu, &pmus, cfg)
+    }
+
+    /// Use the given core PMUs instead of detecting them, see: `hybrid_pmus_from_root`
+    pub fn from_pmus(
+        process: &Process,
+        cpu: &Cpu,
+        pmus: &[CorePmu],
+        cfg: &Config,
+    ) -> config::Result<Self> {
+        let raw = cfg.as_raw();
+        let is_hardware = matches!(raw.type_, PERF_TYPE_HARDWARE | PERF_TYPE_HW_CACHE);
+
+        if pmus.is_empty() || !is_hardware {
+            let counter = Counter::new(process, cpu, &mut cfg.clone())?;
+            return Self {
+                members: vec![("cpu".to_string(), counter)],
+            }
+            .wrap_ok();
+        }
+
+        let mut members = vec![];
+        for pmu in pmus {
+            // the event is only counted on CPUs of this PMU
+            if let Cpu::Id(id) = cpu {
+                if !pmu.cpus.contains(id) {
+                    continue;
+                }
+            }
+            let Some(mut cfg) = cfg.on_pmu(pmu) else {
+                continue;
+            };
+            members.push((pmu.name.clone(), Counter::new(process, cpu, &mut cfg)?));
+        }
+
+        if members.is_empty() {
+            return Err(Error::InvalidProcessCpu);
+        }
+        Self { members }.wrap_ok()
+    }
+
+    pub fn enable(&self) -> io::Result<()> {
+        self.members.iter().try_for_each(|(_, it)| it.enable())
+    }
+
+    pub fn disable(&self) -> io::Result<()> {
+        self.members.iter().try_for_each(|(_, it)| it.disable())
+    }
+
+    pub fn reset(&self) -> io::Result<()> {
+        self.members.iter().try_for_each(|(_, it)| it.reset())
+    }
+
+    /// Names of PMUs this counter is opened on
+    pub fn pmus(&self) -> impl Iterator<Item = &str> {
+        self.members.iter().map(|(name, _)| name.as_str())
+    }
+
+    pub fn stat(&mut self) -> io::Result<HybridCounterStat> {
+        stat::hybrid_counter_stat(self)
+    }
+}
@@ -0,0 +1,40 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+
+use crate::counting::{CounterStat, HybridCounter};
+use crate::infra::WrapResult;
+use std::collections::HashMap;
+use std::io;
+
+#[derive(Debug, Clone)]
+pub struct HybridCounterStat {
+    /// Sum of counts of all PMUs
+    pub event_count: u64,
+    /// Map of PMU name -> [`CounterStat`] on it
+    pub pmu_stats: HashMap<String, CounterStat>,
+}
+
+#[inline]
+pub fn hybrid_counter_stat(counter: &mut HybridCounter) -> io::Result<HybridCounterStat> {
+    let mut pmu_stats = HashMap::new();
+    for (name, member) in &mut counter.members {
+        pmu_stats.insert(name.clone(), member.stat()?);
+    }
+
+    HybridCounterStat {
+        event_count: pmu_stats.values().map(|it| it.event_count).sum(),
+        pmu_stats,
+    }
+    .wrap_ok()
+}
@@ -0,0 +1,191 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+
+use crate::config::{Cpu, Error, Process};
+use crate::counting::{Config, HybridCounter};
+use crate::sampling::OverflowBy;
+use crate::syscall::bindings::*;
+use crate::test::cpu_workload;
+use crate::{
+    hybrid_pmus_from_root, CacheOp, CacheOpResult, CorePmu, DynamicPmuEvent, Event, EventScope,
+    HardwareEvent, SoftwareEvent,
+};
+use crate::{sampling, tracing};
+use std::fs;
+use std::path::PathBuf;
+
+fn gen_pmus() -> Vec<CorePmu> {
+    vec![
+        CorePmu {
+            name: "cpu_atom".to_string(),
+            r#type: 10,
+            cpus: (8..16).collect(),
+        },
+        CorePmu {
+            name: "cpu_core".to_string(),
+            r#type: 4,
+            cpus: (0..8).collect(),
+        },
+    ]
+}
+
+/// Fake sysfs under a temporary root, which is removed on drop
+struct FakeRoot(PathBuf);
+
+impl FakeRoot {
+    fn new(tag: &str) -> Self {
+        let root = std::env::temp_dir().join(format!(
+            "perf-event-rs-hybrid-{}-{}",
+            tag,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&root);
+        Self(root)
+    }
+
+    fn write(&self, path: &str, content: &str) {
+        let path = self.0.join("sys/bus/event_source/devices").join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    fn write_hybrid(&self) {
+        self.write("cpu_core/type", "4\n");
+        self.write("cpu_core/cpus", "0-7\n");
+        self.write("cpu_atom/type", "10\n");
+        self.write("cpu_atom/cpus", "8-15\n");
+    }
+}
+
+impl Drop for FakeRoot {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+#[test]
+fn test_hybrid_pmus_from_root() {
+    let root = FakeRoot::new("pmus");
+
+    assert!(hybrid_pmus_from_root(&root.0).unwrap().is_empty());
+    root.write("cpu/type", "4\n");
+    root.write("software/type", "1\n");
+    assert!(hybrid_pmus_from_root(&root.0).unwrap().is_empty());
+
+    fs::remove_dir_all(root.0.join("sys/bus/event_source/devices/cpu")).unwrap();
+    root.write_hybrid();
+    assert_eq!(hybrid_pmus_from_root(&root.0).unwrap(), gen_pmus());
+}
+
+#[test]
+fn test_configs_on_pmus() {
+    let root = FakeRoot::new("configs");
+    root.write_hybrid();
+    let pmus = hybrid_pmus_from_root(&root.0).unwrap();
+
+    let scopes = EventScope::all();
+    let overflow_by = OverflowBy::Period(1000);
+    let hardware = Event::from(HardwareEvent::Instructions);
+    let cache = Event::from(HardwareEvent::CacheL1d(CacheOp::Read, CacheOpResult::Miss));
+    for ev in [hardware, cache] {
+        let counting = Config::new(&ev, &scopes);
+        let sampling = sampling::Config::new(&ev, &scopes, &overflow_by);
+        let tracing = tracing::Config::new(&ev, &scopes);
+        for pmu in &pmus {
+            let attrs = [
+                counting.on_pmu(pmu).unwrap().into_raw(),
+                sampling.on_pmu(pmu).unwrap().into_raw(),
+                tracing.on_pmu(pmu).unwrap().into_raw(),
+            ];
+            for (attr, orig) in
+                attrs
+                    .iter()
+                    .zip([counting.as_raw(), sampling.as_raw(), tracing.as_raw()])
+            {
+                assert_eq!(attr.type_, orig.type_);
+                assert_eq!(attr.config >> PERF_PMU_TYPE_SHIFT, pmu.r#type as u64);
+                assert_eq!(attr.config & PERF_HW_EVENT_MASK as u64, orig.config);
+                assert_eq!(attr.sample_type, orig.sample_type);
+            }
+        }
+    }
+
+    let ev = Event::from(SoftwareEvent::TaskClock);
+    let sampling = sampling::Config::new(&ev, &scopes, &overflow_by);
+    assert!(sampling.on_pmu(&pmus[0]).is_none());
+    assert!(tracing::Config::new(&ev, &scopes)
+        .on_pmu(&pmus[0])
+        .is_none());
+}
+
+#[test]
+fn test_on_pmu() {
+    let pmu = &gen_pmus()[0];
+
+    let ev = HardwareEvent::Instructions.on_pmu(pmu);
+    let Event::DynamicPmu(DynamicPmuEvent::Other { r#type, config }) = ev else {
+        panic!();
+    };
+    assert_eq!(r#type, PERF_TYPE_HARDWARE);
+    assert_eq!(config, (10 << 32) | PERF_COUNT_HW_INSTRUCTIONS as u64);
+
+    let ev = HardwareEvent::CacheL1d(CacheOp::Read, CacheOpResult::Miss).on_pmu(pmu);
+    let Event::DynamicPmu(DynamicPmuEvent::Other { r#type, config }) = ev else {
+        panic!();
+    };
+    assert_eq!(r#type, PERF_TYPE_HW_CACHE);
+    assert_eq!(config >> 32, 10);
+}
+
+#[test]
+fn test_cpu_out_of_pmus() {
+    let scopes = EventScope::all();
+    let cfg = Config::new(&Event::from(HardwareEvent::CpuCycles), &scopes);
+
+    let result = HybridCounter::from_pmus(&Process::Any, &Cpu::Id(99), &gen_pmus(), &cfg);
+    assert!(matches!(result, Err(Error::InvalidProcessCpu)));
+}
+
+#[test]
+fn test_software_fallback() {
+    let scopes = EventScope::all();
+    let cfg = Config::new(&Event::from(SoftwareEvent::TaskClock), &scopes);
+
+    let mut counter =
+        HybridCounter::from_pmus(&Process::Current, &Cpu::Any, &gen_pmus(), &cfg).unwrap();
+    assert_eq!(counter.pmus().collect::<Vec<_>>(), vec!["cpu"]);
+
+    counter.enable().unwrap();
+    cpu_workload();
+    counter.disable().unwrap();
+    let stat = counter.stat().unwrap();
+    assert!(stat.event_count > 0);
+    assert_eq!(stat.event_count, stat.pmu_stats["cpu"].event_count);
+}
+
+#[test]
+fn test_hardware() {
+    let scopes = EventScope::all();
+    let cfg = Config::new(&Event::from(HardwareEvent::Instructions), &scopes);
+
+    let mut counter = HybridCounter::new(&Process::Current, &Cpu::Any, &cfg).unwrap();
+    counter.enable().unwrap();
+    cpu_workload();
+    counter.disable().unwrap();
+    let stat = counter.stat().unwrap();
+    dbg!(&stat);
+    assert!(stat.event_count > 0);
+    let sum: u64 = stat.pmu_stats.values().map(|it| it.event_count).sum();
+    assert_eq!(stat.event_count, sum);
+}
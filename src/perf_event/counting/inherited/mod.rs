@@ -0,0 +1,139 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+
+mod stat;
+#[cfg(test)]
+mod tests;
+
+use crate::config;
+use crate::config::{Error, Process};
+use crate::counting::{Config, CounterStat};
+use crate::infra::WrapResult;
+use crate::sampling::record::RecordBody;
+use crate::sampling::Sampler;
+use crate::syscall::bindings::*;
+use crate::syscall::perf_event_open_wrapped;
+use crate::topology::online_cpus;
+pub use stat::InheritedCounterStat;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::os::fd::FromRawFd;
+
+/// Counter that also reports the final counts of exited children.
+///
+/// The kernel refuses to mmap inherited per-task events, so one event is opened
+/// on every online CPU, each paired with a ring buffer. With `inherit_stat` set,
+/// every exiting child emits `PERF_RECORD_READ` into these buffers.
+///
+/// On context switch the kernel may swap the identical perf contexts of a parent
+/// and its child, such a child exits without `PERF_RECORD_READ`,
+/// but its count is still included in the total.
+pub struct InheritedCounter {
+    pub(crate) members: Vec<Sampler>,
+    /// Map of `(pid, tid)` -> final counts of exited children
+    pub(crate) children: HashMap<(u32, u32), CounterStat>,
+}
+
+impl InheritedCounter {
+    /// `inherit` and `inherit_stat` are always enabled in `cfg`, and `read_format` is
+    /// replaced by `GROUP | ID | TOTAL_TIME_ENABLED | TOTAL_TIME_RUNNING` (`| LOST` with linux-6.0),
+    /// any `read_format` set in `cfg` is ignored.
+    /// `mmap_pages` is the same as in `Sampler::new`.
+    pub fn new(process: &Process, mmap_pages: usize, cfg: &mut Config) -> config::Result<Self> {
+        let pid = match process.as_i32()? {
+            -1 => return Err(Error::InvalidProcessCpu),
+            pid => pid,
+        };
+
+        let perf_event_attr = cfg.as_raw_mut();
+        perf_event_attr.set_inherit(1);
+        perf_event_attr.set_inherit_stat(1);
+        // the counts and times of the children are read from `PERF_RECORD_READ`,
+        // which carries the event id to tell the members apart
+        #[rustfmt::skip]
+        let read_format =
+              PERF_FORMAT_TOTAL_TIME_ENABLED
+            | PERF_FORMAT_TOTAL_TIME_RUNNING
+            | PERF_FORMAT_ID
+            | PERF_FORMAT_GROUP;
+        #[cfg(feature = "linux-6.0")]
+        let read_format = read_format | PERF_FORMAT_LOST;
+        perf_event_attr.read_format = read_format as _;
+
+        let members = online_cpus()
+            .map_err(Error::SyscallFailed)?
+            .into_iter()
+            .map(|cpu| {
+                let fd = unsafe { perf_event_open_wrapped(perf_event_attr, pid, cpu as _, -1, 0) }
+                    .map_err(Error::SyscallFailed)?;
+                let file = unsafe { File::from_raw_fd(fd) };
+
+                Sampler::from_file(file, mmap_pages, perf_event_attr).map_err(Error::SyscallFailed)
+            })
+            .collect::<config::Result<_>>()?;
+
+        Self {
+            members,
+            children: HashMap::new(),
+        }
+        .wrap_ok()
+    }
+
+    pub fn enable(&self) -> io::Result<()> {
+        self.members.iter().try_for_each(|it| it.enable())
+    }
+
+    pub fn disable(&self) -> io::Result<()> {
+        self.members.iter().try_for_each(|it| it.disable())
+    }
+
+    /// Counts folded from exited children are not reset by the kernel,
+    /// so they are kept in the table of exited children too.
+    pub fn reset(&self) -> io::Result<()> {
+        self.members.iter().try_for_each(|it| it.reset())
+    }
+
+    /// Drain pending `PERF_RECORD_READ` records into the table of exited children.
+    fn drain(&mut self) {
+        for member in &mut self.members {
+            while let Some(record) = member.next_record() {
+                let RecordBody::Read(body) = record.body else {
+                    continue;
+                };
+                let child = self
+                    .children
+                    .entry((body.pid, body.tid))
+                    .or_insert_with(|| CounterStat {
+                        event_id: 0,
+                        event_count: 0,
+                        time_enabled: 0,
+                        time_running: 0,
+                    });
+                // a child ran on several CPUs reports once for each of them
+                for (id, count) in &body.values.member_counts {
+                    child.event_id = *id;
+                    child.event_count += count.event_count;
+                }
+                child.time_enabled += body.values.time_enabled;
+                child.time_running += body.values.time_running;
+            }
+        }
+    }
+
+    pub fn stat(&mut self) -> io::Result<InheritedCounterStat> {
+        self.drain();
+        stat::inherited_counter_stat(self)
+    }
+}
@@ -0,0 +1,54 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+
+use crate::counting::{CounterStat, InheritedCounter};
+use crate::infra::WrapResult;
+use std::collections::HashMap;
+use std::io;
+
+#[derive(Debug, Clone)]
+pub struct InheritedCounterStat {
+    /// Count of the monitored process, including all its exited children
+    pub event_count: u64,
+    pub time_enabled: u64,
+    pub time_running: u64,
+    /// Map of `(pid, tid)` -> final counts of exited children
+    pub children: HashMap<(u32, u32), CounterStat>,
+}
+
+impl InheritedCounterStat {
+    /// Count of the monitored process without its exited children.
+    pub fn self_count(&self) -> u64 {
+        let children: u64 = self.children.values().map(|it| it.event_count).sum();
+        self.event_count.saturating_sub(children)
+    }
+}
+
+#[inline]
+pub fn inherited_counter_stat(counter: &mut InheritedCounter) -> io::Result<InheritedCounterStat> {
+    let mut stat = InheritedCounterStat {
+        event_count: 0,
+        time_enabled: 0,
+        time_running: 0,
+        children: counter.children.clone(),
+    };
+    // child events fold their counts into the parent event on exit
+    for member in &mut counter.members {
+        let member = member.stat()?;
+        stat.event_count += member.event_count;
+        stat.time_enabled += member.time_enabled;
+        stat.time_running += member.time_running;
+    }
+    stat.wrap_ok()
+}
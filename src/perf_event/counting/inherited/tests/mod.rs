@@ -0,0 +1,78 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+
+use crate::config::Process;
+use crate::counting::{Config, InheritedCounter};
+use crate::{Event, EventScope, SoftwareEvent};
+use std::process::Command;
+
+fn gen_counter() -> InheritedCounter {
+    let scopes = EventScope::all();
+    let ev = Event::from(SoftwareEvent::TaskClock);
+    let mut cfg = Config::new(&ev, &scopes);
+
+    InheritedCounter::new(&Process::Current, 1 + (1 << 4), &mut cfg).unwrap()
+}
+
+fn spawn_children() -> Vec<u32> {
+    (0..4)
+        .map(|_| {
+            let mut child = Command::new("sh")
+                .args(["-c", "i=0; while [ $i -lt 20000 ]; do i=$((i+1)); done"])
+                .spawn()
+                .unwrap();
+            let pid = child.id();
+            child.wait().unwrap();
+            pid
+        })
+        .collect()
+}
+
+#[test]
+fn test_children() {
+    let mut counter = gen_counter();
+
+    counter.enable().unwrap();
+    let pids = spawn_children();
+    counter.disable().unwrap();
+
+    let stat = counter.stat().unwrap();
+    dbg!(&stat, &pids);
+    // children swapped contexts with us report nothing
+    assert!(!stat.children.is_empty());
+    for (&(pid, tid), child) in &stat.children {
+        assert!(pids.contains(&pid));
+        assert_eq!(pid, tid);
+        assert!(child.event_count > 0);
+    }
+    let children: u64 = stat.children.values().map(|it| it.event_count).sum();
+    assert!(stat.event_count >= children);
+    assert_eq!(stat.self_count(), stat.event_count - children);
+}
+
+#[test]
+fn test_reset() {
+    let mut counter = gen_counter();
+
+    counter.enable().unwrap();
+    spawn_children();
+    counter.disable().unwrap();
+    let before = counter.stat().unwrap();
+    assert!(before.self_count() > 0);
+
+    counter.reset().unwrap();
+    let after = counter.stat().unwrap();
+    assert!(after.event_count < before.event_count);
+    assert_eq!(after.children.len(), before.children.len());
+}
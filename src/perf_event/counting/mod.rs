@@ -14,9 +14,17 @@
 
 mod config;
 mod group;
+#[cfg(feature = "linux-5.13")]
+mod hybrid;
+mod inherited;
 mod single;
+mod task;
 
 #[allow(unused_imports)]
 pub use config::*;
 pub use group::*;
+#[cfg(feature = "linux-5.13")]
+pub use hybrid::*;
+pub use inherited::*;
 pub use single::*;
+pub use task::*;
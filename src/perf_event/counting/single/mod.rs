@@ -20,6 +20,7 @@ use crate::config;
 use crate::config::{Cpu, Error, Process};
 use crate::counting::single::stat::counter_stat;
 use crate::counting::Config;
+use crate::sampling::Sampler;
 use crate::syscall::bindings::*;
 use crate::syscall::{ioctl_wrapped, perf_event_open_wrapped};
 pub use stat::CounterStat;
@@ -66,8 +67,13 @@ impl Counter {
         ioctl_wrapped::<()>(&self.file, PERF_EVENT_IOCTL_RESET, None)
     }
 
-    pub fn set_output(&self, new: &File) -> io::Result<()> {
-        let raw_fd = new.as_raw_fd() as i64;
+    /// Redirect records of this event into the ring buffer of `output`,
+    /// which must be on the same CPU, or for the same task if `Cpu::Any`.
+    ///
+    /// Samplers can not be redirected once their own ring buffer is mapped,
+    /// open them with `SharedSampler::add_member_for` instead.
+    pub fn set_output(&self, output: &Sampler) -> io::Result<()> {
+        let raw_fd = output.as_raw_fd() as i64;
         ioctl_wrapped(&self.file, PERF_EVENT_IOCTL_SET_OUTPUT, Some(raw_fd))
     }
 
@@ -0,0 +1,65 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+
+#[cfg(test)]
+mod tests;
+
+use crate::infra::WrapResult;
+use crate::syscall::prctl_wrapped;
+use libc::{PR_TASK_PERF_EVENTS_DISABLE, PR_TASK_PERF_EVENTS_ENABLE};
+use std::io;
+use std::marker::PhantomData;
+
+/// Enable all events opened by the calling thread, see: `PR_TASK_PERF_EVENTS_ENABLE`
+///
+/// The kernel tracks events by the thread which opened them, regardless of what they
+/// monitor, so counters, group members, samplers and tracers opened by the calling
+/// thread are all enabled one by one, even if they were disabled explicitly.
+/// Events opened with `enable_on_exec` are enabled too, without waiting for exec.
+pub fn enable_all() -> io::Result<()> {
+    prctl_wrapped(PR_TASK_PERF_EVENTS_ENABLE)
+}
+
+/// Disable all events opened by the calling thread, see: `PR_TASK_PERF_EVENTS_DISABLE`
+///
+/// Records already in the ring buffers of samplers are kept,
+/// events opened with `enable_on_exec` are still enabled on exec.
+pub fn disable_all() -> io::Result<()> {
+    prctl_wrapped(PR_TASK_PERF_EVENTS_DISABLE)
+}
+
+/// Disable all events opened by the calling thread until dropped,
+/// e.g. to exclude startup from measurement.
+///
+/// Dropping the guard enables all of them, see: [`enable_all`]
+pub struct DisableAllGuard {
+    // prctl only affects the calling thread, so the guard must be dropped on it
+    _not_send: PhantomData<*const ()>,
+}
+
+impl DisableAllGuard {
+    pub fn new() -> io::Result<Self> {
+        disable_all()?;
+        Self {
+            _not_send: PhantomData,
+        }
+        .wrap_ok()
+    }
+}
+
+impl Drop for DisableAllGuard {
+    fn drop(&mut self) {
+        let _ = enable_all();
+    }
+}
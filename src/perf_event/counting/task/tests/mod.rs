@@ -0,0 +1,140 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+
+use crate::config::{Cpu, Process};
+use crate::counting::{
+    disable_all, enable_all, Config, Counter, CounterGroup, DisableAllGuard, ExtraConfig,
+};
+use crate::sampling::{OverflowBy, Sampler};
+use crate::test::cpu_workload;
+use crate::{sampling, Event, EventScope, SoftwareEvent};
+
+fn gen_cfg(extra_config: &ExtraConfig) -> Config {
+    let scopes = EventScope::all();
+    let ev = Event::from(SoftwareEvent::TaskClock);
+    Config::extra_new(&ev, &scopes, extra_config)
+}
+
+fn gen_counter() -> Counter {
+    let mut cfg = gen_cfg(&Default::default());
+    Counter::new(&Process::Current, &Cpu::Any, &mut cfg).unwrap()
+}
+
+#[test]
+fn test_counter() {
+    let mut counter = gen_counter();
+    counter.enable().unwrap();
+
+    disable_all().unwrap();
+    let disabled = counter.stat().unwrap().event_count;
+    cpu_workload();
+    assert_eq!(counter.stat().unwrap().event_count, disabled);
+
+    enable_all().unwrap();
+    cpu_workload();
+    assert!(counter.stat().unwrap().event_count > disabled);
+}
+
+#[test]
+fn test_guard() {
+    let mut counter = gen_counter();
+    counter.enable().unwrap();
+
+    let disabled = {
+        let _guard = DisableAllGuard::new().unwrap();
+        let disabled = counter.stat().unwrap().event_count;
+        cpu_workload();
+        assert_eq!(counter.stat().unwrap().event_count, disabled);
+        disabled
+    };
+
+    cpu_workload();
+    assert!(counter.stat().unwrap().event_count > disabled);
+}
+
+#[test]
+fn test_explicitly_disabled() {
+    // counter is disabled when opened
+    let mut counter = gen_counter();
+
+    enable_all().unwrap();
+    cpu_workload();
+    assert!(counter.stat().unwrap().event_count > 0);
+    counter.disable().unwrap();
+}
+
+#[test]
+fn test_enable_on_exec() {
+    let extra_config = ExtraConfig {
+        enable_on_exec: true,
+        ..Default::default()
+    };
+    let mut counter =
+        Counter::new(&Process::Current, &Cpu::Any, &mut gen_cfg(&extra_config)).unwrap();
+
+    cpu_workload();
+    assert_eq!(counter.stat().unwrap().event_count, 0);
+
+    enable_all().unwrap();
+    cpu_workload();
+    assert!(counter.stat().unwrap().event_count > 0);
+    counter.disable().unwrap();
+}
+
+#[test]
+fn test_group() {
+    let mut group = CounterGroup::new(&Process::Current, &Cpu::Any).unwrap();
+    let guard_1 = group.add_member(&mut gen_cfg(&Default::default())).unwrap();
+    let guard_2 = group.add_member(&mut gen_cfg(&Default::default())).unwrap();
+    let mut group = group.enable().unwrap();
+
+    disable_all().unwrap();
+    let disabled = group.stat().unwrap();
+    cpu_workload();
+    let stat = group.stat().unwrap();
+    for guard in [&guard_1, &guard_2] {
+        assert_eq!(
+            stat.member_count(guard).unwrap(),
+            disabled.member_count(guard).unwrap()
+        );
+    }
+
+    enable_all().unwrap();
+    cpu_workload();
+    let stat = group.stat().unwrap();
+    for guard in [&guard_1, &guard_2] {
+        assert!(stat.member_count(guard).unwrap() > disabled.member_count(guard).unwrap());
+    }
+}
+
+#[test]
+fn test_sampler() {
+    let scopes = EventScope::all();
+    let ev = Event::from(SoftwareEvent::TaskClock);
+    let cfg = sampling::Config::new(&ev, &scopes, &OverflowBy::Period(1000));
+    let mut sampler = Sampler::new(&Process::Current, &Cpu::Any, 1 + (1 << 10), &cfg).unwrap();
+    sampler.enable().unwrap();
+
+    cpu_workload();
+    disable_all().unwrap();
+    // records before disabling are kept
+    assert!(sampler.next_record().is_some());
+    while sampler.next_record().is_some() {}
+    cpu_workload();
+    assert!(sampler.next_record().is_none());
+
+    enable_all().unwrap();
+    cpu_workload();
+    assert!(sampler.next_record().is_some());
+}
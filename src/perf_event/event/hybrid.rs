@@ -0,0 +1,98 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+
+use crate::infra::WrapResult;
+use crate::perf_event::PerfEventAttr;
+use crate::syscall::bindings::*;
+use crate::topology::parse_cpu_list;
+use crate::{DynamicPmuEvent, Event, HardwareEvent};
+use std::path::Path;
+use std::{fs, io};
+
+/// Core PMU of hybrid CPUs, e.g. `cpu_core` and `cpu_atom` on Intel
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CorePmu {
+    /// Name of the PMU in `/sys/bus/event_source/devices`
+    pub name: String,
+    /// The content of `/sys/bus/event_source/devices/*/type`
+    pub r#type: u32,
+    /// The content of `/sys/bus/event_source/devices/*/cpus`
+    pub cpus: Vec<u32>,
+}
+
+/// Get core PMUs of hybrid CPUs, empty on non-hybrid systems.
+pub fn hybrid_pmus() -> io::Result<Vec<CorePmu>> {
+    hybrid_pmus_from_root("/")
+}
+
+/// Read sysfs under `root` instead of `/`, useful for fake hybrid systems.
+pub fn hybrid_pmus_from_root(root: impl AsRef<Path>) -> io::Result<Vec<CorePmu>> {
+    let dir = root.as_ref().join("sys/bus/event_source/devices");
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e),
+    };
+
+    let mut pmus = vec![];
+    for entry in entries {
+        let entry = entry?;
+        let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+            continue;
+        };
+        // non-hybrid systems have only one core PMU named `cpu`
+        let cpus_path = entry.path().join("cpus");
+        if !name.starts_with("cpu_") || !cpus_path.exists() {
+            continue;
+        }
+
+        let r#type = fs::read_to_string(entry.path().join("type"))?;
+        let r#type = r#type
+            .trim()
+            .parse()
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, r#type.trim()))?;
+        let cpus = parse_cpu_list(&fs::read_to_string(cpus_path)?)?;
+
+        pmus.push(CorePmu { name, r#type, cpus });
+    }
+    pmus.sort_by(|a, b| a.name.cmp(&b.name));
+    pmus.wrap_ok()
+}
+
+impl HardwareEvent {
+    /// The event on the given core PMU, which uses the extended type in
+    /// the upper 32 bits of `config`, see: `PERF_PMU_TYPE_SHIFT`
+    pub fn on_pmu(&self, pmu: &CorePmu) -> Event {
+        let r#type = if self.is_cache_event() {
+            PERF_TYPE_HW_CACHE
+        } else {
+            PERF_TYPE_HARDWARE
+        };
+        let config = ((pmu.r#type as u64) << PERF_PMU_TYPE_SHIFT) | self.as_u64();
+        Event::from(DynamicPmuEvent::Other { r#type, config })
+    }
+}
+
+impl PerfEventAttr {
+    /// The attr on the given core PMU, `None` if not of a hardware or cache event
+    pub(crate) fn on_pmu(&self, pmu: &CorePmu) -> Option<Self> {
+        if !matches!(self.type_, PERF_TYPE_HARDWARE | PERF_TYPE_HW_CACHE) {
+            return None;
+        }
+        let mut attr = self.clone();
+        attr.config = ((pmu.r#type as u64) << PERF_PMU_TYPE_SHIFT)
+            | (self.config & PERF_HW_EVENT_MASK as u64);
+        Some(attr)
+    }
+}
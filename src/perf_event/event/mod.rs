@@ -15,6 +15,8 @@
 mod breakpoint;
 mod dynamic_pmu;
 mod hardware;
+#[cfg(feature = "linux-5.13")]
+mod hybrid;
 mod raw;
 mod scope;
 mod software;
@@ -28,6 +30,8 @@ use std::mem::size_of;
 pub use breakpoint::*;
 pub use dynamic_pmu::*;
 pub use hardware::*;
+#[cfg(feature = "linux-5.13")]
+pub use hybrid::*;
 pub use raw::*;
 pub use scope::*;
 pub use software::*;
@@ -0,0 +1,283 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+#[cfg(test)]
+mod tests;
+
+use crate::config;
+use crate::config::{Error, Process};
+use crate::infra::WrapResult;
+use crate::sampling::record::Record;
+use crate::sampling::{Config, CpuSamplerSet};
+use crate::topology::online_cpus;
+use std::collections::BTreeMap;
+use std::ffi::{CString, OsStr, OsString};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::process::ExitStatusExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus};
+use std::time::Duration;
+use std::{env, fs, io, ptr};
+
+/*
+`Command::spawn` returns after the child has exec'ed, so the pid is only
+known once the program is running. The child is forked by hand instead,
+and waits on a pipe before exec until it is resumed. Everything the child
+needs is prepared before fork, since only async-signal-safe functions can
+be called in the child of a multi-threaded process.
+
+Children forked by other threads meanwhile inherit the pipes until they
+exec, which may delay `resume` until then.
+*/
+
+/// Child process forked from a `Command`, which waits before exec until resumed.
+///
+/// Only the program, arguments, environment variables and working directory
+/// of the command are used, stdio is inherited.
+pub struct StoppedChild {
+    pid: u32,
+    /// Write end of the pipe the child waits on, `None` after resumed
+    go: Option<OwnedFd>,
+    /// Read end of the pipe the child reports exec failure with
+    err: OwnedFd,
+    status: Option<ExitStatus>,
+}
+
+fn pipe() -> io::Result<(OwnedFd, OwnedFd)> {
+    let mut fds = [0; 2];
+    match unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } {
+        -1 => Err(io::Error::last_os_error()),
+        _ => Ok(unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) }),
+    }
+}
+
+fn c_string(it: &OsStr) -> io::Result<CString> {
+    CString::new(it.as_bytes()).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+}
+
+/// Search `PATH` like `execvp` if the program is not a path.
+fn resolve(program: &OsStr, path: Option<&OsStr>) -> io::Result<PathBuf> {
+    if program.as_bytes().contains(&b'/') {
+        return Ok(PathBuf::from(program));
+    }
+    let is_executable = |it: &Path| {
+        fs::metadata(it).is_ok_and(|it| it.is_file() && it.permissions().mode() & 0o111 != 0)
+    };
+    path.into_iter()
+        .flat_map(env::split_paths)
+        .map(|dir| dir.join(program))
+        .find(|it| is_executable(it))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, program.to_string_lossy()))
+}
+
+impl StoppedChild {
+    pub fn spawn(command: &Command) -> io::Result<Self> {
+        let mut envs: BTreeMap<OsString, OsString> = env::vars_os().collect();
+        for (key, value) in command.get_envs() {
+            match value {
+                Some(value) => envs.insert(key.to_owned(), value.to_owned()),
+                None => envs.remove(key),
+            };
+        }
+
+        let program = resolve(
+            command.get_program(),
+            envs.get(OsStr::new("PATH")).map(|it| &**it),
+        )?;
+        let program = c_string(program.as_os_str())?;
+        let args = std::iter::once(command.get_program())
+            .chain(command.get_args())
+            .map(c_string)
+            .collect::<io::Result<Vec<_>>>()?;
+        let envs = envs
+            .iter()
+            .map(|(key, value)| {
+                let mut it = key.clone();
+                it.push("=");
+                it.push(value);
+                c_string(&it)
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        let dir = command
+            .get_current_dir()
+            .map(|it| c_string(it.as_os_str()))
+            .transpose()?;
+
+        let argv: Vec<_> = args
+            .iter()
+            .map(|it| it.as_ptr())
+            .chain([ptr::null()])
+            .collect();
+        let envp: Vec<_> = envs
+            .iter()
+            .map(|it| it.as_ptr())
+            .chain([ptr::null()])
+            .collect();
+        let (go_rx, go_tx) = pipe()?;
+        let (err_rx, err_tx) = pipe()?;
+
+        let mut empty_mask = unsafe { std::mem::zeroed::<libc::sigset_t>() };
+        unsafe { libc::sigemptyset(&mut empty_mask) };
+
+        let pid = unsafe { libc::fork() };
+        if pid == 0 {
+            unsafe {
+                // without its own copy of the write end, the read sees EOF once the parent closes it
+                libc::close(go_tx.as_raw_fd());
+                libc::close(err_rx.as_raw_fd());
+                let mut byte = 0_u8;
+                // the parent is gone or dropped the child without resuming it
+                if libc::read(go_rx.as_raw_fd(), &mut byte as *mut u8 as _, 1) != 1 {
+                    libc::_exit(127);
+                }
+                // like `Command::spawn`, the Rust runtime ignores SIGPIPE
+                libc::signal(libc::SIGPIPE, libc::SIG_DFL);
+                libc::sigprocmask(libc::SIG_SETMASK, &empty_mask, ptr::null_mut());
+                let chdir = dir.as_ref().map_or(0, |it| libc::chdir(it.as_ptr()));
+                if chdir == 0 {
+                    libc::execve(program.as_ptr(), argv.as_ptr(), envp.as_ptr());
+                }
+                let errno = *libc::__errno_location();
+                libc::write(err_tx.as_raw_fd(), &errno as *const i32 as _, 4);
+                libc::_exit(127)
+            }
+        }
+        if pid == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        // the child holds the other ends
+        drop(go_rx);
+        drop(err_tx);
+
+        Self {
+            pid: pid as _,
+            go: Some(go_tx),
+            err: err_rx,
+            status: None,
+        }
+        .wrap_ok()
+    }
+
+    pub const fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    /// Let the child exec, returns the error of exec if it failed.
+    pub fn resume(&mut self) -> io::Result<()> {
+        let Some(go) = self.go.take() else {
+            return Ok(());
+        };
+        let byte = 1_u8;
+        if unsafe { libc::write(go.as_raw_fd(), &byte as *const u8 as _, 1) } != 1 {
+            return Err(io::Error::last_os_error());
+        }
+        drop(go);
+
+        // the pipe is closed on exec, or the errno of exec is written
+        let mut errno = 0_i32;
+        let len = loop {
+            match unsafe { libc::read(self.err.as_raw_fd(), &mut errno as *mut i32 as _, 4) } {
+                -1 if io::Error::last_os_error().kind() == io::ErrorKind::Interrupted => {}
+                -1 => return Err(io::Error::last_os_error()),
+                len => break len,
+            }
+        };
+        match len {
+            4 => {
+                self.wait()?;
+                Err(io::Error::from_raw_os_error(errno))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn wait_pid(&mut self, options: i32) -> io::Result<Option<ExitStatus>> {
+        if let Some(status) = self.status {
+            return Ok(Some(status));
+        }
+        let mut status = 0;
+        loop {
+            match unsafe { libc::waitpid(self.pid as _, &mut status, options) } {
+                -1 if io::Error::last_os_error().kind() == io::ErrorKind::Interrupted => {}
+                -1 => return Err(io::Error::last_os_error()),
+                0 => return Ok(None),
+                _ => break,
+            }
+        }
+        self.status = Some(ExitStatus::from_raw(status));
+        Ok(self.status)
+    }
+
+    /// Wait until the child exits, a child which is not resumed is killed.
+    pub fn wait(&mut self) -> io::Result<ExitStatus> {
+        // other children forked meanwhile may hold the pipe, so closing it is not enough
+        if self.go.take().is_some() {
+            unsafe { libc::kill(self.pid as _, libc::SIGKILL) };
+        }
+        self.wait_pid(0).map(|it| it.unwrap_or_default())
+    }
+
+    pub fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        self.wait_pid(libc::WNOHANG)
+    }
+}
+
+impl Drop for StoppedChild {
+    /// A child which is not resumed is killed and reaped,
+    /// a running child is left running like `std::process::Child`.
+    fn drop(&mut self) {
+        if self.go.is_some() {
+            let _ = self.wait();
+        }
+    }
+}
+
+/// Records of a command and its exit status, see: [`record`]
+#[derive(Debug)]
+pub struct Recording {
+    pub records: Vec<Record>,
+    pub status: ExitStatus,
+}
+
+/// Sample the command from its first instruction until it exits, like `perf record -- cmd`.
+///
+/// `inherit` and `enable_on_exec` are enabled on the config, so children of
+/// the command are sampled too, into one ring buffer of `mmap_pages` per CPU.
+pub fn record(command: &Command, mmap_pages: usize, cfg: &Config) -> config::Result<Recording> {
+    let mut perf_event_attr = cfg.as_raw().clone();
+    perf_event_attr.set_inherit(1);
+    perf_event_attr.set_enable_on_exec(1);
+    // SAFETY: copied from a sampling config, which outlives the samplers being opened
+    let cfg = unsafe { Config::from_raw(perf_event_attr) };
+
+    let mut child = StoppedChild::spawn(command).map_err(Error::SyscallFailed)?;
+    let cpus = online_cpus().map_err(Error::SyscallFailed)?;
+    let mut set = CpuSamplerSet::for_process(&Process::Pid(child.pid()), cpus, mmap_pages, &cfg)?;
+    child.resume().map_err(Error::SyscallFailed)?;
+
+    let mut records = vec![];
+    let status = loop {
+        set.wait(Some(Duration::from_millis(100)))
+            .map_err(Error::SyscallFailed)?;
+        records.extend(set.drain().into_iter().map(|(_, it)| it));
+        if let Some(status) = child.try_wait().map_err(Error::SyscallFailed)? {
+            break status;
+        }
+    };
+    set.disable().map_err(Error::SyscallFailed)?;
+    records.extend(set.drain().into_iter().map(|(_, it)| it));
+
+    Recording { records, status }.wrap_ok()
+}
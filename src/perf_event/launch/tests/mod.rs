@@ -0,0 +1,114 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+use crate::config::{Cpu, Process};
+use crate::counting::{Config as CountingConfig, Counter, ExtraConfig as CountingExtraConfig};
+use crate::launch::{record, StoppedChild};
+use crate::sampling::record::RecordBody;
+use crate::sampling::{Config, ExtraConfig, OverflowBy, SampleRecordFields};
+use crate::{Event, EventScope, SoftwareEvent};
+use std::io;
+use std::os::unix::process::ExitStatusExt;
+use std::process::Command;
+
+fn sh(script: &str) -> Command {
+    let mut command = Command::new("sh");
+    command.args(["-c", script]);
+    command
+}
+
+#[test]
+fn test_exit_status() {
+    let mut command = sh("cd_ok=$(pwd); [ \"$cd_ok\" = /tmp ] && exit $CODE");
+    command.env("CODE", "3").current_dir("/tmp");
+    let mut child = StoppedChild::spawn(&command).unwrap();
+    assert!(child.try_wait().unwrap().is_none());
+    child.resume().unwrap();
+    assert_eq!(child.wait().unwrap().code(), Some(3));
+}
+
+#[test]
+fn test_exec_failed() {
+    let command = Command::new("/nonexistent");
+    let mut child = StoppedChild::spawn(&command).unwrap();
+    let e = child.resume().unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::NotFound);
+
+    let command = Command::new("nonexistent-program");
+    let e = StoppedChild::spawn(&command).err().unwrap();
+    assert_eq!(e.kind(), io::ErrorKind::NotFound);
+}
+
+#[test]
+fn test_not_resumed() {
+    let mut child = StoppedChild::spawn(&sh("exit 0")).unwrap();
+    assert_eq!(child.wait().unwrap().signal(), Some(libc::SIGKILL));
+}
+
+/// The child exits by itself once the write end is closed, without being killed
+#[test]
+fn test_parent_closed() {
+    let mut child = StoppedChild::spawn(&sh("exit 0")).unwrap();
+    drop(child.go.take());
+    let status = child.wait().unwrap();
+    assert_eq!(status.signal(), None);
+    assert_eq!(status.code(), Some(127));
+}
+
+#[test]
+fn test_counted_from_exec() {
+    let ev = Event::from(SoftwareEvent::TaskClock);
+    let extra_config = CountingExtraConfig {
+        enable_on_exec: true,
+        ..Default::default()
+    };
+    let mut cfg = CountingConfig::extra_new(&ev, &EventScope::all(), &extra_config);
+
+    let mut child = StoppedChild::spawn(&sh("exit 0")).unwrap();
+    let mut counter = Counter::new(&Process::Pid(child.pid()), &Cpu::Any, &mut cfg).unwrap();
+    assert_eq!(counter.stat().unwrap().event_count, 0);
+    child.resume().unwrap();
+    assert!(child.wait().unwrap().success());
+    assert!(counter.stat().unwrap().event_count > 0);
+}
+
+#[test]
+fn test_record() {
+    let ev = Event::from(SoftwareEvent::TaskClock);
+    let overflow_by = OverflowBy::Period(100_000);
+    let extra_config = ExtraConfig {
+        sample_record_fields: SampleRecordFields {
+            pid_and_tid: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let cfg = Config::extra_new(&ev, &EventScope::all(), &overflow_by, &extra_config);
+
+    let command = sh("i=0; while [ $i -lt 100000 ]; do i=$((i + 1)); done");
+    let recording = record(&command, 1 + 16, &cfg).unwrap();
+    assert!(recording.status.success());
+
+    let pids: Vec<_> = recording
+        .records
+        .iter()
+        .filter_map(|it| match &it.body {
+            RecordBody::Sample(body) => body.pid,
+            _ => None,
+        })
+        .collect();
+    assert!(!pids.is_empty());
+    // all samples are of the command
+    assert!(pids.iter().all(|it| *it == pids[0]));
+    assert_ne!(pids[0], std::process::id());
+}
@@ -15,8 +15,12 @@
 pub mod config;
 pub mod counting;
 pub mod event;
+pub mod launch;
 pub mod sampling;
+pub mod topology;
 pub mod tracing;
+#[cfg(feature = "linux-4.7")]
+pub mod trigger;
 
 use crate::syscall::bindings::perf_event_attr;
 pub use event::*;
@@ -43,6 +43,11 @@ pub struct ExtraConfig {
     pub include_callchain_kernel: bool,
     pub include_callchain_user: bool,
 
+    /// Overwrite the oldest records when the ring buffer is full, i.e. `write_backward`,
+    /// records are read by `Sampler::snapshot` instead of `Sampler::next_record`
+    #[cfg(feature = "linux-4.7")]
+    pub write_backward: bool,
+
     #[cfg(feature = "linux-4.1")]
     pub clockid: Option<ClockId>,
     pub precise_ip: SampleIpSkid,
@@ -84,6 +89,9 @@ impl Default for ExtraConfig {
             include_callchain_kernel: false,
             include_callchain_user: false,
 
+            #[cfg(feature = "linux-4.7")]
+            write_backward: false,
+
             #[cfg(feature = "linux-4.1")]
             clockid: None,
             precise_ip: SampleIpSkid::Arbitrary,
@@ -17,7 +17,11 @@ mod extra_record;
 mod new;
 mod sample_record_fields;
 
+#[cfg(feature = "linux-5.13")]
+use crate::infra::WrapOption;
 use crate::perf_event::PerfEventAttr;
+#[cfg(feature = "linux-5.13")]
+use crate::CorePmu;
 use crate::{Event, EventScope};
 use std::ffi::CString;
 use std::fmt::Debug;
@@ -36,7 +40,6 @@ pub enum OverflowBy {
 #[derive(Debug, Clone)]
 pub struct Config {
     // This will keep the ptr of `kprobe_func` or `uprobe_path` valid if present.
-    #[allow(dead_code)]
     kprobe_func_or_uprobe_path: Option<Rc<CString>>,
     perf_event_attr: PerfEventAttr,
 }
@@ -77,4 +80,22 @@ impl Config {
     pub const fn as_raw(&self) -> &PerfEventAttr {
         &self.perf_event_attr
     }
+
+    /// This config on the given core PMU of hybrid CPUs, see: [`hybrid_pmus`](crate::hybrid_pmus)
+    ///
+    /// `None` if the event is not a hardware or cache event. The result should only be
+    /// sampled on `pmu.cpus`, and once per core PMU to cover all CPUs.
+    #[cfg(feature = "linux-5.13")]
+    pub fn on_pmu(&self, pmu: &CorePmu) -> Option<Self> {
+        Self {
+            perf_event_attr: self.perf_event_attr.on_pmu(pmu)?,
+            ..self.clone()
+        }
+        .wrap_some()
+    }
+
+    /// Function name of kprobe or path of uprobe, which `perf_event_attr` points to
+    pub(crate) fn kprobe_func_or_uprobe_path(&self) -> Option<&CString> {
+        self.kprobe_func_or_uprobe_path.as_deref()
+    }
 }
@@ -12,7 +12,7 @@
 // You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
 // see <https://www.gnu.org/licenses/>.
 
-use crate::sampling::record::sample::WeightRepr;
+use crate::sampling::record::sample::{BranchSampleType, RegMask, WeightRepr};
 use crate::syscall::bindings::*;
 
 /// Select the fields contained in `sample::Body`
@@ -35,8 +35,16 @@ pub struct SampleRecordFields {
 
     pub data_raw: bool, // PERF_SAMPLE_RAW
 
+    /// Wrap `branch_sample_type` with `Some` to enable this field, \
+    /// not all hardware supports this
+    ///
+    /// `branch_sample_type` must have at least one bit set, the kernel
+    /// rejects an empty filter with `EINVAL`. Privilege bits alone
+    /// (`USER`, `KERNEL`, `HV`) imply `ANY`.
+    pub branch_stack: Option<BranchSampleType>, // PERF_SAMPLE_BRANCH_STACK
+
     /// Wrap `sample_regs_user` with `Some` to enable this field
-    pub abi_and_regs_user: Option<u64>, // PERF_SAMPLE_REGS_USER
+    pub abi_and_regs_user: Option<RegMask>, // PERF_SAMPLE_REGS_USER
 
     /// Wrap `sample_stack_user` with `Some` to enable this field, \
     /// `sample_stack_user` must be `n * 8` in value
@@ -68,7 +76,7 @@ pub struct SampleRecordFields {
 
     /// Wrap `sample_regs_intr` with `Some` to enable this field
     #[cfg(feature = "linux-3.19")]
-    pub abi_and_regs_intr: Option<u64>, // PERF_SAMPLE_REGS_INTR
+    pub abi_and_regs_intr: Option<RegMask>, // PERF_SAMPLE_REGS_INTR
 
     // The `PERF_RECORD_KSYMBOL` was first added to the Linux kernel in 4.14
     // the man documentation incorrectly says "since Linux 4.13"
@@ -119,7 +127,7 @@ impl SampleRecordFields {
             self.v                          , PERF_SAMPLE_READ
             self.ips.is_some()              , PERF_SAMPLE_CALLCHAIN
             self.data_raw                   , PERF_SAMPLE_RAW
-            // TODO: Not all hw supports PERF_SAMPLE_BRANCH_STACK
+            self.branch_stack.is_some()     , PERF_SAMPLE_BRANCH_STACK
             self.abi_and_regs_user.is_some(), PERF_SAMPLE_REGS_USER
             self.data_stack_user.is_some()  , PERF_SAMPLE_STACK_USER
             matches!(self.weight, Some(WeightRepr::Full)), PERF_SAMPLE_WEIGHT
@@ -0,0 +1,238 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+#[cfg(test)]
+mod tests;
+
+use crate::config;
+use crate::config::{Cpu, Error, Process};
+use crate::infra::WrapResult;
+use crate::sampling::record::Record;
+use crate::sampling::{file_stat, wait_fd, Config, Pollable, Readiness, Sampler, SamplerStat};
+use crate::syscall::bindings::*;
+use crate::syscall::{epoll_create_wrapped, epoll_ctl_wrapped, ioctl_wrapped};
+use crate::topology::online_cpus;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io;
+use std::os::fd::{AsRawFd, OwnedFd, RawFd};
+use std::sync::atomic::AtomicU64;
+use std::sync::Arc;
+use std::time::Duration;
+
+/*
+The kernel can not enable events on different CPUs at once, so the set
+enables them one by one and rolls back on failure. The samplers are also
+registered to an epoll fd, which makes the whole set one pollable handle
+for `Poller`, `RecordStream`, `Drainer` and `Merger`.
+*/
+
+/// Enable, disable and read stats of the samplers of [`CpuSamplerSet`],
+/// which keeps working after the set is moved into a poller or merger.
+#[derive(Clone)]
+pub struct CpuSetControl {
+    /// Map of `cpu` -> duplicate fd of its sampler
+    files: Arc<BTreeMap<u32, File>>,
+    /// Map of `cpu` -> `dropped_bytes` of its sampler
+    dropped_bytes: Arc<BTreeMap<u32, Arc<AtomicU64>>>,
+}
+
+impl CpuSetControl {
+    /// Enable all samplers one by one, and disable the enabled ones if any fails.
+    /// This is not atomic: the samplers start at different times, and the ones
+    /// enabled before a failure may have written records before being disabled.
+    pub fn enable(&self) -> io::Result<()> {
+        for (i, file) in self.files.values().enumerate() {
+            if let Err(e) = ioctl_wrapped::<()>(file, PERF_EVENT_IOCTL_ENABLE, None) {
+                for file in self.files.values().take(i) {
+                    let _ = ioctl_wrapped::<()>(file, PERF_EVENT_IOCTL_DISABLE, None);
+                }
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Disable all samplers, returns the first error after trying all of them.
+    pub fn disable(&self) -> io::Result<()> {
+        self.each(PERF_EVENT_IOCTL_DISABLE)
+    }
+
+    pub fn reset(&self) -> io::Result<()> {
+        self.each(PERF_EVENT_IOCTL_RESET)
+    }
+
+    fn each(&self, request: impl Into<u64> + Copy) -> io::Result<()> {
+        let mut result = Ok(());
+        for file in self.files.values() {
+            let it = ioctl_wrapped::<()>(file, request, None);
+            if result.is_ok() {
+                result = it;
+            }
+        }
+        result
+    }
+
+    /// Map of `cpu` -> stat of its sampler
+    pub fn stat(&self) -> io::Result<BTreeMap<u32, SamplerStat>> {
+        self.files
+            .iter()
+            .map(|(cpu, file)| Ok((*cpu, file_stat(file, &self.dropped_bytes[cpu])?)))
+            .collect()
+    }
+}
+
+/// One sampler with its own ring buffer per CPU, for all processes by default.
+pub struct CpuSamplerSet {
+    samplers: BTreeMap<u32, Sampler>,
+    control: CpuSetControl,
+    epoll: OwnedFd,
+    /// CPU to read first in `next_record`, so every CPU is read in turn
+    next_cpu: u32,
+}
+
+impl CpuSamplerSet {
+    /// Open the config on every online CPU.
+    pub fn new(mmap_pages: usize, cfg: &Config) -> config::Result<Self> {
+        let cpus = online_cpus().map_err(Error::SyscallFailed)?;
+        Self::with_cpus(cpus, mmap_pages, cfg)
+    }
+
+    /// Open the config on the given CPUs, e.g. from `topology::parse_cpu_list`.
+    pub fn with_cpus(
+        cpus: impl IntoIterator<Item = u32>,
+        mmap_pages: usize,
+        cfg: &Config,
+    ) -> config::Result<Self> {
+        Self::for_process(&Process::Any, cpus, mmap_pages, cfg)
+    }
+
+    /// Like `with_cpus`, but only for the given process.
+    pub fn for_process(
+        process: &Process,
+        cpus: impl IntoIterator<Item = u32>,
+        mmap_pages: usize,
+        cfg: &Config,
+    ) -> config::Result<Self> {
+        let epoll = epoll_create_wrapped().map_err(Error::SyscallFailed)?;
+        let mut samplers = BTreeMap::new();
+        let mut files = BTreeMap::new();
+        let mut dropped_bytes = BTreeMap::new();
+        for cpu in cpus {
+            let sampler = Sampler::new(process, &Cpu::Id(cpu), mmap_pages, cfg)?;
+            let events = (libc::EPOLLIN | libc::EPOLLHUP) as u32;
+            epoll_ctl_wrapped(
+                &epoll,
+                libc::EPOLL_CTL_ADD,
+                sampler.as_raw_fd(),
+                events,
+                cpu as _,
+            )
+            .map_err(Error::SyscallFailed)?;
+            files.insert(cpu, sampler.file.try_clone().map_err(Error::SyscallFailed)?);
+            dropped_bytes.insert(cpu, sampler.dropped_bytes.clone());
+            samplers.insert(cpu, sampler);
+        }
+
+        Self {
+            samplers,
+            control: CpuSetControl {
+                files: Arc::new(files),
+                dropped_bytes: Arc::new(dropped_bytes),
+            },
+            epoll,
+            next_cpu: 0,
+        }
+        .wrap_ok()
+    }
+
+    pub fn control(&self) -> CpuSetControl {
+        self.control.clone()
+    }
+
+    /// Enable all samplers one by one, and disable the enabled ones if any fails.
+    /// This is not atomic: the samplers start at different times, and the ones
+    /// enabled before a failure may have written records before being disabled.
+    pub fn enable(&self) -> io::Result<()> {
+        self.control.enable()
+    }
+
+    /// Disable all samplers, returns the first error after trying all of them.
+    pub fn disable(&self) -> io::Result<()> {
+        self.control.disable()
+    }
+
+    pub fn reset(&self) -> io::Result<()> {
+        self.control.reset()
+    }
+
+    /// Map of `cpu` -> stat of its sampler
+    pub fn stat(&self) -> io::Result<BTreeMap<u32, SamplerStat>> {
+        self.control.stat()
+    }
+
+    /// Map of `cpu` -> sampler on it
+    pub const fn samplers(&self) -> &BTreeMap<u32, Sampler> {
+        &self.samplers
+    }
+
+    pub fn get_mut(&mut self, cpu: u32) -> Option<&mut Sampler> {
+        self.samplers.get_mut(&cpu)
+    }
+
+    /// Read the next record of any CPU, CPUs are read in turn.
+    pub fn next_record(&mut self) -> Option<(u32, Record)> {
+        fn first<'a>(
+            mut samplers: impl Iterator<Item = (&'a u32, &'a mut Sampler)>,
+        ) -> Option<(u32, Record)> {
+            samplers.find_map(|(cpu, it)| it.next_record().map(|it| (*cpu, it)))
+        }
+
+        let next_cpu = self.next_cpu;
+        let (cpu, record) = match first(self.samplers.range_mut(next_cpu..)) {
+            Some(it) => it,
+            None => first(self.samplers.range_mut(..next_cpu))?,
+        };
+        self.next_cpu = cpu + 1;
+        Some((cpu, record))
+    }
+
+    /// Read all records of all CPUs.
+    pub fn drain(&mut self) -> Vec<(u32, Record)> {
+        self.samplers
+            .iter_mut()
+            .flat_map(|(cpu, it)| std::iter::from_fn(|| it.next_record()).map(|it| (*cpu, it)))
+            .collect()
+    }
+
+    /// Wait until any CPU is readable, `None` waits forever.
+    pub fn wait(&self, timeout: Option<Duration>) -> io::Result<Readiness> {
+        wait_fd(self.epoll.as_raw_fd(), timeout)
+    }
+}
+
+impl AsRawFd for CpuSamplerSet {
+    fn as_raw_fd(&self) -> RawFd {
+        self.epoll.as_raw_fd()
+    }
+}
+
+/// The CPU of each record is dropped, so it is unknown to consumers of
+/// `Poller`, `Drainer` or `RecordStream`. Enable `SampleRecordFields::cpu`
+/// (with `ExtraConfig::extra_record_with_sample_id` for non-sample records)
+/// to find it in the records.
+impl Pollable for CpuSamplerSet {
+    fn next_record(&mut self) -> Option<Record> {
+        self.next_record().map(|(_, it)| it)
+    }
+}
@@ -0,0 +1,133 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+use crate::sampling::record::RecordBody;
+use crate::sampling::{
+    Config, CpuSamplerSet, ExtraConfig, Merger, OverflowBy, Poller, SampleRecordFields,
+};
+use crate::topology::online_cpus;
+use crate::{Event, EventScope, SoftwareEvent};
+use std::time::{Duration, Instant};
+
+fn gen_cfg() -> Config {
+    let ev = Event::from(SoftwareEvent::CpuClock);
+    let overflow_by = OverflowBy::Period(100_000);
+    let extra_config = ExtraConfig {
+        sample_record_fields: SampleRecordFields {
+            cpu: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    Merger::config(&ev, &EventScope::all(), &overflow_by, &extra_config)
+}
+
+fn workload() {
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_millis(20) {
+        std::hint::black_box(rand::random::<u64>());
+    }
+}
+
+#[test]
+fn test_cpu_set() {
+    let cpus = online_cpus().unwrap();
+    let mmap_pages = 1 + 16;
+    let mut set = CpuSamplerSet::new(mmap_pages, &gen_cfg()).unwrap();
+    assert!(set.samplers().keys().copied().eq(cpus.iter().copied()));
+
+    set.enable().unwrap();
+    workload();
+    set.disable().unwrap();
+
+    let records = set.drain();
+    assert!(!records.is_empty());
+    for (cpu, record) in &records {
+        if let RecordBody::Sample(body) = &record.body {
+            assert_eq!(body.cpu, Some(*cpu));
+        }
+    }
+    assert!(set.next_record().is_none());
+
+    let stat = set.stat().unwrap();
+    assert!(stat.keys().copied().eq(cpus.iter().copied()));
+    assert!(stat.values().any(|it| it.event_count > 0));
+    #[cfg(feature = "linux-6.0")]
+    assert!(stat.values().all(|it| it.event_lost == 0));
+}
+
+#[test]
+fn test_next_record_in_turn() {
+    let cpus = online_cpus().unwrap();
+    let mmap_pages = 1 + 16;
+    let mut set = CpuSamplerSet::new(mmap_pages, &gen_cfg()).unwrap();
+    set.enable().unwrap();
+    workload();
+    set.disable().unwrap();
+
+    let records: Vec<_> = std::iter::from_fn(|| set.next_record()).collect();
+    let busy = cpus
+        .iter()
+        .filter(|cpu| records.iter().any(|(it, _)| it == *cpu))
+        .count();
+    // the first records are of different CPUs
+    let first: Vec<_> = records.iter().take(busy).map(|(cpu, _)| cpu).collect();
+    assert!(first.windows(2).all(|it| it[0] < it[1]));
+}
+
+#[test]
+fn test_poller() {
+    let mmap_pages = 1 + 16;
+    let set = CpuSamplerSet::new(mmap_pages, &gen_cfg()).unwrap();
+    let control = set.control();
+    let mut poller = Poller::new().unwrap();
+    let handle_id = poller.add(set).unwrap();
+
+    control.enable().unwrap();
+    workload();
+    control.disable().unwrap();
+    let ready = poller.wait(Some(Duration::from_secs(10))).unwrap();
+    assert_eq!(ready.len(), 1);
+    assert_eq!(ready[0].0, handle_id);
+    assert!(ready[0].1.readable);
+
+    let batches = poller.drain();
+    assert_eq!(batches.len(), 1);
+    assert!(!batches[0].records.is_empty());
+}
+
+#[test]
+fn test_merger() {
+    let mmap_pages = 1 + 16;
+    let set = CpuSamplerSet::new(mmap_pages, &gen_cfg()).unwrap();
+    let control = set.control();
+    let mut merger = Merger::new(Duration::ZERO);
+    merger.add(set);
+
+    control.enable().unwrap();
+    workload();
+    let mut merged = merger.round();
+    workload();
+    control.disable().unwrap();
+    merged.extend(merger.round());
+    merged.extend(merger.flush());
+
+    assert!(!merged.is_empty());
+    assert!(merged.iter().all(|it| !it.out_of_order));
+    assert!(merged.windows(2).all(|it| it[0].time <= it[1].time));
+    assert!(control
+        .stat()
+        .unwrap()
+        .values()
+        .any(|it| it.event_count > 0));
+}
@@ -0,0 +1,254 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+#[cfg(test)]
+mod tests;
+
+use crate::sampling::record::RecordBody;
+use crate::sampling::{Batch, Poller};
+use std::collections::VecDeque;
+use std::io;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// What to do when the channel of [`Drainer`] is full.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum FullPolicy {
+    /// Wait for the receiver, the kernel reports records dropped meanwhile
+    /// with `PERF_RECORD_LOST` when the ring buffers are full
+    Block,
+    /// Drop the oldest batch in the channel
+    DropOldest,
+    /// Drop the new batch
+    DropNewest,
+}
+
+#[derive(Debug, Clone)]
+pub struct DrainerConfig {
+    /// Max batches in the channel
+    pub capacity: usize,
+    pub policy: FullPolicy,
+    /// How long to wait for wakeups before draining all handles,
+    /// which reads records below the wakeup threshold
+    pub interval: Duration,
+}
+
+impl Default for DrainerConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 1024,
+            policy: FullPolicy::Block,
+            interval: Duration::from_millis(100),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DrainerStat {
+    /// Records read from the ring buffers
+    pub drained: u64,
+    /// Records dropped since the channel is full
+    pub dropped: u64,
+    /// Records lost by the kernel, i.e. the sum of `PERF_RECORD_LOST`
+    pub lost: u64,
+}
+
+#[derive(Default)]
+struct Shared {
+    queue: Mutex<VecDeque<Batch>>,
+    not_empty: Condvar,
+    not_full: Condvar,
+    stop: AtomicBool,
+    /// The reader thread exited
+    closed: AtomicBool,
+    drained: AtomicU64,
+    dropped: AtomicU64,
+    lost: AtomicU64,
+}
+
+impl Shared {
+    fn queue(&self) -> MutexGuard<'_, VecDeque<Batch>> {
+        self.queue.lock().unwrap()
+    }
+}
+
+/// Drain the handles of a [`Poller`] in a background thread,
+/// and deliver the records in batches through a bounded channel.
+///
+/// Handles hung up are removed after their last batch,
+/// the reader thread ends once there are no handles left.
+pub struct Drainer {
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<io::Result<Poller>>>,
+}
+
+impl Drainer {
+    pub fn new(poller: Poller, cfg: DrainerConfig) -> io::Result<Self> {
+        let shared = Arc::new(Shared::default());
+        let thread = thread::Builder::new()
+            .name("perf-event-drainer".to_string())
+            .spawn({
+                let shared = shared.clone();
+                move || {
+                    let result = run(poller, &cfg, &shared);
+                    shared.closed.store(true, Ordering::Release);
+                    // the receiver either saw `closed` or is waiting once the lock is taken
+                    drop(shared.queue());
+                    shared.not_empty.notify_all();
+                    result
+                }
+            })?;
+
+        Ok(Self {
+            shared,
+            thread: Some(thread),
+        })
+    }
+
+    /// Wait for the next batch, `None` if the drainer is stopped or every handle has hung up,
+    /// and the channel is empty.
+    pub fn recv(&self) -> Option<Batch> {
+        self.recv_until(None)
+    }
+
+    /// Like `recv`, but also `None` if timed out.
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<Batch> {
+        self.recv_until(Some(Instant::now() + timeout))
+    }
+
+    pub fn try_recv(&self) -> Option<Batch> {
+        let batch = self.shared.queue().pop_front();
+        if batch.is_some() {
+            self.shared.not_full.notify_one();
+        }
+        batch
+    }
+
+    fn recv_until(&self, deadline: Option<Instant>) -> Option<Batch> {
+        let mut queue = self.shared.queue();
+        loop {
+            if let Some(batch) = queue.pop_front() {
+                self.shared.not_full.notify_one();
+                return Some(batch);
+            }
+            if self.shared.closed.load(Ordering::Acquire) {
+                return None;
+            }
+            queue = match deadline {
+                None => self.shared.not_empty.wait(queue).unwrap(),
+                Some(deadline) => {
+                    let timeout = deadline.saturating_duration_since(Instant::now());
+                    if timeout.is_zero() {
+                        return None;
+                    }
+                    self.shared
+                        .not_empty
+                        .wait_timeout(queue, timeout)
+                        .unwrap()
+                        .0
+                }
+            };
+        }
+    }
+
+    pub fn stat(&self) -> DrainerStat {
+        DrainerStat {
+            drained: self.shared.drained.load(Ordering::Relaxed),
+            dropped: self.shared.dropped.load(Ordering::Relaxed),
+            lost: self.shared.lost.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Stop the reader thread and take back the poller with the remaining handles,
+    /// batches already in the channel are dropped.
+    pub fn stop(mut self) -> io::Result<Poller> {
+        self.stop_thread().unwrap()
+    }
+
+    fn stop_thread(&mut self) -> Option<io::Result<Poller>> {
+        let thread = self.thread.take()?;
+        self.shared.stop.store(true, Ordering::Release);
+        drop(self.shared.queue());
+        self.shared.not_full.notify_all();
+        thread.join().unwrap().into()
+    }
+}
+
+impl Drop for Drainer {
+    fn drop(&mut self) {
+        let _ = self.stop_thread();
+    }
+}
+
+fn run(mut poller: Poller, cfg: &DrainerConfig, shared: &Shared) -> io::Result<Poller> {
+    // the receiver sees the end once every handle has hung up
+    while !shared.stop.load(Ordering::Acquire) && !poller.is_empty() {
+        let mut batches = poller.poll(Some(cfg.interval))?;
+        if batches.is_empty() {
+            batches = poller.drain();
+        }
+        for batch in batches {
+            if batch.hup {
+                poller.remove(batch.handle_id)?;
+            }
+            send(batch, cfg, shared);
+        }
+    }
+    Ok(poller)
+}
+
+fn send(batch: Batch, cfg: &DrainerConfig, shared: &Shared) {
+    if batch.records.is_empty() {
+        return;
+    }
+    let len = batch.records.len() as u64;
+    let lost = batch
+        .records
+        .iter()
+        .map(|it| match &it.body {
+            RecordBody::Lost(body) => body.lost,
+            _ => 0,
+        })
+        .sum();
+    shared.drained.fetch_add(len, Ordering::Relaxed);
+    shared.lost.fetch_add(lost, Ordering::Relaxed);
+
+    let mut queue = shared.queue();
+    while queue.len() >= cfg.capacity.max(1) {
+        match cfg.policy {
+            FullPolicy::Block => {
+                if shared.stop.load(Ordering::Acquire) {
+                    shared.dropped.fetch_add(len, Ordering::Relaxed);
+                    return;
+                }
+                queue = shared.not_full.wait(queue).unwrap();
+            }
+            FullPolicy::DropOldest => {
+                let oldest = queue.pop_front().unwrap();
+                shared
+                    .dropped
+                    .fetch_add(oldest.records.len() as _, Ordering::Relaxed);
+            }
+            FullPolicy::DropNewest => {
+                shared.dropped.fetch_add(len, Ordering::Relaxed);
+                return;
+            }
+        }
+    }
+    queue.push_back(batch);
+    drop(queue);
+    shared.not_empty.notify_one();
+}
@@ -0,0 +1,263 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+use crate::config::{Cpu, Process};
+use crate::sampling::record::RecordBody;
+use crate::sampling::{
+    Batch, Config, Drainer, DrainerConfig, ExtraConfig, ExtraRecord, FixedSamplerGroup, FullPolicy,
+    OverflowBy, Poller, Sampler, SamplerGroup, SamplerGuard,
+};
+use crate::tracing::Tracer;
+use crate::{Event, EventScope, SoftwareEvent};
+use std::process::Command;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn gen_sampler(process: &Process, ev: SoftwareEvent, extra_config: &ExtraConfig) -> Sampler {
+    let scopes = EventScope::all();
+    let ev = Event::from(ev);
+    let overflow_by = OverflowBy::Period(10000);
+    let cfg = Config::extra_new(&ev, &scopes, &overflow_by, extra_config);
+    let mmap_pages = 1 + 1;
+    Sampler::new(process, &Cpu::Any, mmap_pages, &cfg).unwrap()
+}
+
+/// Thread which runs for the given duration on each message,
+/// so it is idle while the test thread is checking results.
+struct Worker {
+    tid: u32,
+    go: mpsc::Sender<Duration>,
+    done: mpsc::Receiver<()>,
+}
+
+impl Worker {
+    fn spawn() -> Self {
+        let (tid_tx, tid_rx) = mpsc::channel();
+        let (go, go_rx) = mpsc::channel::<Duration>();
+        let (done_tx, done) = mpsc::channel();
+        thread::spawn(move || {
+            tid_tx.send(unsafe { libc::gettid() } as u32).unwrap();
+            for duration in go_rx {
+                let start = Instant::now();
+                while start.elapsed() < duration {
+                    std::hint::black_box(rand::random::<u64>());
+                }
+                done_tx.send(()).unwrap();
+            }
+        });
+        let tid = tid_rx.recv().unwrap();
+        Self { tid, go, done }
+    }
+
+    fn run(&self, duration: Duration) {
+        self.go.send(duration).unwrap();
+        self.done.recv().unwrap();
+    }
+}
+
+fn gen_poller(worker: &Worker, samplers: usize) -> Poller {
+    let mut poller = Poller::new().unwrap();
+    for _ in 0..samplers {
+        let process = Process::Pid(worker.tid);
+        let sampler = gen_sampler(&process, SoftwareEvent::CpuClock, &Default::default());
+        sampler.enable().unwrap();
+        poller.add(sampler).unwrap();
+    }
+    poller
+}
+
+fn recv_all(drainer: &Drainer) -> Vec<Batch> {
+    std::iter::from_fn(|| drainer.try_recv()).collect()
+}
+
+fn records_len(batches: &[Batch]) -> u64 {
+    batches.iter().map(|it| it.records.len() as u64).sum()
+}
+
+#[test]
+fn test_send() {
+    fn assert_send<T: Send>() {}
+    assert_send::<Sampler>();
+    assert_send::<SamplerGroup>();
+    assert_send::<FixedSamplerGroup>();
+    assert_send::<SamplerGuard>();
+    assert_send::<Tracer>();
+    assert_send::<Poller>();
+    assert_send::<Drainer>();
+}
+
+/// The poller is opened on this thread, drained on another one and received on a third one.
+#[test]
+fn test_cross_thread() {
+    let worker = Worker::spawn();
+    let poller = gen_poller(&worker, 2);
+    let cfg = DrainerConfig {
+        capacity: 1,
+        policy: FullPolicy::DropOldest,
+        interval: Duration::from_millis(1),
+    };
+    let drainer = thread::spawn(move || Drainer::new(poller, cfg).unwrap())
+        .join()
+        .unwrap();
+
+    let (done_tx, done_rx) = mpsc::channel();
+    let receiver = thread::spawn(move || {
+        done_rx.recv().unwrap();
+        thread::sleep(Duration::from_millis(300));
+        let batches = recv_all(&drainer);
+        let stat = drainer.stat();
+        (batches, stat, drainer.stop().unwrap())
+    });
+    for _ in 0..10 {
+        worker.run(Duration::from_millis(10));
+        thread::sleep(Duration::from_millis(10));
+    }
+    done_tx.send(()).unwrap();
+    let (batches, stat, poller) = receiver.join().unwrap();
+
+    assert_eq!(batches.len(), 1);
+    assert!(stat.dropped > 0);
+    assert_eq!(stat.drained, records_len(&batches) + stat.dropped);
+    assert_eq!(poller.len(), 2);
+}
+
+#[test]
+fn test_drainer() {
+    let worker = Worker::spawn();
+    let drainer = Drainer::new(gen_poller(&worker, 2), Default::default()).unwrap();
+
+    worker.run(Duration::from_millis(10));
+    let mut batches = vec![];
+    while batches.iter().map(|it: &Batch| it.handle_id).max() != Some(1) {
+        batches.push(drainer.recv_timeout(Duration::from_secs(10)).unwrap());
+    }
+    thread::sleep(Duration::from_millis(300));
+    batches.extend(recv_all(&drainer));
+
+    let stat = drainer.stat();
+    assert_eq!(stat.drained, records_len(&batches));
+    assert_eq!(stat.dropped, 0);
+    assert!(batches.iter().all(|it| !it.hup && !it.records.is_empty()));
+
+    let poller = drainer.stop().unwrap();
+    assert_eq!(poller.len(), 2);
+}
+
+#[test]
+fn test_drop_policies() {
+    for policy in [FullPolicy::DropOldest, FullPolicy::DropNewest] {
+        let cfg = DrainerConfig {
+            capacity: 1,
+            policy,
+            interval: Duration::from_millis(1),
+        };
+        let worker = Worker::spawn();
+        let drainer = Drainer::new(gen_poller(&worker, 1), cfg).unwrap();
+
+        for _ in 0..10 {
+            worker.run(Duration::from_millis(10));
+            thread::sleep(Duration::from_millis(10));
+        }
+        thread::sleep(Duration::from_millis(300));
+        let batches = recv_all(&drainer);
+
+        let stat = drainer.stat();
+        assert_eq!(batches.len(), 1);
+        assert!(stat.dropped > 0);
+        assert_eq!(stat.drained, records_len(&batches) + stat.dropped);
+    }
+}
+
+/// Blocking leaves the records in the ring buffers, where the kernel drops them.
+#[test]
+fn test_block_policy() {
+    let cfg = DrainerConfig {
+        capacity: 1,
+        policy: FullPolicy::Block,
+        interval: Duration::from_millis(1),
+    };
+    let worker = Worker::spawn();
+    let drainer = Drainer::new(gen_poller(&worker, 1), cfg).unwrap();
+
+    for _ in 0..10 {
+        worker.run(Duration::from_millis(10));
+        thread::sleep(Duration::from_millis(10));
+    }
+    let mut batches = vec![];
+    while let Some(batch) = drainer.recv_timeout(Duration::from_millis(300)) {
+        batches.push(batch);
+    }
+    // the kernel writes `PERF_RECORD_LOST` with the next record when there is space
+    worker.run(Duration::from_millis(1));
+    while let Some(batch) = drainer.recv_timeout(Duration::from_millis(300)) {
+        batches.push(batch);
+    }
+
+    let stat = drainer.stat();
+    assert_eq!(stat.dropped, 0);
+    assert_eq!(stat.drained, records_len(&batches));
+    assert!(stat.lost > 0);
+    let lost: u64 = batches
+        .iter()
+        .flat_map(|it| &it.records)
+        .map(|it| match &it.body {
+            RecordBody::Lost(body) => body.lost,
+            _ => 0,
+        })
+        .sum();
+    assert_eq!(stat.lost, lost);
+}
+
+#[test]
+fn test_drainer_hup() {
+    let mut child = Command::new("sleep").arg("0.1").spawn().unwrap();
+    let extra_config = ExtraConfig {
+        extra_record_types: vec![ExtraRecord::ForkAndExit],
+        ..Default::default()
+    };
+    let sampler = gen_sampler(
+        &Process::Pid(child.id()),
+        SoftwareEvent::Dummy,
+        &extra_config,
+    );
+    sampler.enable().unwrap();
+    let mut poller = Poller::new().unwrap();
+    poller.add(sampler).unwrap();
+    let drainer = Drainer::new(poller, Default::default()).unwrap();
+
+    let mut exited = false;
+    loop {
+        let batch = drainer.recv_timeout(Duration::from_secs(10)).unwrap();
+        exited |= batch
+            .records
+            .iter()
+            .any(|it| matches!(it.body, RecordBody::Exit(_)));
+        if batch.hup {
+            break;
+        }
+    }
+    child.wait().unwrap();
+
+    assert!(exited);
+    // ends after the only handle hung up
+    assert!(drainer.recv().is_none());
+    assert!(drainer.stop().unwrap().is_empty());
+}
+
+#[test]
+fn test_drainer_empty() {
+    let drainer = Drainer::new(Poller::new().unwrap(), Default::default()).unwrap();
+    assert!(drainer.recv().is_none());
+    assert!(drainer.stop().unwrap().is_empty());
+}
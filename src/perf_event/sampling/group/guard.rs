@@ -17,6 +17,8 @@ use crate::sampling::group::inner::Inner;
 use crate::sampling::record::Record;
 use crate::sampling::SamplerStat;
 use std::io;
+use std::os::fd::{AsRawFd, RawFd};
+use std::sync::atomic::Ordering;
 use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
 
 pub struct SamplerGuard {
@@ -46,7 +48,12 @@ impl SamplerGuard {
     }
 
     pub fn stat(&mut self) -> io::Result<SamplerStat> {
-        let result = self.as_inner_mut().stat()?;
+        let (result, dropped_bytes) = {
+            let mut inner = self.as_inner_mut();
+            let dropped_bytes = &inner.members[&self.event_id].dropped_bytes;
+            let dropped_bytes = dropped_bytes.load(Ordering::Relaxed);
+            (inner.stat()?, dropped_bytes)
+        };
         let member_count = result.member_count(self)?;
         SamplerStat {
             event_id: self.event_id,
@@ -55,6 +62,7 @@ impl SamplerGuard {
             event_lost: member_count.event_lost,
             time_enabled: result.time_enabled,
             time_running: result.time_running,
+            dropped_bytes,
         }
         .wrap_ok()
     }
@@ -67,3 +75,9 @@ impl Iterator for SamplerGuard {
         self.next_record()
     }
 }
+
+impl AsRawFd for SamplerGuard {
+    fn as_raw_fd(&self) -> RawFd {
+        self.as_inner().members[&self.event_id].as_raw_fd()
+    }
+}
@@ -19,7 +19,6 @@ use crate::sampling::{Sampler, SamplerGroupStat};
 use crate::syscall::bindings::*;
 use crate::syscall::{ioctl_wrapped, perf_event_open_wrapped};
 use libc::pid_t;
-use memmap2::MmapOptions;
 use std::collections::HashMap;
 use std::fs::File;
 use std::io;
@@ -59,26 +58,7 @@ impl Inner {
         let group_fd = self.leader().map(|it| it.file.as_raw_fd()).unwrap_or(-1);
         let fd = unsafe { perf_event_open_wrapped(perf_event_attr, pid, cpu, group_fd, 0) }?;
         let file = unsafe { File::from_raw_fd(fd) };
-        let mmap = unsafe {
-            MmapOptions::new()
-                .len(page_size::get() * mmap_pages)
-                .map_mut(&file)
-        }
-        .unwrap();
-
-        let page_size = page_size::get();
-
-        let member = Sampler {
-            mmap,
-            file,
-            data_size: ((mmap_pages - 1) * page_size) as _,
-            data_offset: page_size as _,
-            sample_type: perf_event_attr.sample_type,
-            sample_id_all: perf_event_attr.sample_id_all() > 0,
-            regs_user_len: perf_event_attr.sample_regs_user.count_ones() as _,
-            #[cfg(feature = "linux-3.19")]
-            regs_intr_len: perf_event_attr.sample_regs_intr.count_ones() as _,
-        };
+        let member = Sampler::from_file(file, mmap_pages, perf_event_attr)?;
 
         let event_id = member.event_id()?;
         if self.leader_event_id.is_none() {
@@ -19,7 +19,7 @@ use std::collections::HashMap;
 use std::io::{Error, ErrorKind, Read};
 use std::{io, slice};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct SamplerGroupStat {
     pub time_enabled: u64,
     pub time_running: u64,
@@ -27,7 +27,7 @@ pub struct SamplerGroupStat {
     pub member_counts: HashMap<u64, MemberCount>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct MemberCount {
     pub event_count: u64,
     #[cfg(feature = "linux-6.0")]
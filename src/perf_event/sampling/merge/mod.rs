@@ -0,0 +1,214 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+#[cfg(test)]
+mod tests;
+
+use crate::sampling::record::Record;
+use crate::sampling::{Config, ExtraConfig, OverflowBy, Pollable};
+use crate::{Event, EventScope};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::time::Duration;
+
+/*
+Records of one ring buffer are in time order, but a record read from one
+source may be older than records already read from another. Like the ordered
+events queue of perf, each round drains every source into a heap, and only
+records up to the newest time of the previous round are emitted, since any
+record written before that round has been read by now. The window further
+holds records back until they are older than the newest record by it.
+*/
+
+/// Record emitted by [`Merger`] in time order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Merged {
+    /// Index of the source returned by [`Merger::add`]
+    pub source: usize,
+    /// Time of the record, or of the previous record of its source if it has no time
+    pub time: u64,
+    pub record: Record,
+    /// Older than records already emitted, i.e. arrived after its window had passed
+    pub out_of_order: bool,
+}
+
+struct Queued {
+    time: u64,
+    /// Arrival order, keeps records of the same time in reading order
+    seq: u64,
+    source: usize,
+    record: Record,
+}
+
+impl PartialEq for Queued {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Queued {}
+
+impl PartialOrd for Queued {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Queued {
+    // Reversed, so `BinaryHeap` pops the oldest first
+    fn cmp(&self, other: &Self) -> Ordering {
+        (other.time, other.seq).cmp(&(self.time, self.seq))
+    }
+}
+
+/// Merge records of many samplers, tracers or group members in time order.
+///
+/// Sources should be built with [`Merger::config`] or have `PERF_SAMPLE_TIME`
+/// and `sample_id_all` enabled, and use the same clock.
+pub struct Merger {
+    sources: Vec<Box<dyn Pollable>>,
+    /// Time of the last timed record of each source
+    last_times: Vec<Option<u64>>,
+    heap: BinaryHeap<Queued>,
+    next_seq: u64,
+    window: u64,
+    /// Newest time read so far
+    newest: Option<u64>,
+    /// Newest time read up to the previous round
+    prev_round: Option<u64>,
+    /// Time of the last emitted record
+    emitted: Option<u64>,
+    out_of_order: u64,
+}
+
+impl Merger {
+    /// Records are held back until they are older than the newest record by `window`.
+    pub fn new(window: Duration) -> Self {
+        Self {
+            sources: vec![],
+            last_times: vec![],
+            heap: BinaryHeap::new(),
+            next_seq: 0,
+            window: window.as_nanos().min(u64::MAX as _) as _,
+            newest: None,
+            prev_round: None,
+            emitted: None,
+            out_of_order: 0,
+        }
+    }
+
+    /// Build a sampling config with `PERF_SAMPLE_TIME` and `sample_id_all` enabled.
+    pub fn config<'t>(
+        event: &Event,
+        scopes: impl IntoIterator<Item = &'t EventScope>,
+        overflow_by: &OverflowBy,
+        extra_config: &ExtraConfig,
+    ) -> Config {
+        let mut extra_config = extra_config.clone();
+        extra_config.sample_record_fields.time = true;
+        extra_config.extra_record_with_sample_id = true;
+        Config::extra_new(event, scopes, overflow_by, &extra_config)
+    }
+
+    /// Register the source, returns its index.
+    pub fn add(&mut self, source: impl Pollable + 'static) -> usize {
+        self.sources.push(Box::new(source));
+        self.last_times.push(None);
+        self.sources.len() - 1
+    }
+
+    pub fn get_mut(&mut self, source: usize) -> Option<&mut dyn Pollable> {
+        self.sources.get_mut(source).map(|it| &mut **it as _)
+    }
+
+    pub fn len(&self) -> usize {
+        self.sources.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty()
+    }
+
+    /// Records read but not emitted yet.
+    pub fn pending(&self) -> usize {
+        self.heap.len()
+    }
+
+    /// Records emitted out of order so far.
+    pub const fn out_of_order(&self) -> u64 {
+        self.out_of_order
+    }
+
+    /// Queue a record of the source, without reading the sources.
+    pub fn push(&mut self, source: usize, record: Record) {
+        let time = record.time();
+        if time.is_some() {
+            self.last_times[source] = time;
+            self.newest = self.newest.max(time);
+        }
+        let time = time
+            .or(self.last_times[source])
+            .or(self.emitted)
+            .unwrap_or(0);
+        self.heap.push(Queued {
+            time,
+            seq: self.next_seq,
+            source,
+            record,
+        });
+        self.next_seq += 1;
+    }
+
+    /// Drain every source, and emit records which can no longer be preceded.
+    pub fn round(&mut self) -> Vec<Merged> {
+        for source in 0..self.sources.len() {
+            while let Some(record) = self.sources[source].next_record() {
+                self.push(source, record);
+            }
+        }
+        let limit = self.newest.map(|it| it.saturating_sub(self.window));
+        let limit = self.prev_round.min(limit);
+        let merged = self.emit(|it| limit.is_some_and(|limit| it.time <= limit));
+        self.prev_round = self.newest;
+        merged
+    }
+
+    /// Emit all queued records, e.g. after the sources are disabled and drained.
+    pub fn flush(&mut self) -> Vec<Merged> {
+        let merged = self.emit(|_| true);
+        self.prev_round = self.newest;
+        merged
+    }
+
+    fn emit(&mut self, ready: impl Fn(&Queued) -> bool) -> Vec<Merged> {
+        let mut merged = vec![];
+        while self.heap.peek().is_some_and(&ready) {
+            let Some(it) = self.heap.pop() else {
+                break;
+            };
+            let out_of_order = self.emitted.is_some_and(|emitted| it.time < emitted);
+            if out_of_order {
+                self.out_of_order += 1;
+            } else {
+                self.emitted = Some(it.time);
+            }
+            merged.push(Merged {
+                source: it.source,
+                time: it.time,
+                record: it.record,
+                out_of_order,
+            });
+        }
+        merged
+    }
+}
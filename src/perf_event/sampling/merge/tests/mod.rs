@@ -0,0 +1,165 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+use crate::config::{Cpu, Process};
+use crate::sampling::record::{throttle, Record, RecordBody};
+use crate::sampling::{Merged, Merger, OverflowBy, Pollable, Sampler};
+use crate::{Event, EventScope, SoftwareEvent};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::os::fd::{AsRawFd, RawFd};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn gen_sampler(tid: u32, ev: SoftwareEvent) -> Sampler {
+    let ev = Event::from(ev);
+    let overflow_by = OverflowBy::Period(100_000);
+    let cfg = Merger::config(&ev, &EventScope::all(), &overflow_by, &Default::default());
+    let mmap_pages = 1 + 16;
+    Sampler::new(&Process::Pid(tid), &Cpu::Any, mmap_pages, &cfg).unwrap()
+}
+
+/// Source of the records pushed into the shared queue.
+struct Fake {
+    records: Arc<Mutex<VecDeque<Record>>>,
+    file: File,
+}
+
+impl AsRawFd for Fake {
+    fn as_raw_fd(&self) -> RawFd {
+        self.file.as_raw_fd()
+    }
+}
+
+impl Pollable for Fake {
+    fn next_record(&mut self) -> Option<Record> {
+        self.records.lock().unwrap().pop_front()
+    }
+}
+
+fn add_fake(merger: &mut Merger) -> (usize, Arc<Mutex<VecDeque<Record>>>) {
+    let records = Arc::new(Mutex::new(VecDeque::new()));
+    let fake = Fake {
+        records: records.clone(),
+        file: File::open("/dev/null").unwrap(),
+    };
+    (merger.add(fake), records)
+}
+
+fn gen_record(time: u64) -> Record {
+    let body = throttle::Body {
+        time,
+        id: 0,
+        stream_id: 0,
+        sample_id: None,
+    };
+    Record {
+        misc: 0,
+        body: RecordBody::Throttle(Box::new(body)),
+    }
+}
+
+fn feed(records: &Mutex<VecDeque<Record>>, times: &[u64]) {
+    let mut records = records.lock().unwrap();
+    records.extend(times.iter().copied().map(gen_record));
+}
+
+fn times(merged: &[Merged]) -> Vec<(usize, u64, bool)> {
+    merged
+        .iter()
+        .map(|it| (it.source, it.time, it.out_of_order))
+        .collect()
+}
+
+#[test]
+fn test_rounds() {
+    let mut merger = Merger::new(Duration::ZERO);
+    let (a, a_records) = add_fake(&mut merger);
+    let (b, b_records) = add_fake(&mut merger);
+
+    // nothing is emitted before a full round has passed
+    feed(&a_records, &[10, 20]);
+    assert!(merger.round().is_empty());
+    assert_eq!(merger.pending(), 2);
+
+    feed(&b_records, &[15, 30]);
+    let merged = merger.round();
+    assert_eq!(
+        times(&merged),
+        vec![(a, 10, false), (b, 15, false), (a, 20, false)]
+    );
+
+    // older than emitted records
+    feed(&a_records, &[5]);
+    let merged = merger.round();
+    assert_eq!(times(&merged), vec![(a, 5, true), (b, 30, false)]);
+    assert_eq!(merger.out_of_order(), 1);
+    assert_eq!(merger.pending(), 0);
+}
+
+#[test]
+fn test_window() {
+    let mut merger = Merger::new(Duration::from_nanos(100));
+    let (a, a_records) = add_fake(&mut merger);
+
+    feed(&a_records, &[10, 50]);
+    assert!(merger.round().is_empty());
+    feed(&a_records, &[150]);
+    assert_eq!(times(&merger.round()), vec![(a, 10, false), (a, 50, false)]);
+    assert!(merger.round().is_empty());
+    assert_eq!(times(&merger.flush()), vec![(a, 150, false)]);
+}
+
+#[test]
+fn test_samplers() {
+    let (tid_tx, tid_rx) = mpsc::channel();
+    let (go_tx, go_rx) = mpsc::channel::<()>();
+    let worker = thread::spawn(move || {
+        tid_tx.send(unsafe { libc::gettid() } as u32).unwrap();
+        go_rx.recv().unwrap();
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_millis(50) {
+            std::hint::black_box(rand::random::<u64>());
+        }
+    });
+    let tid = tid_rx.recv().unwrap();
+
+    let mut merger = Merger::new(Duration::ZERO);
+    for ev in [SoftwareEvent::CpuClock, SoftwareEvent::TaskClock] {
+        let sampler = gen_sampler(tid, ev);
+        sampler.enable().unwrap();
+        merger.add(sampler);
+    }
+
+    go_tx.send(()).unwrap();
+    let mut merged = vec![];
+    while !worker.is_finished() {
+        merged.extend(merger.round());
+        thread::sleep(Duration::from_millis(1));
+    }
+    worker.join().unwrap();
+    merged.extend(merger.round());
+    merged.extend(merger.flush());
+
+    assert!(merged.iter().all(|it| !it.out_of_order));
+    assert!(merged.windows(2).all(|it| it[0].time <= it[1].time));
+    for source in 0..merger.len() {
+        assert!(merged.iter().any(|it| it.source == source));
+    }
+    let samples = merged
+        .iter()
+        .filter(|it| matches!(it.record.body, RecordBody::Sample(_)));
+    assert!(samples.clone().all(|it| it.record.time() == Some(it.time)));
+    assert!(samples.count() > 0);
+}
@@ -13,14 +13,32 @@
 // see <https://www.gnu.org/licenses/>.
 
 mod config;
+mod cpu_set;
+mod drainer;
 mod group;
+mod merge;
+mod poll;
+mod process;
 pub mod record;
+#[cfg(feature = "linux-3.12")]
+mod shared;
 mod single;
+#[cfg(feature = "tokio")]
+mod stream;
 
 #[allow(unused_imports)]
 pub use config::*;
+pub use cpu_set::*;
+pub use drainer::*;
 pub use group::*;
+pub use merge::*;
+pub use poll::*;
+pub use process::*;
+#[cfg(feature = "linux-3.12")]
+pub use shared::*;
 pub use single::*;
+#[cfg(feature = "tokio")]
+pub use stream::*;
 
 #[repr(C)]
 #[derive(Debug, Clone)]
@@ -0,0 +1,203 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+#[cfg(test)]
+mod tests;
+
+use crate::infra::{WrapOption, WrapResult};
+use crate::sampling::record::Record;
+use crate::sampling::{Sampler, SamplerGuard};
+use crate::syscall::{epoll_create_wrapped, epoll_ctl_wrapped, epoll_wait_wrapped, poll_wrapped};
+use crate::tracing::Tracer;
+use std::collections::HashMap;
+use std::io;
+use std::os::fd::{AsRawFd, OwnedFd, RawFd};
+use std::time::Duration;
+
+/*
+The kernel marks the event readable on each wakeup, which happens every
+`Wakeup::Events` samples or `Wakeup::Watermark` bytes, and hangs it up
+when the monitored task exits. Records below the wakeup threshold are not
+notified, read them with `next_record` after disabling the event.
+*/
+
+/// Readiness of a sampler or tracer, both are false if timed out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Readiness {
+    /// Records are available to read
+    pub readable: bool,
+    /// The monitored task exited (`POLLHUP`), remaining records can still be read
+    pub hup: bool,
+}
+
+/// `None` waits forever, otherwise rounds up to milliseconds.
+fn timeout_ms(timeout: Option<Duration>) -> i32 {
+    timeout.map_or(-1, |it| {
+        it.as_nanos().div_ceil(1_000_000).min(i32::MAX as _) as _
+    })
+}
+
+/// Wait until the fd is readable or hung up.
+pub fn wait_fd(fd: RawFd, timeout: Option<Duration>) -> io::Result<Readiness> {
+    let revents = poll_wrapped(fd, libc::POLLIN, timeout_ms(timeout))?;
+    Readiness {
+        readable: revents & libc::POLLIN != 0,
+        hup: revents & libc::POLLHUP != 0,
+    }
+    .wrap_ok()
+}
+
+/// Event that can be registered to [`Poller`].
+pub trait Pollable: AsRawFd + Send {
+    fn next_record(&mut self) -> Option<Record>;
+}
+
+impl Pollable for Sampler {
+    fn next_record(&mut self) -> Option<Record> {
+        self.next_record()
+    }
+}
+
+impl Pollable for Tracer {
+    fn next_record(&mut self) -> Option<Record> {
+        self.next_record()
+    }
+}
+
+impl Pollable for SamplerGuard {
+    fn next_record(&mut self) -> Option<Record> {
+        self.next_record()
+    }
+}
+
+/// Records drained from one handle of [`Poller`].
+#[derive(Debug)]
+pub struct Batch {
+    pub handle_id: u64,
+    pub records: Vec<Record>,
+    /// The monitored task exited, the handle should be removed
+    /// since it will be reported as hung up until then
+    pub hup: bool,
+}
+
+/// Wait for many samplers, tracers or group members in one epoll set.
+pub struct Poller {
+    epoll: OwnedFd,
+    handles: HashMap<u64, Box<dyn Pollable>>,
+    next_handle_id: u64,
+    events: Vec<libc::epoll_event>,
+}
+
+impl Poller {
+    pub fn new() -> io::Result<Self> {
+        Self {
+            epoll: epoll_create_wrapped()?,
+            handles: HashMap::new(),
+            next_handle_id: 0,
+            events: vec![],
+        }
+        .wrap_ok()
+    }
+
+    /// Register the handle, returns its id.
+    pub fn add(&mut self, handle: impl Pollable + 'static) -> io::Result<u64> {
+        let handle_id = self.next_handle_id;
+        let events = (libc::EPOLLIN | libc::EPOLLHUP) as u32;
+        epoll_ctl_wrapped(
+            &self.epoll,
+            libc::EPOLL_CTL_ADD,
+            handle.as_raw_fd(),
+            events,
+            handle_id,
+        )?;
+        self.next_handle_id += 1;
+        self.handles.insert(handle_id, Box::new(handle));
+        self.events.push(libc::epoll_event { events: 0, u64: 0 });
+        Ok(handle_id)
+    }
+
+    /// Unregister the handle, returns it if present.
+    pub fn remove(&mut self, handle_id: u64) -> io::Result<Option<Box<dyn Pollable>>> {
+        let Some(handle) = self.handles.remove(&handle_id) else {
+            return Ok(None);
+        };
+        epoll_ctl_wrapped(&self.epoll, libc::EPOLL_CTL_DEL, handle.as_raw_fd(), 0, 0)?;
+        self.events.pop();
+        Ok(Some(handle))
+    }
+
+    pub fn get_mut(&mut self, handle_id: u64) -> Option<&mut dyn Pollable> {
+        self.handles.get_mut(&handle_id).map(|it| &mut **it as _)
+    }
+
+    pub fn len(&self) -> usize {
+        self.handles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.handles.is_empty()
+    }
+
+    /// Wait until any handle is ready, returns the ready handles,
+    /// which is empty if timed out.
+    pub fn wait(&mut self, timeout: Option<Duration>) -> io::Result<Vec<(u64, Readiness)>> {
+        if self.events.is_empty() {
+            return Ok(vec![]);
+        }
+        let len = epoll_wait_wrapped(&self.epoll, &mut self.events, timeout_ms(timeout))?;
+        self.events[..len]
+            .iter()
+            .map(|it| {
+                let events = it.events as i32;
+                let readiness = Readiness {
+                    readable: events & libc::EPOLLIN != 0,
+                    hup: events & libc::EPOLLHUP != 0,
+                };
+                (it.u64, readiness)
+            })
+            .collect::<Vec<_>>()
+            .wrap_ok()
+    }
+
+    /// Drain all handles without waiting, e.g. for records below the wakeup threshold.
+    pub fn drain(&mut self) -> Vec<Batch> {
+        self.handles
+            .iter_mut()
+            .map(|(handle_id, handle)| Batch {
+                handle_id: *handle_id,
+                records: std::iter::from_fn(|| handle.next_record()).collect(),
+                hup: false,
+            })
+            .filter(|it| !it.records.is_empty())
+            .collect()
+    }
+
+    /// Wait until any handle is ready, and drain the ready handles.
+    /// A batch may be empty if its records were already read since the wakeup.
+    pub fn poll(&mut self, timeout: Option<Duration>) -> io::Result<Vec<Batch>> {
+        let ready = self.wait(timeout)?;
+        ready
+            .into_iter()
+            .filter_map(|(handle_id, readiness)| {
+                let handle = self.handles.get_mut(&handle_id)?;
+                Batch {
+                    handle_id,
+                    records: std::iter::from_fn(|| handle.next_record()).collect(),
+                    hup: readiness.hup,
+                }
+                .wrap_some()
+            })
+            .collect::<Vec<_>>()
+            .wrap_ok()
+    }
+}
@@ -0,0 +1,141 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+use crate::config::{Cpu, Process};
+use crate::sampling::record::RecordBody;
+use crate::sampling::{
+    Config, ExtraConfig, ExtraRecord, OverflowBy, Poller, Readiness, Sampler, SamplerGroup,
+};
+use crate::tracing::Tracer;
+use crate::{Event, EventScope, SoftwareEvent};
+use std::collections::HashMap;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+fn gen_cfg(ev: SoftwareEvent, extra_config: &ExtraConfig) -> Config {
+    let scopes = EventScope::all();
+    let ev = Event::from(ev);
+    let overflow_by = OverflowBy::Period(100000);
+    Config::extra_new(&ev, &scopes, &overflow_by, extra_config)
+}
+
+fn gen_sampler(process: &Process, cfg: &Config) -> Sampler {
+    let mmap_pages = 1 + 16;
+    Sampler::new(process, &Cpu::Any, mmap_pages, cfg).unwrap()
+}
+
+fn workload() {
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_millis(10) {
+        std::hint::black_box(rand::random::<u64>());
+    }
+}
+
+#[test]
+fn test_sampler_wait() {
+    let cfg = gen_cfg(SoftwareEvent::CpuClock, &Default::default());
+    let mut sampler = gen_sampler(&Process::Current, &cfg);
+    let timeout = Some(Duration::from_millis(10));
+
+    assert_eq!(sampler.wait(timeout).unwrap(), Readiness::default());
+
+    sampler.enable().unwrap();
+    workload();
+    sampler.disable().unwrap();
+    assert!(sampler.wait(None).unwrap().readable);
+    // still readable until drained
+    assert!(sampler.wait(timeout).unwrap().readable);
+    assert!(sampler.next_record().is_some());
+    while sampler.next_record().is_some() {}
+    assert_eq!(sampler.wait(timeout).unwrap(), Readiness::default());
+}
+
+#[test]
+fn test_tracer_wait() {
+    let ev = Event::from(SoftwareEvent::PageFaults);
+    let cfg = crate::tracing::Config::new(&ev, &EventScope::all());
+    let mut tracer = Tracer::new(&Process::Current, &Cpu::Any, 1 + 16, &cfg).unwrap();
+
+    tracer.enable().unwrap();
+    std::hint::black_box(vec![1_u8; 1 << 20]);
+    tracer.disable().unwrap();
+    assert!(tracer.wait(None).unwrap().readable);
+    assert!(tracer.next_record().is_some());
+}
+
+#[test]
+fn test_poller() {
+    let cfg = gen_cfg(SoftwareEvent::CpuClock, &Default::default());
+    let mut group = SamplerGroup::new(&Process::Current, &Cpu::Any, 1 + 16).unwrap();
+    let member = group.add_member(&cfg).unwrap();
+    let group = group.enable().unwrap();
+    let sampler = gen_sampler(&Process::Current, &cfg);
+    sampler.enable().unwrap();
+
+    let mut poller = Poller::new().unwrap();
+    let sampler_id = poller.add(sampler).unwrap();
+    let member_id = poller.add(member).unwrap();
+    assert_ne!(sampler_id, member_id);
+    assert_eq!(poller.len(), 2);
+
+    workload();
+    let mut counts = HashMap::new();
+    while counts.len() < 2 {
+        for batch in poller.poll(Some(Duration::from_secs(1))).unwrap() {
+            assert!(!batch.hup);
+            *counts.entry(batch.handle_id).or_insert(0) += batch.records.len();
+        }
+    }
+    group.disable().unwrap();
+    assert!(counts[&sampler_id] > 0);
+    assert!(counts[&member_id] > 0);
+
+    assert!(poller.remove(sampler_id).unwrap().is_some());
+    assert!(poller.remove(sampler_id).unwrap().is_none());
+    assert_eq!(poller.len(), 1);
+    assert!(poller.get_mut(member_id).is_some());
+}
+
+#[test]
+fn test_poller_hup() {
+    let mut child = Command::new("sleep").arg("0.1").spawn().unwrap();
+    let extra_config = ExtraConfig {
+        extra_record_types: vec![ExtraRecord::ForkAndExit],
+        ..Default::default()
+    };
+    let cfg = gen_cfg(SoftwareEvent::Dummy, &extra_config);
+    let sampler = gen_sampler(&Process::Pid(child.id()), &cfg);
+    sampler.enable().unwrap();
+
+    let mut poller = Poller::new().unwrap();
+    let id = poller.add(sampler).unwrap();
+
+    let mut exited = false;
+    let hup = loop {
+        let batches = poller.poll(Some(Duration::from_secs(10))).unwrap();
+        assert!(!batches.is_empty());
+        let batch = &batches[0];
+        assert_eq!(batch.handle_id, id);
+        exited |= batch
+            .records
+            .iter()
+            .any(|it| matches!(it.body, RecordBody::Exit(_)));
+        if batch.hup {
+            break batch.hup;
+        }
+    };
+    child.wait().unwrap();
+
+    assert!(hup);
+    assert!(exited || poller.get_mut(id).unwrap().next_record().is_some());
+}
@@ -0,0 +1,246 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+#[cfg(test)]
+mod tests;
+
+use crate::config;
+use crate::config::{Error, Process};
+use crate::infra::WrapResult;
+use crate::perf_event::PerfEventAttr;
+use crate::sampling::record::{Record, RecordBody};
+use crate::sampling::{Config, CpuSamplerSet, Pollable, Readiness};
+use crate::syscall::bindings::*;
+use crate::syscall::{ioctl_wrapped, perf_event_open_wrapped};
+use crate::topology::online_cpus;
+use std::collections::{BTreeMap, BTreeSet};
+use std::ffi::CString;
+use std::fs::File;
+use std::os::fd::{AsRawFd, FromRawFd, RawFd};
+use std::time::Duration;
+use std::{fs, io, mem};
+
+/*
+The kernel only redirects the output of an event to a ring buffer on the
+same CPU, or of the same task if the event is on any CPU, and inherited
+per-task events can not be mapped. So every thread is sampled by one event
+per CPU, whose output goes to the ring buffer of that CPU, which is owned
+by the event of the main thread.
+
+`PERF_RECORD_FORK` and `PERF_RECORD_EXIT` are enabled to attach new threads
+and detach exited ones, samples of a new thread before it is attached are
+missed. With `inherit` enabled the kernel follows new threads instead.
+*/
+
+/// Sample all threads of a running process into one ring buffer per CPU.
+pub struct ProcessSampler {
+    pid: u32,
+    /// The config with `PERF_RECORD_FORK` and `PERF_RECORD_EXIT` enabled
+    perf_event_attr: PerfEventAttr,
+    /// Owned copy of the string `perf_event_attr` points to, if any
+    #[allow(dead_code)]
+    kprobe_func_or_uprobe_path: Option<CString>,
+    buffers: CpuSamplerSet,
+    /// Map of `tid` -> events on every CPU, empty for the main thread
+    /// whose events own the ring buffers
+    threads: BTreeMap<u32, Vec<File>>,
+    enabled: bool,
+    /// Failure to attach a new thread
+    error: Option<io::Error>,
+}
+
+/// Threads of the process, from `/proc/<pid>/task`.
+fn task_ids(pid: u32) -> io::Result<Vec<u32>> {
+    let mut tids: Vec<u32> = fs::read_dir(format!("/proc/{}/task", pid))?
+        .filter_map(|it| it.ok()?.file_name().to_str()?.parse().ok())
+        .collect();
+    tids.sort_unstable();
+    tids.wrap_ok()
+}
+
+fn is_gone(e: &io::Error) -> bool {
+    matches!(e.raw_os_error(), Some(libc::ESRCH))
+}
+
+impl ProcessSampler {
+    /// Open the config for every thread of the process on every online CPU,
+    /// each CPU has a ring buffer of `mmap_pages`.
+    pub fn new(pid: u32, mmap_pages: usize, cfg: &Config) -> config::Result<Self> {
+        let cpus = online_cpus().map_err(Error::SyscallFailed)?;
+        Self::with_cpus(pid, cpus, mmap_pages, cfg)
+    }
+
+    pub fn with_cpus(
+        pid: u32,
+        cpus: impl IntoIterator<Item = u32>,
+        mmap_pages: usize,
+        cfg: &Config,
+    ) -> config::Result<Self> {
+        let mut perf_event_attr = cfg.as_raw().clone();
+        perf_event_attr.set_task(1);
+        let kprobe_func_or_uprobe_path = cfg.kprobe_func_or_uprobe_path().cloned();
+        if let Some(it) = &kprobe_func_or_uprobe_path {
+            perf_event_attr.__bindgen_anon_3.config1 = it.as_ptr() as _;
+        }
+
+        // SAFETY: copied from a sampling config
+        let cfg = unsafe { Config::from_raw(perf_event_attr.clone()) };
+        let buffers = CpuSamplerSet::for_process(&Process::Pid(pid), cpus, mmap_pages, &cfg)?;
+
+        let mut sampler = Self {
+            pid,
+            perf_event_attr,
+            kprobe_func_or_uprobe_path,
+            buffers,
+            threads: BTreeMap::from([(pid, vec![])]),
+            enabled: false,
+            error: None,
+        };
+        sampler.attach_all()?;
+        sampler.wrap_ok()
+    }
+
+    /// Attach threads until no new thread is found, since threads may be
+    /// created before their parent is attached.
+    fn attach_all(&mut self) -> config::Result<()> {
+        // exited threads may still be listed, they are not attached nor scanned again
+        let mut gone = BTreeSet::new();
+        loop {
+            let tids = task_ids(self.pid).map_err(Error::SyscallFailed)?;
+            let tids: Vec<u32> = tids
+                .into_iter()
+                .filter(|it| !self.threads.contains_key(it) && !gone.contains(it))
+                .collect();
+            if tids.is_empty() {
+                return Ok(());
+            }
+            for tid in tids {
+                match self.attach(tid) {
+                    Ok(()) => {}
+                    Err(e) if is_gone(&e) => {
+                        gone.insert(tid);
+                    }
+                    Err(e) => return Err(Error::SyscallFailed(e)),
+                }
+            }
+        }
+    }
+
+    fn attach(&mut self, tid: u32) -> io::Result<()> {
+        let mut files = vec![];
+        for (cpu, output) in self.buffers.samplers() {
+            let fd = unsafe {
+                perf_event_open_wrapped(&self.perf_event_attr, tid as _, *cpu as _, -1, 0)
+            }?;
+            let file = unsafe { File::from_raw_fd(fd) };
+            let raw_fd = output.as_raw_fd() as i64;
+            ioctl_wrapped(&file, PERF_EVENT_IOCTL_SET_OUTPUT, Some(raw_fd))?;
+            if self.enabled {
+                ioctl_wrapped::<()>(&file, PERF_EVENT_IOCTL_ENABLE, None)?;
+            }
+            files.push(file);
+        }
+        self.threads.insert(tid, files);
+        Ok(())
+    }
+
+    pub fn enable(&mut self) -> io::Result<()> {
+        self.buffers.enable()?;
+        self.each_thread(PERF_EVENT_IOCTL_ENABLE)?;
+        self.enabled = true;
+        Ok(())
+    }
+
+    pub fn disable(&mut self) -> io::Result<()> {
+        self.enabled = false;
+        self.buffers.disable()?;
+        self.each_thread(PERF_EVENT_IOCTL_DISABLE)
+    }
+
+    fn each_thread(&self, request: impl Into<u64> + Copy) -> io::Result<()> {
+        self.threads
+            .values()
+            .flatten()
+            .try_for_each(|file| ioctl_wrapped::<()>(file, request, None))
+    }
+
+    pub const fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    /// Threads which are attached now.
+    pub fn threads(&self) -> Vec<u32> {
+        self.threads.keys().copied().collect()
+    }
+
+    /// The ring buffers, one per CPU.
+    pub const fn buffers(&self) -> &CpuSamplerSet {
+        &self.buffers
+    }
+
+    /// Read the next record of any CPU, new threads and exited threads
+    /// are attached or detached by their task records.
+    pub fn next_record(&mut self) -> Option<Record> {
+        let (_, record) = self.buffers.next_record()?;
+        match &record.body {
+            RecordBody::Fork(body) if body.pid == self.pid => self.on_fork(body.tid),
+            RecordBody::Exit(body) if body.pid == self.pid => self.on_exit(body.tid),
+            _ => {}
+        }
+        Some(record)
+    }
+
+    fn on_fork(&mut self, tid: u32) {
+        if self.perf_event_attr.inherit() > 0 || self.threads.contains_key(&tid) {
+            return;
+        }
+        match self.attach(tid) {
+            Err(e) if !is_gone(&e) && self.error.is_none() => self.error = Some(e),
+            _ => {}
+        }
+    }
+
+    fn on_exit(&mut self, tid: u32) {
+        // the main thread owns the ring buffers
+        if tid != self.pid {
+            self.threads.remove(&tid);
+        }
+    }
+
+    /// Read all records of all CPUs.
+    pub fn drain(&mut self) -> Vec<Record> {
+        std::iter::from_fn(|| self.next_record()).collect()
+    }
+
+    /// Wait until any CPU is readable, `None` waits forever.
+    pub fn wait(&self, timeout: Option<Duration>) -> io::Result<Readiness> {
+        self.buffers.wait(timeout)
+    }
+
+    /// Take the first failure to attach a new thread since the last call.
+    pub fn take_error(&mut self) -> Option<io::Error> {
+        mem::take(&mut self.error)
+    }
+}
+
+impl AsRawFd for ProcessSampler {
+    fn as_raw_fd(&self) -> RawFd {
+        self.buffers.as_raw_fd()
+    }
+}
+
+impl Pollable for ProcessSampler {
+    fn next_record(&mut self) -> Option<Record> {
+        self.next_record()
+    }
+}
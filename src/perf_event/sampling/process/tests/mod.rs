@@ -0,0 +1,91 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+use crate::sampling::record::{Record, RecordBody};
+use crate::sampling::{Config, ExtraConfig, OverflowBy, ProcessSampler, SampleRecordFields};
+use crate::{Event, EventScope, SoftwareEvent};
+use std::sync::mpsc;
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+fn gen_cfg() -> Config {
+    let ev = Event::from(SoftwareEvent::CpuClock);
+    let overflow_by = OverflowBy::Period(100_000);
+    let extra_config = ExtraConfig {
+        sample_record_fields: SampleRecordFields {
+            pid_and_tid: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    Config::extra_new(&ev, &EventScope::all(), &overflow_by, &extra_config)
+}
+
+/// Spawn a thread which runs for a while on the message, returns its tid.
+fn spawn_thread() -> (u32, mpsc::Sender<()>, JoinHandle<()>) {
+    let (tid_tx, tid_rx) = mpsc::channel();
+    let (go_tx, go_rx) = mpsc::channel::<()>();
+    let handle = thread::spawn(move || {
+        tid_tx.send(unsafe { libc::gettid() } as u32).unwrap();
+        go_rx.recv().unwrap();
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_millis(20) {
+            std::hint::black_box(rand::random::<u64>());
+        }
+    });
+    (tid_rx.recv().unwrap(), go_tx, handle)
+}
+
+fn has_samples(records: &[Record], tid: u32) -> bool {
+    records.iter().any(|it| match &it.body {
+        RecordBody::Sample(body) => body.tid == Some(tid),
+        _ => false,
+    })
+}
+
+#[test]
+fn test_process_sampler() {
+    let pid = std::process::id();
+    let (old_tid, old_go, old_handle) = spawn_thread();
+
+    let mmap_pages = 1 + 16;
+    let mut sampler = ProcessSampler::new(pid, mmap_pages, &gen_cfg()).unwrap();
+    assert!(sampler.threads().contains(&pid));
+    assert!(sampler.threads().contains(&old_tid));
+    sampler.enable().unwrap();
+
+    // attached by its fork record
+    let (new_tid, new_go, new_handle) = spawn_thread();
+    let mut records = vec![];
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while !sampler.threads().contains(&new_tid) {
+        assert!(Instant::now() < deadline);
+        records.extend(sampler.drain());
+    }
+
+    old_go.send(()).unwrap();
+    new_go.send(()).unwrap();
+    old_handle.join().unwrap();
+    new_handle.join().unwrap();
+    sampler.disable().unwrap();
+    records.extend(sampler.drain());
+
+    assert!(sampler.take_error().is_none());
+    assert!(has_samples(&records, old_tid));
+    assert!(has_samples(&records, new_tid));
+    // detached by their exit records
+    assert!(!sampler.threads().contains(&old_tid));
+    assert!(!sampler.threads().contains(&new_tid));
+    assert!(sampler.threads().contains(&pid));
+}
@@ -12,11 +12,13 @@
 // You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
 // see <https://www.gnu.org/licenses/>.
 
+use crate::sampling::record::encode::Writer;
 use crate::sampling::record::sample_id::SampleId;
+use crate::sampling::record::ParseContext;
 
 mod raw;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Body {
     pub aux_offset: u64,
     pub aux_size: u64,
@@ -35,3 +37,12 @@ impl Body {
         }
     }
 }
+
+impl Body {
+    pub(crate) fn write_to(&self, w: &mut Writer, ctx: &ParseContext) {
+        w.u64(self.aux_offset);
+        w.u64(self.aux_size);
+        w.u64(self.flags);
+        w.sample_id(&self.sample_id, ctx);
+    }
+}
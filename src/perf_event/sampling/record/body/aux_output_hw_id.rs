@@ -13,7 +13,7 @@
 // see <https://www.gnu.org/licenses/>.
 
 #[repr(C)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Body {
     // TODO: missing docs in manual
 }
@@ -12,12 +12,14 @@
 // You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
 // see <https://www.gnu.org/licenses/>.
 
+use crate::sampling::record::encode::Writer;
 use crate::sampling::record::sample_id::SampleId;
+use crate::sampling::record::ParseContext;
 use crate::syscall::bindings::BPF_TAG_SIZE;
 
 mod raw;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Body {
     pub r#type: u16,
     pub flags: u16,
@@ -38,3 +40,13 @@ impl Body {
         }
     }
 }
+
+impl Body {
+    pub(crate) fn write_to(&self, w: &mut Writer, ctx: &ParseContext) {
+        w.u16(self.r#type);
+        w.u16(self.flags);
+        w.u32(self.id);
+        w.bytes(&self.tag);
+        w.sample_id(&self.sample_id, ctx);
+    }
+}
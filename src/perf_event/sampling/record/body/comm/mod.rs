@@ -14,10 +14,12 @@
 
 mod raw;
 
+use crate::sampling::record::encode::Writer;
 use crate::sampling::record::sample_id::SampleId;
+use crate::sampling::record::ParseContext;
 use std::ffi::CString;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Body {
     pub pid: u32,
     pub tid: u32,
@@ -41,3 +43,12 @@ impl Body {
         }
     }
 }
+
+impl Body {
+    pub(crate) fn write_to(&self, w: &mut Writer, ctx: &ParseContext) {
+        w.u32(self.pid);
+        w.u32(self.tid);
+        w.str(&self.comm);
+        w.sample_id(&self.sample_id, ctx);
+    }
+}
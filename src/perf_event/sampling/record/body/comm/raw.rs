@@ -46,8 +46,8 @@ impl Raw {
         let ptr = self.read_ptr;
         let zt = ZeroTerminated::from_ptr(ptr);
         let slice = zt.as_slice();
-        // Above [u8] will be rounded up to 64-bit in size in the kernel
-        self.read_ptr = slice.follow_mem_ptr().align_as_ptr::<u64>() as _;
+        // Above [u8] and the terminating 0 will be rounded up to 64-bit in size in the kernel
+        self.read_ptr = slice.follow_mem_ptr().add(1).align_as_ptr::<u64>() as _;
         slice
     }
 
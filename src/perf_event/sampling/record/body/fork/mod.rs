@@ -12,12 +12,14 @@
 // You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
 // see <https://www.gnu.org/licenses/>.
 
+use crate::sampling::record::encode::Writer;
 use crate::sampling::record::sample_id::SampleId;
+use crate::sampling::record::ParseContext;
 
 mod raw;
 
 #[repr(C)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Body {
     pub pid: u32,
     pub ppid: u32,
@@ -40,3 +42,14 @@ impl Body {
         }
     }
 }
+
+impl Body {
+    pub(crate) fn write_to(&self, w: &mut Writer, ctx: &ParseContext) {
+        w.u32(self.pid);
+        w.u32(self.ppid);
+        w.u32(self.tid);
+        w.u32(self.ptid);
+        w.u64(self.time);
+        w.sample_id(&self.sample_id, ctx);
+    }
+}
@@ -19,9 +19,21 @@ struct {
 };
 */
 
+use crate::sampling::record::encode::Writer;
+use crate::sampling::record::ParseContext;
+
 #[repr(C)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Body {
     pub pid: u32,
     pub tid: u32,
 }
+
+impl Body {
+    /// `sample_id` is not kept in this body, so it's written as 0
+    pub(crate) fn write_to(&self, w: &mut Writer, ctx: &ParseContext) {
+        w.u32(self.pid);
+        w.u32(self.tid);
+        w.sample_id(&None, ctx);
+    }
+}
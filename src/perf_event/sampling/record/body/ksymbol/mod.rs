@@ -12,12 +12,14 @@
 // You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
 // see <https://www.gnu.org/licenses/>.
 
+use crate::sampling::record::encode::Writer;
 use crate::sampling::record::sample_id::SampleId;
+use crate::sampling::record::ParseContext;
 use std::ffi::CString;
 
 mod raw;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Body {
     pub addr: u64,
     pub len: u32,
@@ -45,3 +47,14 @@ impl Body {
         }
     }
 }
+
+impl Body {
+    pub(crate) fn write_to(&self, w: &mut Writer, ctx: &ParseContext) {
+        w.u64(self.addr);
+        w.u32(self.len);
+        w.u16(self.ksym_type);
+        w.u16(self.flags);
+        w.str(&self.name);
+        w.sample_id(&self.sample_id, ctx);
+    }
+}
@@ -12,12 +12,14 @@
 // You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
 // see <https://www.gnu.org/licenses/>.
 
+use crate::sampling::record::encode::Writer;
+use crate::sampling::record::ParseContext;
 use std::ffi::CString;
 
 mod raw;
 
 #[repr(C)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Body {
     pub pid: u32,
     pub tid: u32,
@@ -40,3 +42,16 @@ impl Body {
         }
     }
 }
+
+impl Body {
+    /// `sample_id` is not kept in this body, so it's written as 0
+    pub(crate) fn write_to(&self, w: &mut Writer, ctx: &ParseContext) {
+        w.u32(self.pid);
+        w.u32(self.tid);
+        w.u64(self.addr);
+        w.u64(self.len);
+        w.u64(self.pgoff);
+        w.str(&self.filename);
+        w.sample_id(&None, ctx);
+    }
+}
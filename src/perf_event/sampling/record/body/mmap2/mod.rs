@@ -12,14 +12,16 @@
 // You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
 // see <https://www.gnu.org/licenses/>.
 
+use crate::sampling::record::encode::Writer;
 use crate::sampling::record::sample_id::SampleId;
+use crate::sampling::record::ParseContext;
 #[cfg(feature = "linux-5.12")]
 use crate::syscall::bindings::PERF_RECORD_MISC_MMAP_BUILD_ID;
 use std::ffi::CString;
 
 mod raw;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum AnonEnum {
     Normal {
         maj: u32,
@@ -30,7 +32,7 @@ pub enum AnonEnum {
     BuildId(Vec<u8>),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Body {
     pub pid: u32,
     pub tid: u32,
@@ -70,8 +72,11 @@ impl Body {
                 match misc {
                     #[cfg(feature = "linux-5.12")]
                     PERF_RECORD_MISC_MMAP_BUILD_ID => {
-                        let build_id_len = anon_union.anon_struct_2.build_id_size as _;
-                        let build_id = anon_union.anon_struct_2.build_id[0..build_id_len].to_vec();
+                        let build_id = &anon_union.anon_struct_2.build_id;
+                        let build_id_len = build_id
+                            .len()
+                            .min(anon_union.anon_struct_2.build_id_size as _);
+                        let build_id = build_id[..build_id_len].to_vec();
                         AnonEnum::BuildId(build_id)
                     }
                     _ => AnonEnum::Normal {
@@ -89,3 +94,38 @@ impl Body {
         }
     }
 }
+
+impl Body {
+    /// `misc` of the record should be `PERF_RECORD_MISC_MMAP_BUILD_ID` for `AnonEnum::BuildId`
+    pub(crate) fn write_to(&self, w: &mut Writer, ctx: &ParseContext) {
+        w.u32(self.pid);
+        w.u32(self.tid);
+        w.u64(self.addr);
+        w.u64(self.len);
+        w.u64(self.pgoff);
+        match &self.anon_enum {
+            AnonEnum::Normal {
+                maj,
+                min,
+                ino,
+                ino_generation,
+            } => {
+                w.u32(*maj);
+                w.u32(*min);
+                w.u64(*ino);
+                w.u64(*ino_generation);
+            }
+            AnonEnum::BuildId(build_id) => {
+                let mut buf = [0_u8; 24];
+                let len = build_id.len().min(20);
+                buf[0] = len as _;
+                buf[4..4 + len].copy_from_slice(&build_id[..len]);
+                w.bytes(&buf);
+            }
+        }
+        w.u32(self.prot);
+        w.u32(self.flags);
+        w.str(&self.filename);
+        w.sample_id(&self.sample_id, ctx);
+    }
+}
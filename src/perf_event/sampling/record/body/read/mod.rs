@@ -12,21 +12,30 @@
 // You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
 // see <https://www.gnu.org/licenses/>.
 
+use crate::sampling::record::encode::Writer;
+use crate::sampling::record::read_format::Layout;
 use crate::sampling::record::sample_id::SampleId;
+use crate::sampling::record::ParseContext;
 use crate::sampling::SamplerGroupStat;
 
 mod raw;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Body {
     pub pid: u32,
     pub tid: u32,
+    /// Decoded like `sample::Body::v`
     pub values: SamplerGroupStat,
     pub sample_id: Option<SampleId>,
 }
 
 impl Body {
-    pub(crate) unsafe fn from_ptr(ptr: *const u8, sample_type: u64, sample_id_all: bool) -> Self {
+    pub(crate) unsafe fn from_ptr(
+        ptr: *const u8,
+        sample_type: u64,
+        read_format: u64,
+        sample_id_all: bool,
+    ) -> Self {
         let mut raw = raw::Raw {
             read_ptr: ptr,
             sample_type,
@@ -37,10 +46,19 @@ impl Body {
             pid: sized.pid,
             tid: sized.tid,
             values: {
-                let (head, values) = raw.values();
-                SamplerGroupStat::from_raw(head, values)
+                let layout = Layout::new(read_format);
+                layout.decode(raw.values(layout))
             },
             sample_id: sample_id_all.then(|| raw.sample_id()),
         }
     }
 }
+
+impl Body {
+    pub(crate) fn write_to(&self, w: &mut Writer, ctx: &ParseContext) {
+        w.u32(self.pid);
+        w.u32(self.tid);
+        w.read_format(&self.values, ctx.read_format);
+        w.sample_id(&self.sample_id, ctx);
+    }
+}
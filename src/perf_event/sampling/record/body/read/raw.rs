@@ -21,9 +21,8 @@ struct {
 */
 
 use crate::infra::SliceExt;
+use crate::sampling::record::read_format::Layout;
 use crate::sampling::record::sample_id::SampleId;
-use crate::sampling::{ReadFormatHead, ReadFormatValue};
-use std::slice;
 
 #[repr(C)]
 pub struct Sized {
@@ -43,13 +42,10 @@ impl Raw {
         &*ptr
     }
 
-    pub unsafe fn values(&mut self) -> (&ReadFormatHead, &[ReadFormatValue]) {
-        let head_ptr = self.read_ptr as *const ReadFormatHead;
-        let head = &*head_ptr;
-        let body_ptr = head_ptr.add(1) as *const ReadFormatValue;
-        let slice = slice::from_raw_parts(body_ptr, head.members_len as _);
-        self.read_ptr = slice.follow_mem_ptr() as _;
-        (head, slice)
+    pub unsafe fn values(&mut self, layout: Layout) -> &[u64] {
+        let words = layout.words(self.read_ptr);
+        self.read_ptr = words.follow_mem_ptr() as _;
+        words
     }
 
     pub unsafe fn sample_id(&self) -> SampleId {
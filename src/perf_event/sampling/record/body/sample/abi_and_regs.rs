@@ -12,6 +12,8 @@
 // You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
 // see <https://www.gnu.org/licenses/>.
 
+use super::{RegMask, RegsView};
+use crate::sampling::record::encode::Writer;
 use crate::syscall::bindings::*;
 
 #[derive(PartialEq, Eq, Clone, Debug)]
@@ -20,33 +22,59 @@ pub enum Abi {
     AbiNone,
     Abi32,
     Abi64,
+    /// ABI unknown to this crate
+    Unknown(u64),
 }
 
 impl Abi {
-    pub(crate) fn from_raw(abi: u64) -> Self {
+    pub(crate) const fn from_raw(abi: u64) -> Self {
         #[rustfmt::skip]
         let val = match abi as _ {
             PERF_SAMPLE_REGS_ABI_NONE => Self::AbiNone,
             PERF_SAMPLE_REGS_ABI_32   => Self::Abi32,
             PERF_SAMPLE_REGS_ABI_64   => Self::Abi64,
-            abi => unreachable!("ABI: {}", abi),
+            _                         => Self::Unknown(abi),
+        };
+        val
+    }
+
+    pub(crate) const fn to_raw(&self) -> u64 {
+        #[rustfmt::skip]
+        let val = match self {
+            Self::AbiNone    => PERF_SAMPLE_REGS_ABI_NONE as _,
+            Self::Abi32      => PERF_SAMPLE_REGS_ABI_32 as _,
+            Self::Abi64      => PERF_SAMPLE_REGS_ABI_64 as _,
+            Self::Unknown(abi) => *abi,
         };
         val
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct AbiAndRegs {
     pub abi: Abi,
+    /// `sample_regs_*` the registers were sampled with
+    pub mask: RegMask,
     pub regs: Vec<u64>,
 }
 
 impl AbiAndRegs {
-    pub(crate) fn from_raw(raw: (&u64, &[u64])) -> Self {
+    pub(crate) fn from_raw(raw: (&u64, &[u64]), mask: RegMask) -> Self {
         let (abi, regs) = raw;
         Self {
             abi: Abi::from_raw(*abi),
+            mask,
             regs: regs.to_vec(),
         }
     }
+
+    /// Access the registers by name
+    pub fn view(&self) -> RegsView<'_> {
+        RegsView::new(self.abi.clone(), self.mask, &self.regs)
+    }
+
+    pub(crate) fn write_to(&self, w: &mut Writer) {
+        w.u64(self.abi.to_raw());
+        self.regs.iter().for_each(|it| w.u64(*it));
+    }
 }
@@ -0,0 +1,259 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+
+use crate::sampling::record::encode::Writer;
+use crate::syscall::bindings::*;
+use bitflags::bitflags;
+use std::mem::size_of;
+use std::slice;
+
+bitflags! {
+    /// Branches to sample (`PERF_SAMPLE_BRANCH_*`)
+    ///
+    /// Without any of `USER`, `KERNEL` and `HV`, the privilege levels follow the scopes of the event.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct BranchSampleType: u64 {
+        /// User branches
+        const USER       = PERF_SAMPLE_BRANCH_USER       as _;
+        /// Kernel branches
+        const KERNEL     = PERF_SAMPLE_BRANCH_KERNEL     as _;
+        /// Hypervisor branches
+        const HV         = PERF_SAMPLE_BRANCH_HV         as _;
+        /// Any branch types
+        const ANY        = PERF_SAMPLE_BRANCH_ANY        as _;
+        /// Any call branch
+        const ANY_CALL   = PERF_SAMPLE_BRANCH_ANY_CALL   as _;
+        /// Any return branch
+        const ANY_RETURN = PERF_SAMPLE_BRANCH_ANY_RETURN as _;
+        /// Indirect calls
+        const IND_CALL   = PERF_SAMPLE_BRANCH_IND_CALL   as _;
+        /// Transaction aborts
+        const ABORT_TX   = PERF_SAMPLE_BRANCH_ABORT_TX   as _;
+        /// In transaction
+        const IN_TX      = PERF_SAMPLE_BRANCH_IN_TX      as _;
+        /// Not in transaction
+        const NO_TX      = PERF_SAMPLE_BRANCH_NO_TX      as _;
+        /// Conditional branches
+        #[cfg(feature = "linux-3.16")]
+        const COND       = PERF_SAMPLE_BRANCH_COND       as _;
+        /// Call/ret stack
+        #[cfg(feature = "linux-4.1")]
+        const CALL_STACK = PERF_SAMPLE_BRANCH_CALL_STACK as _;
+        /// Indirect jumps
+        #[cfg(feature = "linux-4.2")]
+        const IND_JUMP   = PERF_SAMPLE_BRANCH_IND_JUMP   as _;
+        /// Direct calls
+        #[cfg(feature = "linux-4.4")]
+        const CALL       = PERF_SAMPLE_BRANCH_CALL       as _;
+        /// No flags, `mispred`, `predicted`, `in_tx` and `abort` are left 0
+        #[cfg(feature = "linux-4.7")]
+        const NO_FLAGS   = PERF_SAMPLE_BRANCH_NO_FLAGS   as _;
+        /// No cycles, `cycles` is left 0
+        #[cfg(feature = "linux-4.7")]
+        const NO_CYCLES  = PERF_SAMPLE_BRANCH_NO_CYCLES  as _;
+        /// Save the branch type in `type_`
+        #[cfg(feature = "linux-4.14")]
+        const TYPE_SAVE  = PERF_SAMPLE_BRANCH_TYPE_SAVE  as _;
+        /// Save `hw_idx`
+        #[cfg(feature = "linux-5.7")]
+        const HW_INDEX   = PERF_SAMPLE_BRANCH_HW_INDEX   as _;
+        /// Save the privilege level of the branch target
+        #[cfg(feature = "linux-6.3")]
+        const PRIV_SAVE  = PERF_SAMPLE_BRANCH_PRIV_SAVE  as _;
+    }
+}
+
+/// Common branch type classification (`PERF_BR_*`)
+#[cfg(feature = "linux-4.14")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BranchType {
+    Cond,
+    Uncond,
+    Ind,
+    Call,
+    IndCall,
+    Ret,
+    Syscall,
+    Sysret,
+    CondCall,
+    CondRet,
+    #[cfg(feature = "linux-6.0")]
+    Eret,
+    #[cfg(feature = "linux-6.0")]
+    Irq,
+    #[cfg(feature = "linux-6.3")]
+    Serror,
+    #[cfg(feature = "linux-6.3")]
+    NoTx,
+    /// Type in `new_type` of the raw entry
+    #[cfg(feature = "linux-6.3")]
+    ExtendAbi,
+    /// `PERF_BR_UNKNOWN` is `Unknown(0)`, other values are unknown to this crate
+    Unknown(u8),
+}
+
+#[cfg(feature = "linux-4.14")]
+impl BranchType {
+    pub(crate) const fn from_raw(raw: u8) -> Self {
+        #[rustfmt::skip]
+        let val = match raw as u32 {
+            PERF_BR_COND       => Self::Cond,
+            PERF_BR_UNCOND     => Self::Uncond,
+            PERF_BR_IND        => Self::Ind,
+            PERF_BR_CALL       => Self::Call,
+            PERF_BR_IND_CALL   => Self::IndCall,
+            PERF_BR_RET        => Self::Ret,
+            PERF_BR_SYSCALL    => Self::Syscall,
+            PERF_BR_SYSRET     => Self::Sysret,
+            PERF_BR_COND_CALL  => Self::CondCall,
+            PERF_BR_COND_RET   => Self::CondRet,
+            #[cfg(feature = "linux-6.0")]
+            PERF_BR_ERET       => Self::Eret,
+            #[cfg(feature = "linux-6.0")]
+            PERF_BR_IRQ        => Self::Irq,
+            #[cfg(feature = "linux-6.3")]
+            PERF_BR_SERROR     => Self::Serror,
+            #[cfg(feature = "linux-6.3")]
+            PERF_BR_NO_TX      => Self::NoTx,
+            #[cfg(feature = "linux-6.3")]
+            PERF_BR_EXTEND_ABI => Self::ExtendAbi,
+            _ => Self::Unknown(raw),
+        };
+        val
+    }
+
+    pub(crate) const fn to_raw(self) -> u8 {
+        #[rustfmt::skip]
+        let val: u32 = match self {
+            Self::Cond      => PERF_BR_COND,
+            Self::Uncond    => PERF_BR_UNCOND,
+            Self::Ind       => PERF_BR_IND,
+            Self::Call      => PERF_BR_CALL,
+            Self::IndCall   => PERF_BR_IND_CALL,
+            Self::Ret       => PERF_BR_RET,
+            Self::Syscall   => PERF_BR_SYSCALL,
+            Self::Sysret    => PERF_BR_SYSRET,
+            Self::CondCall  => PERF_BR_COND_CALL,
+            Self::CondRet   => PERF_BR_COND_RET,
+            #[cfg(feature = "linux-6.0")]
+            Self::Eret      => PERF_BR_ERET,
+            #[cfg(feature = "linux-6.0")]
+            Self::Irq       => PERF_BR_IRQ,
+            #[cfg(feature = "linux-6.3")]
+            Self::Serror    => PERF_BR_SERROR,
+            #[cfg(feature = "linux-6.3")]
+            Self::NoTx      => PERF_BR_NO_TX,
+            #[cfg(feature = "linux-6.3")]
+            Self::ExtendAbi => PERF_BR_EXTEND_ABI,
+            Self::Unknown(raw) => raw as _,
+        };
+        val as _
+    }
+}
+
+/// One `perf_branch_entry`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BranchEntry {
+    pub from: u64,
+    pub to: u64,
+    /// Target mispredicted
+    pub mispred: bool,
+    /// Target predicted
+    pub predicted: bool,
+    /// In transaction
+    pub in_tx: bool,
+    /// Transaction abort
+    pub abort: bool,
+    /// Cycles since the last branch, 0 if not supported
+    pub cycles: u16,
+    /// Requires `BranchSampleType::TYPE_SAVE`
+    #[cfg(feature = "linux-4.14")]
+    pub type_: BranchType,
+    /// The whole flags word following `to`, including `spec`, `new_type`, `priv`
+    /// and the reserved bits which are not decoded above
+    ///
+    /// Written back by encoding, with the decoded fields above set over it.
+    pub raw_flags: u64,
+}
+
+impl BranchEntry {
+    pub(crate) fn from_raw(raw: &perf_branch_entry) -> Self {
+        Self {
+            from: raw.from,
+            to: raw.to,
+            mispred: raw.mispred() > 0,
+            predicted: raw.predicted() > 0,
+            in_tx: raw.in_tx() > 0,
+            abort: raw.abort() > 0,
+            cycles: raw.cycles() as _,
+            #[cfg(feature = "linux-4.14")]
+            type_: BranchType::from_raw(raw.type_() as _),
+            // the bitfield unit is the third word of the entry
+            raw_flags: unsafe { *(raw as *const perf_branch_entry as *const u64).add(2) },
+        }
+    }
+
+    pub(crate) fn to_raw(&self) -> perf_branch_entry {
+        let mut raw = perf_branch_entry {
+            from: self.from,
+            to: self.to,
+            _bitfield_1: __BindgenBitfieldUnit::new(self.raw_flags.to_ne_bytes()),
+            ..Default::default()
+        };
+        raw.set_mispred(self.mispred as _);
+        raw.set_predicted(self.predicted as _);
+        raw.set_in_tx(self.in_tx as _);
+        raw.set_abort(self.abort as _);
+        raw.set_cycles(self.cycles as _);
+        #[cfg(feature = "linux-4.14")]
+        raw.set_type_(self.type_.to_raw() as _);
+        raw
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BranchStack {
+    /// Index of the newest entry in the hardware buffer,
+    /// `Some` if sampled with `BranchSampleType::HW_INDEX`
+    pub hw_idx: Option<u64>,
+    /// Newest first
+    pub entries: Vec<BranchEntry>,
+}
+
+impl BranchStack {
+    pub(crate) fn from_raw(raw: (Option<&u64>, &[perf_branch_entry])) -> Self {
+        let (hw_idx, entries) = raw;
+        Self {
+            hw_idx: hw_idx.copied(),
+            entries: entries.iter().map(BranchEntry::from_raw).collect(),
+        }
+    }
+
+    pub(crate) fn write_to(&self, w: &mut Writer, hw_index: bool) {
+        w.u64(self.entries.len() as _);
+        if hw_index {
+            w.u64(self.hw_idx.unwrap_or_default());
+        }
+        for entry in &self.entries {
+            let raw = entry.to_raw();
+            let bytes = unsafe {
+                slice::from_raw_parts(
+                    &raw as *const _ as *const u8,
+                    size_of::<perf_branch_entry>(),
+                )
+            };
+            w.bytes(bytes);
+        }
+    }
+}
@@ -12,163 +12,518 @@
 // You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
 // see <https://www.gnu.org/licenses/>.
 
+//! Lossless decoding of `perf_mem_data_src`.
+//!
+//! Every sub-field keeps all of its bits, including the ones unknown to this
+//! crate, so [`DataSrc::to_raw`] gives back exactly what was decoded. The
+//! `Display` impls follow `perf script -F data_src` and the columns of
+//! `perf mem report`.
+
 use crate::syscall::bindings::*;
+use bitflags::bitflags;
+use std::fmt::{self, Display, Formatter, Write};
 
-#[derive(PartialEq, Eq, Clone, Debug)]
-pub enum MemOp {
-    Na,
-    Load,
-    Store,
-    Pfetch,
-    Exec,
+bitflags! {
+    /// Type of opcode (`PERF_MEM_OP_*`)
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct MemOp: u8 {
+        const NA     = PERF_MEM_OP_NA     as _;
+        const LOAD   = PERF_MEM_OP_LOAD   as _;
+        const STORE  = PERF_MEM_OP_STORE  as _;
+        const PFETCH = PERF_MEM_OP_PFETCH as _;
+        const EXEC   = PERF_MEM_OP_EXEC   as _;
+    }
+
+    /// Memory hierarchy level and hit or miss (`PERF_MEM_LVL_*`)
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct MemLvl: u16 {
+        const NA       = PERF_MEM_LVL_NA       as _;
+        const HIT      = PERF_MEM_LVL_HIT      as _;
+        const MISS     = PERF_MEM_LVL_MISS     as _;
+        const L1       = PERF_MEM_LVL_L1       as _;
+        const LFB      = PERF_MEM_LVL_LFB      as _;
+        const L2       = PERF_MEM_LVL_L2       as _;
+        const L3       = PERF_MEM_LVL_L3       as _;
+        const LOC_RAM  = PERF_MEM_LVL_LOC_RAM  as _;
+        const REM_RAM1 = PERF_MEM_LVL_REM_RAM1 as _;
+        const REM_RAM2 = PERF_MEM_LVL_REM_RAM2 as _;
+        const REM_CCE1 = PERF_MEM_LVL_REM_CCE1 as _;
+        const REM_CCE2 = PERF_MEM_LVL_REM_CCE2 as _;
+        const IO       = PERF_MEM_LVL_IO       as _;
+        const UNC      = PERF_MEM_LVL_UNC      as _;
+    }
+
+    /// Snoop mode (`PERF_MEM_SNOOP_*`)
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct MemSnoop: u8 {
+        const NA   = PERF_MEM_SNOOP_NA   as _;
+        const NONE = PERF_MEM_SNOOP_NONE as _;
+        const HIT  = PERF_MEM_SNOOP_HIT  as _;
+        const MISS = PERF_MEM_SNOOP_MISS as _;
+        const HITM = PERF_MEM_SNOOP_HITM as _;
+    }
+
+    /// Lock instruction (`PERF_MEM_LOCK_*`)
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct MemLock: u8 {
+        const NA     = PERF_MEM_LOCK_NA     as _;
+        const LOCKED = PERF_MEM_LOCK_LOCKED as _;
+    }
+
+    /// TLB access (`PERF_MEM_TLB_*`)
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct MemDtlb: u8 {
+        const NA   = PERF_MEM_TLB_NA   as _;
+        const HIT  = PERF_MEM_TLB_HIT  as _;
+        const MISS = PERF_MEM_TLB_MISS as _;
+        const L1   = PERF_MEM_TLB_L1   as _;
+        const L2   = PERF_MEM_TLB_L2   as _;
+        const WK   = PERF_MEM_TLB_WK   as _;
+        const OS   = PERF_MEM_TLB_OS   as _;
+    }
+
+    /// Snoop mode, extended (`PERF_MEM_SNOOPX_*`)
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct MemSnoopX: u8 {
+        #[cfg(feature = "linux-4.14")]
+        const FWD  = PERF_MEM_SNOOPX_FWD  as _;
+        #[cfg(feature = "linux-6.0")]
+        const PEER = PERF_MEM_SNOOPX_PEER as _;
+    }
+
+    /// Access blocked (`PERF_MEM_BLK_*`)
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct MemBlk: u8 {
+        #[cfg(feature = "linux-5.12")]
+        const NA   = PERF_MEM_BLK_NA   as _;
+        #[cfg(feature = "linux-5.12")]
+        const DATA = PERF_MEM_BLK_DATA as _;
+        #[cfg(feature = "linux-5.12")]
+        const ADDR = PERF_MEM_BLK_ADDR as _;
+    }
 }
 
-#[derive(PartialEq, Eq, Clone, Debug)]
-pub enum MemLvl {
-    Na,
-    Hit,
-    Miss,
+/// Memory hierarchy level number (`PERF_MEM_LVLNUM_*`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MemLvlNum {
+    #[cfg(feature = "linux-4.14")]
     L1,
-    Lfb,
+    #[cfg(feature = "linux-4.14")]
     L2,
+    #[cfg(feature = "linux-4.14")]
     L3,
-    LocRam,
-    RemRam1,
-    RemRam2,
-    RemCce1,
-    RemCce2,
+    #[cfg(feature = "linux-4.14")]
+    L4,
+    #[cfg(feature = "linux-6.3")]
+    Cxl,
+    #[cfg(feature = "linux-6.3")]
     Io,
-    Unc,
-}
-
-#[derive(PartialEq, Eq, Clone, Debug)]
-pub enum MemSnoop {
-    Na,
-    None,
-    Hit,
-    Miss,
-    Hitm,
-}
-
-#[derive(PartialEq, Eq, Clone, Debug)]
-pub enum MemLock {
+    #[cfg(feature = "linux-4.14")]
+    AnyCache,
+    #[cfg(feature = "linux-4.14")]
+    Lfb,
+    #[cfg(feature = "linux-4.14")]
+    Ram,
+    #[cfg(feature = "linux-4.14")]
+    Pmem,
+    #[cfg(feature = "linux-4.14")]
     Na,
-    Locked,
+    /// Value unknown to this crate
+    Unknown(u8),
 }
 
-#[derive(PartialEq, Eq, Clone, Debug)]
-pub enum MemDtlb {
-    Na,
-    Hit,
-    Miss,
-    L1,
-    L2,
-    Wk,
-    Os,
+/// Number of hops to the data source (`PERF_MEM_HOPS_*`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MemHops {
+    /// Remote core, same node
+    #[cfg(feature = "linux-5.16")]
+    Hops0,
+    /// Remote node, same socket
+    #[cfg(feature = "linux-6.0")]
+    Hops1,
+    /// Remote socket, same board
+    #[cfg(feature = "linux-6.0")]
+    Hops2,
+    /// Remote board
+    #[cfg(feature = "linux-6.0")]
+    Hops3,
+    /// Value unknown to this crate
+    Unknown(u8),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct DataSrc {
     pub mem_op: MemOp,
     pub mem_lvl: MemLvl,
     pub mem_snoop: MemSnoop,
     pub mem_lock: MemLock,
     pub mem_dtlb: MemDtlb,
+    /// `None` if the level is only reported by `mem_lvl`
+    pub mem_lvl_num: Option<MemLvlNum>,
+    pub mem_remote: bool,
+    pub mem_snoopx: MemSnoopX,
+    pub mem_blk: MemBlk,
+    /// `None` if the hops are not reported
+    pub mem_hops: Option<MemHops>,
+    /// Reserved bits, kept so that decoding is lossless
+    pub mem_rsvd: u32,
 }
 
-impl MemOp {
-    pub(crate) fn from_raw(raw: u64) -> Self {
-        #[rustfmt::skip]
-        let val = match (raw >> PERF_MEM_OP_SHIFT) as u32 {
-            bits if bits & PERF_MEM_OP_NA     > 0 => Self::Na,
-            bits if bits & PERF_MEM_OP_LOAD   > 0 => Self::Load,
-            bits if bits & PERF_MEM_OP_STORE  > 0 => Self::Store,
-            bits if bits & PERF_MEM_OP_PFETCH > 0 => Self::Pfetch,
-            bits if bits & PERF_MEM_OP_EXEC   > 0 => Self::Exec,
-            bits => unreachable!("mem_op bits: {}", bits),
-        };
-        val
-    }
+// Offset and width of the fields in `perf_mem_data_src`, the later ones
+// are spelled out as older headers lack them.
+const MEM_OP_MASK: u64 = 0x1f;
+const MEM_LVL_MASK: u64 = 0x3fff;
+const MEM_SNOOP_MASK: u64 = 0x1f;
+const MEM_LOCK_MASK: u64 = 0x3;
+const MEM_DTLB_MASK: u64 = 0x7f;
+const MEM_LVLNUM_SHIFT: u32 = 33;
+const MEM_LVLNUM_MASK: u64 = 0xf;
+const MEM_REMOTE_SHIFT: u32 = 37;
+const MEM_SNOOPX_SHIFT: u32 = 38;
+const MEM_SNOOPX_MASK: u64 = 0x3;
+const MEM_BLK_SHIFT: u32 = 40;
+const MEM_BLK_MASK: u64 = 0x7;
+const MEM_HOPS_SHIFT: u32 = 43;
+const MEM_HOPS_MASK: u64 = 0x7;
+const MEM_RSVD_SHIFT: u32 = 46;
+const MEM_RSVD_MASK: u64 = 0x3ffff;
+
+const fn field(raw: u64, shift: u32, mask: u64) -> u64 {
+    (raw >> shift) & mask
 }
 
-impl MemLvl {
-    pub(crate) fn from_raw(raw: u64) -> Self {
+impl MemLvlNum {
+    pub(crate) const fn from_raw(raw: u8) -> Self {
         #[rustfmt::skip]
-        let val = match (raw >> PERF_MEM_LVL_SHIFT) as u32 {
-            bits if bits & PERF_MEM_LVL_NA       > 0 => Self::Na,
-            bits if bits & PERF_MEM_LVL_HIT      > 0 => Self::Hit,
-            bits if bits & PERF_MEM_LVL_MISS     > 0 => Self::Miss,
-            bits if bits & PERF_MEM_LVL_L1       > 0 => Self::L1,
-            bits if bits & PERF_MEM_LVL_LFB      > 0 => Self::Lfb,
-            bits if bits & PERF_MEM_LVL_L2       > 0 => Self::L2,
-            bits if bits & PERF_MEM_LVL_L3       > 0 => Self::L3,
-            bits if bits & PERF_MEM_LVL_LOC_RAM  > 0 => Self::LocRam,
-            bits if bits & PERF_MEM_LVL_REM_RAM1 > 0 => Self::RemRam1,
-            bits if bits & PERF_MEM_LVL_REM_RAM2 > 0 => Self::RemRam2,
-            bits if bits & PERF_MEM_LVL_REM_CCE1 > 0 => Self::RemCce1,
-            bits if bits & PERF_MEM_LVL_REM_CCE2 > 0 => Self::RemCce2,
-            bits if bits & PERF_MEM_LVL_IO       > 0 => Self::Io,
-            bits if bits & PERF_MEM_LVL_UNC      > 0 => Self::Unc,
-            bits => unreachable!("mem_lvl bits: {}", bits),
+        let val = match raw as u32 {
+            #[cfg(feature = "linux-4.14")]
+            PERF_MEM_LVLNUM_L1        => Self::L1,
+            #[cfg(feature = "linux-4.14")]
+            PERF_MEM_LVLNUM_L2        => Self::L2,
+            #[cfg(feature = "linux-4.14")]
+            PERF_MEM_LVLNUM_L3        => Self::L3,
+            #[cfg(feature = "linux-4.14")]
+            PERF_MEM_LVLNUM_L4        => Self::L4,
+            #[cfg(feature = "linux-6.3")]
+            PERF_MEM_LVLNUM_CXL       => Self::Cxl,
+            #[cfg(feature = "linux-6.3")]
+            PERF_MEM_LVLNUM_IO        => Self::Io,
+            #[cfg(feature = "linux-4.14")]
+            PERF_MEM_LVLNUM_ANY_CACHE => Self::AnyCache,
+            #[cfg(feature = "linux-4.14")]
+            PERF_MEM_LVLNUM_LFB       => Self::Lfb,
+            #[cfg(feature = "linux-4.14")]
+            PERF_MEM_LVLNUM_RAM       => Self::Ram,
+            #[cfg(feature = "linux-4.14")]
+            PERF_MEM_LVLNUM_PMEM      => Self::Pmem,
+            #[cfg(feature = "linux-4.14")]
+            PERF_MEM_LVLNUM_NA        => Self::Na,
+            _ => Self::Unknown(raw),
         };
         val
     }
-}
 
-impl MemSnoop {
-    pub(crate) fn from_raw(raw: u64) -> Self {
+    pub(crate) const fn to_raw(self) -> u8 {
         #[rustfmt::skip]
-        let val = match (raw >> PERF_MEM_SNOOP_SHIFT) as u32 {
-            bits if bits & PERF_MEM_SNOOP_NA   > 0 => Self::Na,
-            bits if bits & PERF_MEM_SNOOP_NONE > 0 => Self::None,
-            bits if bits & PERF_MEM_SNOOP_HIT  > 0 => Self::Hit,
-            bits if bits & PERF_MEM_SNOOP_MISS > 0 => Self::Miss,
-            bits if bits & PERF_MEM_SNOOP_HITM > 0 => Self::Hitm,
-            bits => unreachable!("mem_snoop bits: {}", bits),
+        let val: u32 = match self {
+            #[cfg(feature = "linux-4.14")]
+            Self::L1       => PERF_MEM_LVLNUM_L1,
+            #[cfg(feature = "linux-4.14")]
+            Self::L2       => PERF_MEM_LVLNUM_L2,
+            #[cfg(feature = "linux-4.14")]
+            Self::L3       => PERF_MEM_LVLNUM_L3,
+            #[cfg(feature = "linux-4.14")]
+            Self::L4       => PERF_MEM_LVLNUM_L4,
+            #[cfg(feature = "linux-6.3")]
+            Self::Cxl      => PERF_MEM_LVLNUM_CXL,
+            #[cfg(feature = "linux-6.3")]
+            Self::Io       => PERF_MEM_LVLNUM_IO,
+            #[cfg(feature = "linux-4.14")]
+            Self::AnyCache => PERF_MEM_LVLNUM_ANY_CACHE,
+            #[cfg(feature = "linux-4.14")]
+            Self::Lfb      => PERF_MEM_LVLNUM_LFB,
+            #[cfg(feature = "linux-4.14")]
+            Self::Ram      => PERF_MEM_LVLNUM_RAM,
+            #[cfg(feature = "linux-4.14")]
+            Self::Pmem     => PERF_MEM_LVLNUM_PMEM,
+            #[cfg(feature = "linux-4.14")]
+            Self::Na       => PERF_MEM_LVLNUM_NA,
+            Self::Unknown(raw) => raw as _,
         };
-        val
+        val as _
     }
 }
 
-impl MemLock {
-    pub(crate) fn from_raw(raw: u64) -> Self {
+impl MemHops {
+    pub(crate) const fn from_raw(raw: u8) -> Self {
         #[rustfmt::skip]
-        let val = match (raw >> PERF_MEM_LOCK_SHIFT) as u32 {
-            bits if bits & PERF_MEM_LOCK_NA     > 0 => Self::Na,
-            bits if bits & PERF_MEM_LOCK_LOCKED > 0 => Self::Locked,
-            bits => unreachable!("mem_lock bits: {}", bits),
+        let val = match raw as u32 {
+            #[cfg(feature = "linux-5.16")]
+            PERF_MEM_HOPS_0 => Self::Hops0,
+            #[cfg(feature = "linux-6.0")]
+            PERF_MEM_HOPS_1 => Self::Hops1,
+            #[cfg(feature = "linux-6.0")]
+            PERF_MEM_HOPS_2 => Self::Hops2,
+            #[cfg(feature = "linux-6.0")]
+            PERF_MEM_HOPS_3 => Self::Hops3,
+            _ => Self::Unknown(raw),
         };
         val
     }
-}
 
-impl MemDtlb {
-    pub(crate) fn from_raw(raw: u64) -> Self {
+    pub(crate) const fn to_raw(self) -> u8 {
         #[rustfmt::skip]
-        let val = match (raw >> PERF_MEM_TLB_SHIFT) as u32 {
-            bits if bits & PERF_MEM_TLB_NA   > 0 => Self::Na,
-            bits if bits & PERF_MEM_TLB_HIT  > 0 => Self::Hit,
-            bits if bits & PERF_MEM_TLB_MISS > 0 => Self::Miss,
-            bits if bits & PERF_MEM_TLB_L1   > 0 => Self::L1,
-            bits if bits & PERF_MEM_TLB_L2   > 0 => Self::L2,
-            bits if bits & PERF_MEM_TLB_WK   > 0 => Self::Wk,
-            bits if bits & PERF_MEM_TLB_OS   > 0 => Self::Os,
-            bits => unreachable!("mem_dtlb bits: {}", bits),
+        let val: u32 = match self {
+            #[cfg(feature = "linux-5.16")]
+            Self::Hops0 => PERF_MEM_HOPS_0,
+            #[cfg(feature = "linux-6.0")]
+            Self::Hops1 => PERF_MEM_HOPS_1,
+            #[cfg(feature = "linux-6.0")]
+            Self::Hops2 => PERF_MEM_HOPS_2,
+            #[cfg(feature = "linux-6.0")]
+            Self::Hops3 => PERF_MEM_HOPS_3,
+            Self::Unknown(raw) => raw as _,
         };
-        val
+        val as _
     }
 }
 
 impl DataSrc {
-    pub(crate) fn from_raw(raw: u64) -> Self {
+    pub(crate) const fn from_raw(raw: u64) -> Self {
+        let lvl_num = field(raw, MEM_LVLNUM_SHIFT, MEM_LVLNUM_MASK) as u8;
+        let hops = field(raw, MEM_HOPS_SHIFT, MEM_HOPS_MASK) as u8;
         #[rustfmt::skip]
         let val = Self {
-            mem_op:    MemOp   ::from_raw(raw),
-            mem_lvl:   MemLvl  ::from_raw(raw),
-            mem_snoop: MemSnoop::from_raw(raw),
-            mem_lock:  MemLock ::from_raw(raw),
-            mem_dtlb:  MemDtlb ::from_raw(raw),
+            mem_op:      MemOp    ::from_bits_retain(field(raw, PERF_MEM_OP_SHIFT,    MEM_OP_MASK)    as _),
+            mem_lvl:     MemLvl   ::from_bits_retain(field(raw, PERF_MEM_LVL_SHIFT,   MEM_LVL_MASK)   as _),
+            mem_snoop:   MemSnoop ::from_bits_retain(field(raw, PERF_MEM_SNOOP_SHIFT, MEM_SNOOP_MASK) as _),
+            mem_lock:    MemLock  ::from_bits_retain(field(raw, PERF_MEM_LOCK_SHIFT,  MEM_LOCK_MASK)  as _),
+            mem_dtlb:    MemDtlb  ::from_bits_retain(field(raw, PERF_MEM_TLB_SHIFT,   MEM_DTLB_MASK)  as _),
+            mem_lvl_num: if lvl_num == 0 { None } else { Some(MemLvlNum::from_raw(lvl_num)) },
+            mem_remote:  field(raw, MEM_REMOTE_SHIFT, 1) == 1,
+            mem_snoopx:  MemSnoopX::from_bits_retain(field(raw, MEM_SNOOPX_SHIFT, MEM_SNOOPX_MASK) as _),
+            mem_blk:     MemBlk   ::from_bits_retain(field(raw, MEM_BLK_SHIFT,    MEM_BLK_MASK)    as _),
+            mem_hops:    if hops == 0 { None } else { Some(MemHops::from_raw(hops)) },
+            mem_rsvd:    field(raw, MEM_RSVD_SHIFT, MEM_RSVD_MASK) as _,
+        };
+        val
+    }
+
+    pub(crate) const fn to_raw(&self) -> u64 {
+        let lvl_num = match self.mem_lvl_num {
+            Some(it) => it.to_raw(),
+            None => 0,
+        };
+        let hops = match self.mem_hops {
+            Some(it) => it.to_raw(),
+            None => 0,
         };
+        #[rustfmt::skip]
+        let val = (self.mem_op.bits()     as u64 & MEM_OP_MASK)     << PERF_MEM_OP_SHIFT
+            | (self.mem_lvl.bits()        as u64 & MEM_LVL_MASK)    << PERF_MEM_LVL_SHIFT
+            | (self.mem_snoop.bits()      as u64 & MEM_SNOOP_MASK)  << PERF_MEM_SNOOP_SHIFT
+            | (self.mem_lock.bits()       as u64 & MEM_LOCK_MASK)   << PERF_MEM_LOCK_SHIFT
+            | (self.mem_dtlb.bits()       as u64 & MEM_DTLB_MASK)   << PERF_MEM_TLB_SHIFT
+            | (lvl_num                    as u64 & MEM_LVLNUM_MASK) << MEM_LVLNUM_SHIFT
+            | (self.mem_remote            as u64)                   << MEM_REMOTE_SHIFT
+            | (self.mem_snoopx.bits()     as u64 & MEM_SNOOPX_MASK) << MEM_SNOOPX_SHIFT
+            | (self.mem_blk.bits()        as u64 & MEM_BLK_MASK)    << MEM_BLK_SHIFT
+            | (hops                       as u64 & MEM_HOPS_MASK)   << MEM_HOPS_SHIFT
+            | (self.mem_rsvd              as u64 & MEM_RSVD_MASK)   << MEM_RSVD_SHIFT;
         val
     }
+
+    /// Opcode, as `perf mem report` prints it
+    pub const fn op(&self) -> &'static str {
+        let op = self.mem_op;
+        if op.contains(MemOp::LOAD) {
+            "LOAD"
+        } else if op.contains(MemOp::STORE) {
+            "STORE"
+        } else if op.contains(MemOp::PFETCH) {
+            "PFETCH"
+        } else if op.contains(MemOp::EXEC) {
+            "EXEC"
+        } else {
+            "N/A"
+        }
+    }
+
+    /// The `Memory access` column of `perf mem report`, e.g. `L3 hit` or
+    /// `Remote socket, same board RAM hit`
+    pub fn access(&self) -> String {
+        const LVL: [&str; 14] = [
+            "N/A",
+            "HIT",
+            "MISS",
+            "L1",
+            "LFB/MAB",
+            "L2",
+            "L3",
+            "Local RAM",
+            "Remote RAM (1 hop)",
+            "Remote RAM (2 hops)",
+            "Remote Cache (1 hop)",
+            "Remote Cache (2 hops)",
+            "I/O",
+            "Uncached",
+        ];
+        const LVL_NUM: [&str; 16] = [
+            "",
+            "L1",
+            "L2",
+            "L3",
+            "L4",
+            "L2 MHB",
+            "Memory-side Cache",
+            "",
+            "Uncached",
+            "CXL",
+            "I/O",
+            "Any cache",
+            "LFB/MAB",
+            "RAM",
+            "PMEM",
+            "N/A",
+        ];
+        const HOPS: [&str; 5] = [
+            "N/A",
+            "core, same node",
+            "node, same socket",
+            "socket, same board",
+            "board",
+        ];
+
+        let hit_miss = if self.mem_lvl.contains(MemLvl::HIT) {
+            " hit"
+        } else if self.mem_lvl.contains(MemLvl::MISS) {
+            " miss"
+        } else {
+            ""
+        };
+
+        let mut out = String::new();
+        let lvl_num = self.mem_lvl_num.map_or(0, MemLvlNum::to_raw) as usize;
+        if lvl_num != 0 && lvl_num != LVL_NUM.len() - 1 {
+            if self.mem_remote {
+                out.push_str("Remote ");
+            }
+            let hops = self.mem_hops.map_or(0, MemHops::to_raw) as usize;
+            if hops != 0 {
+                match HOPS.get(hops) {
+                    Some(name) => write!(out, "{} ", name).unwrap(),
+                    None => write!(out, "Unknown hops {} ", hops).unwrap(),
+                }
+            }
+            match LVL_NUM[lvl_num] {
+                "" => write!(out, "Unknown level {}", lvl_num).unwrap(),
+                name => out.push_str(name),
+            }
+            out.push_str(hit_miss);
+            return out;
+        }
+
+        let lvl = self.mem_lvl - (MemLvl::NA | MemLvl::HIT | MemLvl::MISS);
+        if join_bits(&mut out, lvl.bits() as _, &LVL) {
+            out.push_str(hit_miss);
+            out
+        } else {
+            "N/A".to_string()
+        }
+    }
+
+    /// The `Snoop` column of `perf mem report`, e.g. `HitM or Fwd`
+    pub fn snoop(&self) -> String {
+        let mut out = String::new();
+        join_bits(
+            &mut out,
+            self.mem_snoop.bits() as _,
+            &["N/A", "None", "Hit", "Miss", "HitM"],
+        );
+        join_bits(&mut out, self.mem_snoopx.bits() as _, &["Fwd", "Peer"]);
+        if out.is_empty() {
+            out.push_str("N/A");
+        }
+        out
+    }
+
+    /// The `TLB access` column of `perf mem report`, e.g. `L1 or L2 hit`
+    pub fn tlb(&self) -> String {
+        let mut out = String::new();
+        let dtlb = self.mem_dtlb - (MemDtlb::HIT | MemDtlb::MISS);
+        #[rustfmt::skip]
+        let names = ["N/A", "HIT", "MISS", "L1", "L2", "Walker", "Fault"];
+        if !join_bits(&mut out, dtlb.bits() as _, &names) {
+            out.push_str("N/A");
+        }
+        if self.mem_dtlb.contains(MemDtlb::HIT) {
+            out.push_str(" hit");
+        }
+        if self.mem_dtlb.contains(MemDtlb::MISS) {
+            out.push_str(" miss");
+        }
+        out
+    }
+
+    /// The `Locked` column of `perf mem report`
+    pub const fn locked(&self) -> &'static str {
+        if self.mem_lock.contains(MemLock::NA) {
+            "N/A"
+        } else if self.mem_lock.contains(MemLock::LOCKED) {
+            "Yes"
+        } else {
+            "No"
+        }
+    }
+
+    /// The `Blocked` column of `perf mem report`, e.g. `Data Addr`
+    pub fn blocked(&self) -> String {
+        // bits of `PERF_MEM_BLK_*`, named here as they are gated
+        let blk = self.mem_blk.bits();
+        if blk == 0 || blk & 1 != 0 {
+            return "N/A".to_string();
+        }
+        let mut out = vec![];
+        if blk & 2 != 0 {
+            out.push("Data");
+        }
+        if blk & 4 != 0 {
+            out.push("Addr");
+        }
+        out.join(" ")
+    }
+}
+
+/// Join the names of the set bits with ` or `, as perf does,
+/// returns whether any name was written.
+fn join_bits(out: &mut String, bits: u64, names: &[&str]) -> bool {
+    let mut written = false;
+    for (i, name) in names.iter().enumerate() {
+        if bits & (1 << i) == 0 {
+            continue;
+        }
+        if !out.is_empty() {
+            out.push_str(" or ");
+        }
+        out.push_str(name);
+        written = true;
+    }
+    written
+}
+
+/// Same as `perf script -F data_src`, e.g.
+/// `|OP LOAD|LVL L1 hit|SNP None|TLB L1 or L2 hit|LCK No|BLK  N/A`
+impl Display for DataSrc {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "|OP {}|LVL {}|SNP {}|TLB {}|LCK {}|BLK  {}",
+            self.op(),
+            self.access(),
+            self.snoop(),
+            self.tlb(),
+            self.locked(),
+            self.blocked(),
+        )
+    }
 }
@@ -13,17 +13,30 @@
 // see <https://www.gnu.org/licenses/>.
 
 mod abi_and_regs;
+mod branch_stack;
 mod data_src;
 mod raw;
+mod regs;
+mod sample_ref;
+#[cfg(feature = "linux-3.13")]
+mod transaction;
 mod weight;
 
+use crate::sampling::record::encode::Writer;
+use crate::sampling::record::read_format::Layout;
+use crate::sampling::record::ParseContext;
 use crate::sampling::SamplerGroupStat;
 use crate::syscall::bindings::*;
 pub use abi_and_regs::*;
+pub use branch_stack::*;
 pub use data_src::*;
+pub use regs::*;
+pub use sample_ref::*;
+#[cfg(feature = "linux-3.13")]
+pub use transaction::*;
 pub use weight::*;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Body {
     #[cfg(feature = "linux-3.12")]
     pub sample_id: Option<u64>,
@@ -36,15 +49,18 @@ pub struct Body {
     pub stream_id: Option<u64>,
     pub cpu: Option<u32>,
     pub period: Option<u64>,
+    /// Fields not in `read_format` are 0, and without `PERF_FORMAT_GROUP`
+    /// the only member is the event itself
     pub v: Option<SamplerGroupStat>,
     pub ips: Option<Vec<u64>>,
     pub data_raw: Option<Vec<u8>>,
+    pub branch_stack: Option<BranchStack>,
     pub abi_and_regs_user: Option<AbiAndRegs>,
     pub data_stack_user: Option<Vec<u8>>,
     pub weight: Option<Weight>,
     pub data_src: Option<DataSrc>,
     #[cfg(feature = "linux-3.13")]
-    pub transaction: Option<u64>,
+    pub transaction: Option<Transaction>,
     #[cfg(feature = "linux-3.19")]
     pub abi_and_regs_intr: Option<AbiAndRegs>,
     #[cfg(feature = "linux-4.14")]
@@ -61,8 +77,10 @@ impl Body {
     pub(crate) unsafe fn from_ptr(
         ptr: *const u8,
         sample_type: u64,
-        regs_user_len: usize,
-        #[cfg(feature = "linux-3.19")] regs_intr_len: usize,
+        read_format: u64,
+        branch_hw_index: bool,
+        regs_user: RegMask,
+        #[cfg(feature = "linux-3.19")] regs_intr: RegMask,
     ) -> Self {
         let mut raw = raw::Raw {
             read_ptr: ptr,
@@ -81,33 +99,27 @@ impl Body {
             stream_id: raw.stream_id().cloned(),
             cpu: raw.cpu().cloned(),
             period: raw.period().cloned(),
-            v: raw.v().map(|(h, b)| SamplerGroupStat::from_raw(h, b)),
+            v: {
+                let layout = Layout::new(read_format);
+                raw.v(layout).map(|it| layout.decode(it))
+            },
             ips: raw.ips().map(|it| it.to_vec()),
             data_raw: raw.data_raw().map(|it| it.to_vec()),
+            branch_stack: raw.branch_stack(branch_hw_index).map(BranchStack::from_raw),
             abi_and_regs_user: raw
-                .abi_and_regs_user(regs_user_len)
-                .map(AbiAndRegs::from_raw),
+                .abi_and_regs_user(regs_user.len())
+                .map(|it| AbiAndRegs::from_raw(it, regs_user)),
             data_stack_user: raw.data_stack_user().map(|it| it.to_vec()),
-            weight: raw.weight().map(|it| {
-                let repr = match sample_type {
-                    // mask may be u64 or u32 in different linux headers
-                    #[allow(clippy::unnecessary_cast)]
-                    st if (st & PERF_SAMPLE_WEIGHT as u64) > 0 => WeightRepr::Full,
-                    #[cfg(feature = "linux-5.12")]
-                    // mask may be u64 or u32 in different linux headers
-                    #[allow(clippy::unnecessary_cast)]
-                    st if (st & PERF_SAMPLE_WEIGHT_STRUCT as u64) > 0 => WeightRepr::Vars,
-                    _ => unreachable!(),
-                };
-                Weight::from_raw(*it, repr)
-            }),
+            weight: raw
+                .weight()
+                .map(|it| Weight::from_raw(*it, weight_repr(sample_type))),
             data_src: raw.data_src().cloned().map(DataSrc::from_raw),
             #[cfg(feature = "linux-3.13")]
-            transaction: raw.transaction().cloned(),
+            transaction: raw.transaction().cloned().map(Transaction::from_raw),
             #[cfg(feature = "linux-3.19")]
             abi_and_regs_intr: raw
-                .abi_and_regs_intr(regs_intr_len)
-                .map(AbiAndRegs::from_raw),
+                .abi_and_regs_intr(regs_intr.len())
+                .map(|it| AbiAndRegs::from_raw(it, regs_intr)),
             #[cfg(feature = "linux-4.14")]
             phys_addr: raw.phys_addr().cloned(),
             #[cfg(feature = "linux-5.7")]
@@ -119,3 +131,130 @@ impl Body {
         }
     }
 }
+
+impl Body {
+    /// `data_raw` is padded with 0 to keep 64-bit alignment like the kernel does,
+    /// and `data_stack_user` is written with its size rounded up to 64-bit.
+    pub(crate) fn write_to(&self, w: &mut Writer, ctx: &ParseContext) {
+        // mask may be u64 or u32 in different linux headers
+        #[allow(clippy::unnecessary_cast)]
+        let enabled = |mask: perf_event_sample_format| (ctx.sample_type & mask as u64) > 0;
+        let u64_field = |w: &mut Writer, mask, val: Option<u64>| {
+            if enabled(mask) {
+                w.u64(val.unwrap_or_default());
+            }
+        };
+
+        #[cfg(feature = "linux-3.12")]
+        u64_field(w, PERF_SAMPLE_IDENTIFIER, self.sample_id);
+        u64_field(w, PERF_SAMPLE_IP, self.ip);
+        if enabled(PERF_SAMPLE_TID) {
+            w.u32(self.pid.unwrap_or_default());
+            w.u32(self.tid.unwrap_or_default());
+        }
+        u64_field(w, PERF_SAMPLE_TIME, self.time);
+        u64_field(w, PERF_SAMPLE_ADDR, self.addr);
+        u64_field(w, PERF_SAMPLE_ID, self.id);
+        u64_field(w, PERF_SAMPLE_STREAM_ID, self.stream_id);
+        if enabled(PERF_SAMPLE_CPU) {
+            w.u32(self.cpu.unwrap_or_default());
+            w.u32(0); // res
+        }
+        u64_field(w, PERF_SAMPLE_PERIOD, self.period);
+        if enabled(PERF_SAMPLE_READ) {
+            match &self.v {
+                Some(v) => w.read_format(v, ctx.read_format),
+                None => w.read_format(&SamplerGroupStat::default(), ctx.read_format),
+            }
+        }
+        if enabled(PERF_SAMPLE_CALLCHAIN) {
+            let ips = self.ips.as_deref().unwrap_or_default();
+            w.u64(ips.len() as _);
+            ips.iter().for_each(|it| w.u64(*it));
+        }
+        if enabled(PERF_SAMPLE_RAW) {
+            let data = self.data_raw.as_deref().unwrap_or_default();
+            // u32 size and data[size] are padded to 64-bit together
+            let size = (data.len() + 4).next_multiple_of(8) - 4;
+            w.u32(size as _);
+            w.bytes(data);
+            w.bytes(&vec![0; size - data.len()]);
+        }
+        if enabled(PERF_SAMPLE_BRANCH_STACK) {
+            match &self.branch_stack {
+                Some(it) => it.write_to(w, ctx.branch_hw_index),
+                None => {
+                    w.u64(0);
+                    if ctx.branch_hw_index {
+                        w.u64(0);
+                    }
+                }
+            }
+        }
+        if enabled(PERF_SAMPLE_REGS_USER) {
+            match &self.abi_and_regs_user {
+                Some(it) => it.write_to(w),
+                None => w.u64(PERF_SAMPLE_REGS_ABI_NONE as _),
+            }
+        }
+        if enabled(PERF_SAMPLE_STACK_USER) {
+            let data = self.data_stack_user.as_deref().unwrap_or_default();
+            let size = data.len().next_multiple_of(8);
+            w.u64(size as _);
+            w.bytes(data);
+            w.bytes(&vec![0; size - data.len()]);
+            if size != 0 {
+                w.u64(data.len() as _); // dyn_size
+            }
+        }
+        #[cfg(feature = "linux-5.12")]
+        let weight_enabled = enabled(PERF_SAMPLE_WEIGHT) || enabled(PERF_SAMPLE_WEIGHT_STRUCT);
+        #[cfg(not(feature = "linux-5.12"))]
+        let weight_enabled = enabled(PERF_SAMPLE_WEIGHT);
+        if weight_enabled {
+            match &self.weight {
+                Some(it) => it.write_to(w),
+                None => w.u64(0),
+            }
+        }
+        u64_field(
+            w,
+            PERF_SAMPLE_DATA_SRC,
+            self.data_src.as_ref().map(DataSrc::to_raw),
+        );
+        #[cfg(feature = "linux-3.13")]
+        u64_field(
+            w,
+            PERF_SAMPLE_TRANSACTION,
+            self.transaction.as_ref().map(Transaction::to_raw),
+        );
+        #[cfg(feature = "linux-3.19")]
+        if enabled(PERF_SAMPLE_REGS_INTR) {
+            match &self.abi_and_regs_intr {
+                Some(it) => it.write_to(w),
+                None => w.u64(PERF_SAMPLE_REGS_ABI_NONE as _),
+            }
+        }
+        #[cfg(feature = "linux-4.14")]
+        u64_field(w, PERF_SAMPLE_PHYS_ADDR, self.phys_addr);
+        #[cfg(feature = "linux-5.7")]
+        u64_field(w, PERF_SAMPLE_CGROUP, self.cgroup);
+        #[cfg(feature = "linux-5.11")]
+        u64_field(w, PERF_SAMPLE_DATA_PAGE_SIZE, self.data_page_size);
+        #[cfg(feature = "linux-5.11")]
+        u64_field(w, PERF_SAMPLE_CODE_PAGE_SIZE, self.code_page_size);
+    }
+}
+
+pub(crate) fn weight_repr(sample_type: u64) -> WeightRepr {
+    match sample_type {
+        // mask may be u64 or u32 in different linux headers
+        #[allow(clippy::unnecessary_cast)]
+        st if (st & PERF_SAMPLE_WEIGHT as u64) > 0 => WeightRepr::Full,
+        #[cfg(feature = "linux-5.12")]
+        // mask may be u64 or u32 in different linux headers
+        #[allow(clippy::unnecessary_cast)]
+        st if (st & PERF_SAMPLE_WEIGHT_STRUCT as u64) > 0 => WeightRepr::Vars,
+        _ => unreachable!(),
+    }
+}
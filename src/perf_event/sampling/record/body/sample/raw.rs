@@ -59,7 +59,7 @@ struct {
 */
 
 use crate::infra::{SliceExt, Vla, WrapOption};
-use crate::sampling::{ReadFormatHead, ReadFormatValue};
+use crate::sampling::record::read_format::Layout;
 use crate::syscall::bindings::*;
 use std::mem::size_of;
 use std::ops::Not;
@@ -120,20 +120,13 @@ impl Raw {
 
     gen_fn! { u64, period    PERF_SAMPLE_PERIOD     }
 
-    pub unsafe fn v(&mut self) -> Option<(&ReadFormatHead, &[ReadFormatValue])> {
+    pub unsafe fn v(&mut self, layout: Layout) -> Option<&[u64]> {
         if self.is_enabled(PERF_SAMPLE_READ).not() {
             return None;
         }
-
-        let head_ptr = self.read_ptr as *const ReadFormatHead;
-        let head = &*head_ptr;
-
-        let body_ptr = head_ptr.add(1) as *const ReadFormatValue;
-        let slice = slice::from_raw_parts(body_ptr, head.members_len as _);
-
-        self.read_ptr = slice.follow_mem_ptr() as _;
-
-        (head, slice).wrap_some()
+        let words = layout.words(self.read_ptr);
+        self.read_ptr = words.follow_mem_ptr() as _;
+        words.wrap_some()
     }
 
     pub unsafe fn ips(&mut self) -> Option<&[u64]> {
@@ -159,11 +152,25 @@ impl Raw {
         slice.wrap_some()
     }
 
-    /*
-    TODO: if PERF_SAMPLE_BRANCH_STACK
-    u64    bnr;
-    struct perf_branch_entry lbr[bnr];
-    */
+    pub unsafe fn branch_stack(
+        &mut self,
+        hw_index: bool,
+    ) -> Option<(Option<&u64>, &[perf_branch_entry])> {
+        if self.is_enabled(PERF_SAMPLE_BRANCH_STACK).not() {
+            return None;
+        }
+
+        let len_ptr = self.read_ptr as *const u64;
+        // u64 hw_idx follows bnr if PERF_SAMPLE_BRANCH_HW_INDEX
+        let (hw_idx, entries_ptr) = if hw_index {
+            (len_ptr.add(1).as_ref(), len_ptr.add(2))
+        } else {
+            (None, len_ptr.add(1))
+        };
+        let entries = slice::from_raw_parts(entries_ptr as *const perf_branch_entry, *len_ptr as _);
+        self.read_ptr = entries.follow_mem_ptr() as _;
+        (hw_idx, entries).wrap_some()
+    }
 
     pub unsafe fn abi_and_regs_user(&mut self, regs_len: usize) -> Option<(&u64, &[u64])> {
         if self.is_enabled(PERF_SAMPLE_REGS_USER).not() {
@@ -0,0 +1,145 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+
+mod aarch64;
+mod riscv64;
+mod x86;
+mod x86_64;
+
+pub use aarch64::*;
+pub use riscv64::*;
+pub use x86::*;
+pub use x86_64::*;
+
+use super::Abi;
+
+/// Registers of the architecture this crate is built for
+#[cfg(target_arch = "x86_64")]
+pub type Reg = X86_64Reg;
+#[cfg(target_arch = "x86")]
+pub type Reg = X86Reg;
+#[cfg(target_arch = "aarch64")]
+pub type Reg = Aarch64Reg;
+#[cfg(target_arch = "riscv64")]
+pub type Reg = Riscv64Reg;
+
+/// Register numbering of `asm/perf_regs.h`
+pub trait Register: Copy {
+    /// Bit of this register in `sample_regs_user` and `sample_regs_intr`
+    fn index(self) -> u32;
+
+    /// Bit of this register for 32-bit tasks (`PERF_SAMPLE_REGS_ABI_32`),
+    /// `None` if they don't have it
+    fn index_abi32(self) -> Option<u32>;
+}
+
+/// Registers to sample, e.g. `RegMask::of([Reg::Ip, Reg::Sp, Reg::Bp])`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct RegMask(u64);
+
+impl RegMask {
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// Raw `sample_regs_*` bits
+    pub const fn from_bits(bits: u64) -> Self {
+        Self(bits)
+    }
+
+    pub fn of<R: Register>(regs: impl IntoIterator<Item = R>) -> Self {
+        regs.into_iter().fold(Self::empty(), Self::with)
+    }
+
+    #[must_use]
+    pub fn with<R: Register>(self, reg: R) -> Self {
+        Self(self.0 | 1 << reg.index())
+    }
+
+    pub const fn bits(self) -> u64 {
+        self.0
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Number of registers sampled
+    pub const fn len(self) -> usize {
+        self.0.count_ones() as _
+    }
+
+    pub fn contains<R: Register>(self, reg: R) -> bool {
+        self.position(reg.index()).is_some()
+    }
+
+    /// Position of bit `index` in the sampled registers,
+    /// which are ordered by their bits.
+    const fn position(self, index: u32) -> Option<usize> {
+        if index >= u64::BITS || self.0 & 1 << index == 0 {
+            return None;
+        }
+        let below = self.0 & ((1 << index) - 1);
+        Some(below.count_ones() as _)
+    }
+}
+
+impl From<RegMask> for u64 {
+    fn from(mask: RegMask) -> Self {
+        mask.bits()
+    }
+}
+
+/// Sampled registers accessed by name
+#[derive(Debug, Clone)]
+pub struct RegsView<'a> {
+    abi: Abi,
+    mask: RegMask,
+    values: &'a [u64],
+}
+
+impl<'a> RegsView<'a> {
+    pub(crate) const fn new(abi: Abi, mask: RegMask, values: &'a [u64]) -> Self {
+        Self { abi, mask, values }
+    }
+
+    pub const fn abi(&self) -> &Abi {
+        &self.abi
+    }
+
+    pub const fn mask(&self) -> RegMask {
+        self.mask
+    }
+
+    pub const fn values(&self) -> &'a [u64] {
+        self.values
+    }
+
+    /// Value of `reg`, in the 32-bit layout if the task was 32-bit,
+    /// `None` if it was not sampled or the task has no such register.
+    pub fn get<R: Register>(&self, reg: R) -> Option<u64> {
+        match self.abi {
+            Abi::AbiNone => None,
+            Abi::Abi32 => {
+                let index = reg.index_abi32()?;
+                let pos = self.mask.position(index)?;
+                self.values.get(pos).map(|it| *it as u32 as _)
+            }
+            Abi::Abi64 | Abi::Unknown(_) => {
+                let pos = self.mask.position(reg.index())?;
+                self.values.get(pos).copied()
+            }
+        }
+    }
+}
@@ -0,0 +1,62 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+
+use super::Register;
+
+/// `PERF_REG_X86_*` of x86_64
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum X86_64Reg {
+    Ax = 0,
+    Bx = 1,
+    Cx = 2,
+    Dx = 3,
+    Si = 4,
+    Di = 5,
+    Bp = 6,
+    Sp = 7,
+    Ip = 8,
+    Flags = 9,
+    Cs = 10,
+    Ss = 11,
+    /// Not sampled by x86_64 kernels, `perf_event_open` fails with `EINVAL`
+    /// (`REG_NOSUPPORT`) when the mask contains it.
+    Ds = 12,
+    /// Rejected like `Ds`
+    Es = 13,
+    /// Rejected like `Ds`
+    Fs = 14,
+    /// Rejected like `Ds`
+    Gs = 15,
+    R8 = 16,
+    R9 = 17,
+    R10 = 18,
+    R11 = 19,
+    R12 = 20,
+    R13 = 21,
+    R14 = 22,
+    R15 = 23,
+}
+
+impl Register for X86_64Reg {
+    fn index(self) -> u32 {
+        self as _
+    }
+
+    fn index_abi32(self) -> Option<u32> {
+        // 32-bit tasks stop at `Gs`
+        let index = self as u32;
+        (index < Self::R8 as u32).then_some(index)
+    }
+}
@@ -0,0 +1,255 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+
+use super::raw::Raw;
+#[cfg(feature = "linux-3.13")]
+use super::Transaction;
+use super::{weight_repr, Abi, BranchStack, DataSrc, RegMask, RegsView, Weight};
+use crate::sampling::record::read_format::Layout;
+use crate::sampling::SamplerGroupStat;
+use std::marker::PhantomData;
+use std::slice;
+
+/// Fields of `PERF_RECORD_SAMPLE` in memory order
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+enum Field {
+    #[cfg(feature = "linux-3.12")]
+    SampleId,
+    Ip,
+    Pid,
+    Tid,
+    Time,
+    Addr,
+    Id,
+    StreamId,
+    Cpu,
+    Period,
+    V,
+    Ips,
+    DataRaw,
+    BranchStack,
+    AbiAndRegsUser,
+    DataStackUser,
+    Weight,
+    DataSrc,
+    #[cfg(feature = "linux-3.13")]
+    Transaction,
+    #[cfg(feature = "linux-3.19")]
+    AbiAndRegsIntr,
+    #[cfg(feature = "linux-4.14")]
+    PhysAddr,
+    #[cfg(feature = "linux-5.7")]
+    Cgroup,
+    #[cfg(feature = "linux-5.11")]
+    DataPageSize,
+    #[cfg(feature = "linux-5.11")]
+    CodePageSize,
+}
+
+/// Borrowed view of `PERF_RECORD_SAMPLE`, fields are located on access.
+#[derive(Clone, Copy)]
+pub struct SampleRef<'a> {
+    ptr: *const u8,
+    sample_type: u64,
+    read_format: u64,
+    branch_hw_index: bool,
+    regs_user: RegMask,
+    #[cfg(feature = "linux-3.19")]
+    regs_intr: RegMask,
+    _marker: PhantomData<&'a [u8]>,
+}
+
+macro_rules! gen_fn {
+    ($ty:ty, $name:ident $field:ident) => {
+        #[inline]
+        pub fn $name(&self) -> Option<$ty> {
+            unsafe { self.seek(Field::$field).$name().cloned() }
+        }
+    };
+}
+
+impl<'a> SampleRef<'a> {
+    /// # Safety
+    /// `ptr` must point to the body of a valid `PERF_RECORD_SAMPLE` which lives for `'a`
+    pub(crate) const unsafe fn from_ptr(
+        ptr: *const u8,
+        sample_type: u64,
+        read_format: u64,
+        branch_hw_index: bool,
+        regs_user: RegMask,
+        #[cfg(feature = "linux-3.19")] regs_intr: RegMask,
+    ) -> Self {
+        Self {
+            ptr,
+            sample_type,
+            read_format,
+            branch_hw_index,
+            regs_user,
+            #[cfg(feature = "linux-3.19")]
+            regs_intr,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Cursor at `target`, skipping all fields before it.
+    unsafe fn seek(&self, target: Field) -> Raw {
+        let mut raw = Raw {
+            read_ptr: self.ptr,
+            sample_type: self.sample_type,
+        };
+
+        macro_rules! skip {
+            ($field:ident, $skip:expr) => {
+                if target <= Field::$field {
+                    return raw;
+                }
+                $skip;
+            };
+        }
+
+        #[cfg(feature = "linux-3.12")]
+        skip!(SampleId, raw.sample_id());
+        skip!(Ip, raw.ip());
+        skip!(Pid, raw.pid());
+        skip!(Tid, raw.tid());
+        skip!(Time, raw.time());
+        skip!(Addr, raw.addr());
+        skip!(Id, raw.id());
+        skip!(StreamId, raw.stream_id());
+        skip!(Cpu, raw.cpu());
+        skip!(Period, raw.period());
+        skip!(V, raw.v(Layout::new(self.read_format)));
+        skip!(Ips, raw.ips());
+        skip!(DataRaw, raw.data_raw());
+        skip!(BranchStack, raw.branch_stack(self.branch_hw_index));
+        skip!(AbiAndRegsUser, raw.abi_and_regs_user(self.regs_user.len()));
+        skip!(DataStackUser, raw.data_stack_user());
+        skip!(Weight, raw.weight());
+        skip!(DataSrc, raw.data_src());
+        #[cfg(feature = "linux-3.13")]
+        skip!(Transaction, raw.transaction());
+        #[cfg(feature = "linux-3.19")]
+        skip!(AbiAndRegsIntr, raw.abi_and_regs_intr(self.regs_intr.len()));
+        #[cfg(feature = "linux-4.14")]
+        skip!(PhysAddr, raw.phys_addr());
+        #[cfg(feature = "linux-5.7")]
+        skip!(Cgroup, raw.cgroup());
+        #[cfg(feature = "linux-5.11")]
+        skip!(DataPageSize, raw.data_page_size());
+        raw
+    }
+
+    #[cfg(feature = "linux-3.12")]
+    gen_fn! { u64, sample_id SampleId }
+    gen_fn! { u64, ip        Ip       }
+    gen_fn! { u32, pid       Pid      }
+    gen_fn! { u32, tid       Tid      }
+    gen_fn! { u64, time      Time     }
+    gen_fn! { u64, addr      Addr     }
+    gen_fn! { u64, id        Id       }
+    gen_fn! { u64, stream_id StreamId }
+    gen_fn! { u32, cpu       Cpu      }
+    gen_fn! { u64, period    Period   }
+
+    pub fn v(&self) -> Option<SamplerGroupStat> {
+        let layout = Layout::new(self.read_format);
+        unsafe { self.seek(Field::V).v(layout) }.map(|it| layout.decode(it))
+    }
+
+    pub fn ips(&self) -> Option<&'a [u64]> {
+        unsafe { self.seek(Field::Ips).ips() }
+            .map(|it| unsafe { slice::from_raw_parts(it.as_ptr(), it.len()) })
+    }
+
+    pub fn data_raw(&self) -> Option<&'a [u8]> {
+        unsafe { self.seek(Field::DataRaw).data_raw() }
+            .map(|it| unsafe { slice::from_raw_parts(it.as_ptr(), it.len()) })
+    }
+
+    pub fn branch_stack(&self) -> Option<BranchStack> {
+        unsafe {
+            self.seek(Field::BranchStack)
+                .branch_stack(self.branch_hw_index)
+        }
+        .map(BranchStack::from_raw)
+    }
+
+    pub fn abi_and_regs_user(&self) -> Option<(Abi, &'a [u64])> {
+        unsafe {
+            self.seek(Field::AbiAndRegsUser)
+                .abi_and_regs_user(self.regs_user.len())
+        }
+        .map(|(abi, regs)| unsafe {
+            let regs = slice::from_raw_parts(regs.as_ptr(), regs.len());
+            (Abi::from_raw(*abi), regs)
+        })
+    }
+
+    /// `abi_and_regs_user` accessed by register name
+    pub fn regs_user(&self) -> Option<RegsView<'a>> {
+        self.abi_and_regs_user()
+            .map(|(abi, regs)| RegsView::new(abi, self.regs_user, regs))
+    }
+
+    pub fn data_stack_user(&self) -> Option<&'a [u8]> {
+        unsafe { self.seek(Field::DataStackUser).data_stack_user() }
+            .map(|it| unsafe { slice::from_raw_parts(it.as_ptr(), it.len()) })
+    }
+
+    pub fn weight(&self) -> Option<Weight> {
+        unsafe { self.seek(Field::Weight).weight() }
+            .map(|it| Weight::from_raw(*it, weight_repr(self.sample_type)))
+    }
+
+    pub fn data_src(&self) -> Option<DataSrc> {
+        unsafe { self.seek(Field::DataSrc).data_src() }
+            .cloned()
+            .map(DataSrc::from_raw)
+    }
+
+    #[cfg(feature = "linux-3.13")]
+    pub fn transaction(&self) -> Option<Transaction> {
+        unsafe { self.seek(Field::Transaction).transaction() }
+            .cloned()
+            .map(Transaction::from_raw)
+    }
+
+    #[cfg(feature = "linux-3.19")]
+    pub fn abi_and_regs_intr(&self) -> Option<(Abi, &'a [u64])> {
+        unsafe {
+            self.seek(Field::AbiAndRegsIntr)
+                .abi_and_regs_intr(self.regs_intr.len())
+        }
+        .map(|(abi, regs)| unsafe {
+            let regs = slice::from_raw_parts(regs.as_ptr(), regs.len());
+            (Abi::from_raw(*abi), regs)
+        })
+    }
+
+    /// `abi_and_regs_intr` accessed by register name
+    #[cfg(feature = "linux-3.19")]
+    pub fn regs_intr(&self) -> Option<RegsView<'a>> {
+        self.abi_and_regs_intr()
+            .map(|(abi, regs)| RegsView::new(abi, self.regs_intr, regs))
+    }
+
+    #[cfg(feature = "linux-4.14")]
+    gen_fn! { u64, phys_addr      PhysAddr     }
+    #[cfg(feature = "linux-5.7")]
+    gen_fn! { u64, cgroup         Cgroup       }
+    #[cfg(feature = "linux-5.11")]
+    gen_fn! { u64, data_page_size DataPageSize }
+    #[cfg(feature = "linux-5.11")]
+    gen_fn! { u64, code_page_size CodePageSize }
+}
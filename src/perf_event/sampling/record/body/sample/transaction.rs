@@ -0,0 +1,60 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+
+use crate::syscall::bindings::*;
+use bitflags::bitflags;
+
+bitflags! {
+    /// Where and why a transaction aborted (`PERF_TXN_*`)
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct TxnFlags: u32 {
+        /// From elision
+        const ELISION        = PERF_TXN_ELISION        as _;
+        /// From transaction
+        const TRANSACTION    = PERF_TXN_TRANSACTION    as _;
+        /// Instruction is related
+        const SYNC           = PERF_TXN_SYNC           as _;
+        /// Instruction not related
+        const ASYNC          = PERF_TXN_ASYNC          as _;
+        /// Retry possible
+        const RETRY          = PERF_TXN_RETRY          as _;
+        /// Conflict abort
+        const CONFLICT       = PERF_TXN_CONFLICT       as _;
+        /// Capacity write abort
+        const CAPACITY_WRITE = PERF_TXN_CAPACITY_WRITE as _;
+        /// Capacity read abort
+        const CAPACITY_READ  = PERF_TXN_CAPACITY_READ  as _;
+    }
+}
+
+/// Decoded `PERF_SAMPLE_TRANSACTION`, bits unknown to this crate are kept in `flags`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transaction {
+    pub flags: TxnFlags,
+    /// Abort code given by the user, e.g. the argument of `xabort`
+    pub abort_code: u32,
+}
+
+impl Transaction {
+    pub(crate) const fn from_raw(raw: u64) -> Self {
+        Self {
+            flags: TxnFlags::from_bits_retain(raw as _),
+            abort_code: ((raw & PERF_TXN_ABORT_MASK) >> PERF_TXN_ABORT_SHIFT) as _,
+        }
+    }
+
+    pub(crate) const fn to_raw(&self) -> u64 {
+        (self.abort_code as u64) << PERF_TXN_ABORT_SHIFT | self.flags.bits() as u64
+    }
+}
@@ -12,6 +12,7 @@
 // You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
 // see <https://www.gnu.org/licenses/>.
 
+use crate::sampling::record::encode::Writer;
 use crate::syscall::bindings::perf_sample_weight;
 
 #[derive(PartialEq, Eq, Clone, Debug)]
@@ -21,7 +22,7 @@ pub enum WeightRepr {
     Vars,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Weight {
     Full(u64),
     #[cfg(feature = "linux-5.12")]
@@ -49,4 +50,20 @@ impl Weight {
             WeightRepr::Full => Self::Full(raw),
         }
     }
+
+    pub(crate) fn write_to(&self, w: &mut Writer) {
+        match self {
+            Self::Full(full) => w.u64(*full),
+            #[cfg(feature = "linux-5.12")]
+            Self::Vars {
+                var1_dw,
+                var2_w,
+                var3_w,
+            } => {
+                w.u32(*var1_dw);
+                w.u16(*var2_w);
+                w.u16(*var3_w);
+            }
+        }
+    }
 }
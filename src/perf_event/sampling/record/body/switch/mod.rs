@@ -12,21 +12,110 @@
 // You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
 // see <https://www.gnu.org/licenses/>.
 
+use crate::sampling::record::encode::Writer;
 use crate::sampling::record::sample_id::SampleId;
+use crate::sampling::record::ParseContext;
+use crate::syscall::bindings::*;
 
 mod raw;
 
-#[derive(Debug, Clone)]
+/// The direction is decoded from `misc` of the record,
+/// and `Record::encode` sets the flags of `misc` from it.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Body {
     pub sample_id: Option<SampleId>,
+    out: bool,
+    #[cfg(feature = "linux-4.17")]
+    preempt: bool,
 }
 
 impl Body {
-    pub(crate) unsafe fn from_ptr(ptr: *const u8, sample_type: u64, sample_id_all: bool) -> Self {
+    pub(crate) unsafe fn from_ptr(
+        ptr: *const u8,
+        misc: u16,
+        sample_type: u64,
+        sample_id_all: bool,
+    ) -> Self {
         let raw = &*(ptr as *const raw::Raw);
 
         Self {
             sample_id: sample_id_all.then(|| raw.sample_id(sample_type)),
+            out: is_out(misc),
+            #[cfg(feature = "linux-4.17")]
+            preempt: is_preempt(misc),
         }
     }
+
+    /// Not preempted, see `with_preempt`
+    pub const fn new(out: bool, sample_id: Option<SampleId>) -> Self {
+        Self {
+            sample_id,
+            out,
+            #[cfg(feature = "linux-4.17")]
+            preempt: false,
+        }
+    }
+
+    #[cfg(feature = "linux-4.17")]
+    pub const fn with_preempt(mut self, preempt: bool) -> Self {
+        self.preempt = preempt;
+        self
+    }
+
+    /// Switched out of the CPU, otherwise in, i.e. `PERF_RECORD_MISC_SWITCH_OUT`
+    pub const fn is_out(&self) -> bool {
+        self.out
+    }
+
+    /// Preempted while runnable, i.e. `PERF_RECORD_MISC_SWITCH_OUT_PREEMPT`
+    #[cfg(feature = "linux-4.17")]
+    pub const fn is_preempt(&self) -> bool {
+        self.preempt
+    }
+
+    /// `misc` with the flags of the direction
+    pub(crate) const fn misc(&self, misc: u16) -> u16 {
+        with_direction(
+            misc,
+            self.out,
+            #[cfg(feature = "linux-4.17")]
+            self.preempt,
+        )
+    }
+}
+
+#[cfg(not(feature = "linux-4.17"))]
+const DIRECTION_MASK: u32 = PERF_RECORD_MISC_SWITCH_OUT;
+#[cfg(feature = "linux-4.17")]
+const DIRECTION_MASK: u32 = PERF_RECORD_MISC_SWITCH_OUT | PERF_RECORD_MISC_SWITCH_OUT_PREEMPT;
+
+pub(crate) const fn is_out(misc: u16) -> bool {
+    misc as u32 & PERF_RECORD_MISC_SWITCH_OUT != 0
+}
+
+#[cfg(feature = "linux-4.17")]
+pub(crate) const fn is_preempt(misc: u16) -> bool {
+    misc as u32 & PERF_RECORD_MISC_SWITCH_OUT_PREEMPT != 0
+}
+
+pub(crate) const fn with_direction(
+    misc: u16,
+    out: bool,
+    #[cfg(feature = "linux-4.17")] preempt: bool,
+) -> u16 {
+    let mut flags = 0;
+    if out {
+        flags |= PERF_RECORD_MISC_SWITCH_OUT;
+    }
+    #[cfg(feature = "linux-4.17")]
+    if preempt {
+        flags |= PERF_RECORD_MISC_SWITCH_OUT_PREEMPT;
+    }
+    (misc & !(DIRECTION_MASK as u16)) | flags as u16
+}
+
+impl Body {
+    pub(crate) fn write_to(&self, w: &mut Writer, ctx: &ParseContext) {
+        w.sample_id(&self.sample_id, ctx);
+    }
 }
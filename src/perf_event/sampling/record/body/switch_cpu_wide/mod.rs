@@ -12,25 +12,95 @@
 // You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
 // see <https://www.gnu.org/licenses/>.
 
+use crate::sampling::record::encode::Writer;
 use crate::sampling::record::sample_id::SampleId;
+#[cfg(feature = "linux-4.17")]
+use crate::sampling::record::switch::is_preempt;
+use crate::sampling::record::switch::{is_out, with_direction};
+use crate::sampling::record::ParseContext;
 
 mod raw;
 
-#[derive(Debug, Clone)]
+/// The direction is decoded from `misc` of the record,
+/// and `Record::encode` sets the flags of `misc` from it.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Body {
     pub next_prev_pid: u32,
     pub next_prev_tid: u32,
     pub sample_id: Option<SampleId>,
+    out: bool,
+    #[cfg(feature = "linux-4.17")]
+    preempt: bool,
 }
 
 impl Body {
-    pub(crate) unsafe fn from_ptr(ptr: *const u8, sample_type: u64, sample_id_all: bool) -> Self {
+    pub(crate) unsafe fn from_ptr(
+        ptr: *const u8,
+        misc: u16,
+        sample_type: u64,
+        sample_id_all: bool,
+    ) -> Self {
         let raw = &*(ptr as *const raw::Raw);
 
         Self {
             next_prev_pid: raw.next_prev_pid,
             next_prev_tid: raw.next_prev_tid,
             sample_id: sample_id_all.then(|| raw.sample_id(sample_type)),
+            out: is_out(misc),
+            #[cfg(feature = "linux-4.17")]
+            preempt: is_preempt(misc),
         }
     }
+
+    /// Not preempted, see `with_preempt`
+    pub const fn new(
+        next_prev_pid: u32,
+        next_prev_tid: u32,
+        out: bool,
+        sample_id: Option<SampleId>,
+    ) -> Self {
+        Self {
+            next_prev_pid,
+            next_prev_tid,
+            sample_id,
+            out,
+            #[cfg(feature = "linux-4.17")]
+            preempt: false,
+        }
+    }
+
+    #[cfg(feature = "linux-4.17")]
+    pub const fn with_preempt(mut self, preempt: bool) -> Self {
+        self.preempt = preempt;
+        self
+    }
+
+    /// Switched out of the CPU, otherwise in, i.e. `PERF_RECORD_MISC_SWITCH_OUT`
+    pub const fn is_out(&self) -> bool {
+        self.out
+    }
+
+    /// Preempted while runnable, i.e. `PERF_RECORD_MISC_SWITCH_OUT_PREEMPT`
+    #[cfg(feature = "linux-4.17")]
+    pub const fn is_preempt(&self) -> bool {
+        self.preempt
+    }
+
+    /// `misc` with the flags of the direction
+    pub(crate) const fn misc(&self, misc: u16) -> u16 {
+        with_direction(
+            misc,
+            self.out,
+            #[cfg(feature = "linux-4.17")]
+            self.preempt,
+        )
+    }
+}
+
+impl Body {
+    pub(crate) fn write_to(&self, w: &mut Writer, ctx: &ParseContext) {
+        w.u32(self.next_prev_pid);
+        w.u32(self.next_prev_tid);
+        w.sample_id(&self.sample_id, ctx);
+    }
 }
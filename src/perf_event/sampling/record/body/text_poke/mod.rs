@@ -12,11 +12,13 @@
 // You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
 // see <https://www.gnu.org/licenses/>.
 
+use crate::sampling::record::encode::Writer;
 use crate::sampling::record::sample_id::SampleId;
+use crate::sampling::record::ParseContext;
 
 mod raw;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Body {
     pub addr: u64,
     pub old_len: u16,
@@ -33,12 +35,24 @@ impl Body {
         };
 
         let sized = raw.sized();
+        let (addr, old_len, new_len) = (sized.addr, sized.old_len, sized.new_len);
         Self {
-            addr: sized.addr,
-            old_len: sized.old_len,
-            new_len: sized.new_len,
-            bytes: raw.bytes().to_vec(),
+            addr,
+            old_len,
+            new_len,
+            bytes: raw.bytes(old_len as usize + new_len as usize).to_vec(),
             sample_id: sample_id_all.then(|| raw.sample_id()),
         }
     }
 }
+
+impl Body {
+    pub(crate) fn write_to(&self, w: &mut Writer, ctx: &ParseContext) {
+        w.u64(self.addr);
+        w.u16(self.old_len);
+        w.u16(self.new_len);
+        w.bytes(&self.bytes);
+        w.align();
+        w.sample_id(&self.sample_id, ctx);
+    }
+}
@@ -22,8 +22,9 @@ struct {
 };
 */
 
-use crate::infra::{ConstPtrExt, SliceExt, ZeroTerminated};
+use crate::infra::{ConstPtrExt, SliceExt};
 use crate::sampling::record::sample_id::SampleId;
+use std::slice;
 
 #[repr(C)]
 pub struct Sized {
@@ -40,14 +41,13 @@ pub struct Raw {
 impl Raw {
     pub unsafe fn sized(&mut self) -> &Sized {
         let ptr = self.read_ptr as *const Sized;
-        self.read_ptr = ptr.add(1) as _;
+        // `bytes` follows `new_len` without the tail padding of `Sized`
+        self.read_ptr = self.read_ptr.add(12);
         &*ptr
     }
 
-    pub unsafe fn bytes(&mut self) -> &[u8] {
-        let ptr = self.read_ptr;
-        let zt = ZeroTerminated::from_ptr(ptr);
-        let slice = zt.as_slice();
+    pub unsafe fn bytes(&mut self, len: usize) -> &[u8] {
+        let slice = slice::from_raw_parts(self.read_ptr, len);
         // Above [u8] will be rounded up to 64-bit in size in the kernel
         self.read_ptr = slice.follow_mem_ptr().align_as_ptr::<u64>() as _;
         slice
@@ -0,0 +1,289 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+
+/*
+Size sanity checks of record bodies.
+
+The decoders read through raw pointers, trusting the lengths written in the records.
+Before decoding, the same layout is walked here with bounds checks,
+so a corrupt record can't make the decoders read out of bounds.
+*/
+
+use crate::sampling::record::read_format::Layout;
+use crate::sampling::record::ParseContext;
+use crate::syscall::bindings::*;
+use std::mem::size_of;
+
+struct Cursor<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn skip(&mut self, len: usize) -> Option<()> {
+        let end = self.pos.checked_add(len)?;
+        if end > self.buf.len() {
+            return None;
+        }
+        self.pos = end;
+        Some(())
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        let bytes = self.buf.get(self.pos..self.pos.checked_add(8)?)?;
+        self.pos += 8;
+        Some(u64::from_ne_bytes(bytes.try_into().ok()?))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        let bytes = self.buf.get(self.pos..self.pos.checked_add(4)?)?;
+        self.pos += 4;
+        Some(u32::from_ne_bytes(bytes.try_into().ok()?))
+    }
+
+    #[cfg(feature = "linux-5.9")]
+    fn u16(&mut self) -> Option<u16> {
+        let bytes = self.buf.get(self.pos..self.pos.checked_add(2)?)?;
+        self.pos += 2;
+        Some(u16::from_ne_bytes(bytes.try_into().ok()?))
+    }
+
+    /// Zero terminated string, rounded up to 64-bit in size in the kernel
+    fn str(&mut self) -> Option<()> {
+        let nul = self.buf.get(self.pos..)?.iter().position(|&b| b == 0)?;
+        self.skip(nul + 1)?;
+        self.align()
+    }
+
+    fn align(&mut self) -> Option<()> {
+        let padding = (8 - self.pos % 8) % 8;
+        self.skip(padding)
+    }
+
+    fn array(&mut self, nr: u64, item_size: usize) -> Option<()> {
+        let len = usize::try_from(nr).ok()?.checked_mul(item_size)?;
+        self.skip(len)
+    }
+}
+
+#[allow(clippy::unnecessary_cast)] // mask may be u64 or u32 in different linux headers
+const fn is_enabled(sample_type: u64, mask: perf_event_sample_format) -> bool {
+    (sample_type & mask as u64) > 0
+}
+
+/// Size of `struct sample_id`
+const fn sample_id_len(sample_type: u64) -> usize {
+    let masks = [
+        PERF_SAMPLE_TID,
+        PERF_SAMPLE_TIME,
+        PERF_SAMPLE_ID,
+        PERF_SAMPLE_STREAM_ID,
+        PERF_SAMPLE_CPU,
+        #[cfg(feature = "linux-3.12")]
+        PERF_SAMPLE_IDENTIFIER,
+    ];
+    let mut len = 0;
+    let mut i = 0;
+    while i < masks.len() {
+        if is_enabled(sample_type, masks[i]) {
+            len += 8;
+        }
+        i += 1;
+    }
+    len
+}
+
+fn read_format(cur: &mut Cursor, read_format: u64) -> Option<()> {
+    let layout = Layout::new(read_format);
+    let len = match layout.group {
+        // `nr` is the first word
+        true => layout.len(cur.u64()?)? - 1,
+        false => layout.len(1)?,
+    };
+    cur.array(len as _, 8)
+}
+
+fn abi_and_regs(cur: &mut Cursor, regs_len: usize) -> Option<()> {
+    let abi = cur.u64()?;
+    if abi != PERF_SAMPLE_REGS_ABI_NONE as u64 {
+        cur.array(regs_len as _, 8)?;
+    }
+    Some(())
+}
+
+fn sample(cur: &mut Cursor, ctx: &ParseContext) -> Option<()> {
+    let st = ctx.sample_type;
+    let u64_fields = [
+        #[cfg(feature = "linux-3.12")]
+        PERF_SAMPLE_IDENTIFIER,
+        PERF_SAMPLE_IP,
+        PERF_SAMPLE_TID,
+        PERF_SAMPLE_TIME,
+        PERF_SAMPLE_ADDR,
+        PERF_SAMPLE_ID,
+        PERF_SAMPLE_STREAM_ID,
+        PERF_SAMPLE_CPU,
+        PERF_SAMPLE_PERIOD,
+    ];
+    for mask in u64_fields {
+        if is_enabled(st, mask) {
+            cur.skip(8)?;
+        }
+    }
+    if is_enabled(st, PERF_SAMPLE_READ) {
+        read_format(cur, ctx.read_format)?;
+    }
+    if is_enabled(st, PERF_SAMPLE_CALLCHAIN) {
+        let nr = cur.u64()?;
+        cur.array(nr, 8)?;
+    }
+    if is_enabled(st, PERF_SAMPLE_RAW) {
+        let size = cur.u32()?;
+        cur.skip(size as _)?;
+    }
+    if is_enabled(st, PERF_SAMPLE_BRANCH_STACK) {
+        let nr = cur.u64()?;
+        if ctx.branch_hw_index {
+            cur.skip(8)?;
+        }
+        cur.array(nr, size_of::<perf_branch_entry>())?;
+    }
+    if is_enabled(st, PERF_SAMPLE_REGS_USER) {
+        abi_and_regs(cur, ctx.regs_user.len())?;
+    }
+    if is_enabled(st, PERF_SAMPLE_STACK_USER) {
+        let size = cur.u64()?;
+        cur.array(size, 1)?;
+        if size != 0 && cur.u64()? > size {
+            return None;
+        }
+    }
+    #[cfg(feature = "linux-5.12")]
+    let weight = is_enabled(st, PERF_SAMPLE_WEIGHT) || is_enabled(st, PERF_SAMPLE_WEIGHT_STRUCT);
+    #[cfg(not(feature = "linux-5.12"))]
+    let weight = is_enabled(st, PERF_SAMPLE_WEIGHT);
+    if weight {
+        cur.skip(8)?;
+    }
+    if is_enabled(st, PERF_SAMPLE_DATA_SRC) {
+        cur.skip(8)?;
+    }
+    #[cfg(feature = "linux-3.13")]
+    if is_enabled(st, PERF_SAMPLE_TRANSACTION) {
+        cur.skip(8)?;
+    }
+    #[cfg(feature = "linux-3.19")]
+    if is_enabled(st, PERF_SAMPLE_REGS_INTR) {
+        abi_and_regs(cur, ctx.regs_intr.len())?;
+    }
+    let u64_fields = [
+        #[cfg(feature = "linux-4.14")]
+        PERF_SAMPLE_PHYS_ADDR,
+        #[cfg(feature = "linux-5.7")]
+        PERF_SAMPLE_CGROUP,
+        #[cfg(feature = "linux-5.11")]
+        PERF_SAMPLE_DATA_PAGE_SIZE,
+        #[cfg(feature = "linux-5.11")]
+        PERF_SAMPLE_CODE_PAGE_SIZE,
+    ];
+    for mask in u64_fields {
+        if is_enabled(st, mask) {
+            cur.skip(8)?;
+        }
+    }
+    Some(())
+}
+
+pub enum Fit {
+    /// The decoder stays within the body
+    Fits,
+    Malformed,
+    UnknownType,
+}
+
+/// Whether the decoder of record `type_` stays within `body` (record without header).
+pub fn check_body(type_: u32, body: &[u8], ctx: &ParseContext) -> Fit {
+    let cur = &mut Cursor { buf: body, pos: 0 };
+    let sample_id = |cur: &mut Cursor| match ctx.sample_id_all {
+        true => cur.skip(sample_id_len(ctx.sample_type)),
+        false => Some(()),
+    };
+
+    #[rustfmt::skip]
+    let fits = match type_ {
+        // pid, tid, addr, len, pgoff, filename
+        PERF_RECORD_MMAP => cur.skip(32).and_then(|_| cur.str()),
+        // id, lost
+        PERF_RECORD_LOST => cur.skip(16).and_then(|_| sample_id(cur)),
+        // pid, tid, comm
+        PERF_RECORD_COMM => cur.skip(8).and_then(|_| cur.str()).and_then(|_| sample_id(cur)),
+        // pid, ppid, tid, ptid, time
+        PERF_RECORD_EXIT | PERF_RECORD_FORK => cur.skip(24).and_then(|_| sample_id(cur)),
+        // time, id, stream_id
+        PERF_RECORD_THROTTLE | PERF_RECORD_UNTHROTTLE => cur.skip(24).and_then(|_| sample_id(cur)),
+        // pid, tid, values
+        PERF_RECORD_READ => cur.skip(8).and_then(|_| read_format(cur, ctx.read_format)).and_then(|_| sample_id(cur)),
+        PERF_RECORD_SAMPLE => sample(cur, ctx),
+        // pid, tid, addr, len, pgoff, maj/min/ino/ino_generation or build_id, prot, flags, filename
+        #[cfg(feature = "linux-3.12")]
+        PERF_RECORD_MMAP2 => cur.skip(64).and_then(|_| cur.str()).and_then(|_| sample_id(cur)),
+        // aux_offset, aux_size, flags
+        #[cfg(feature = "linux-4.1")]
+        PERF_RECORD_AUX => cur.skip(24).and_then(|_| sample_id(cur)),
+        #[cfg(feature = "linux-4.1")]
+        PERF_RECORD_ITRACE_START => cur.skip(size_of::<super::intrace_start::Body>()),
+        // lost
+        #[cfg(feature = "linux-4.2")]
+        PERF_RECORD_LOST_SAMPLES => cur.skip(8).and_then(|_| sample_id(cur)),
+        #[cfg(feature = "linux-4.3")]
+        PERF_RECORD_SWITCH => sample_id(cur),
+        // next_prev_pid, next_prev_tid
+        #[cfg(feature = "linux-4.3")]
+        PERF_RECORD_SWITCH_CPU_WIDE => cur.skip(8).and_then(|_| sample_id(cur)),
+        // pid, tid, nr_namespaces, { dev, inode } [nr_namespaces]
+        #[cfg(feature = "linux-4.12")]
+        PERF_RECORD_NAMESPACES => cur
+            .skip(8)
+            .and_then(|_| cur.u64())
+            .and_then(|nr| cur.array(nr, 16))
+            .and_then(|_| sample_id(cur)),
+        // addr, len, ksym_type, flags, name
+        #[cfg(feature = "linux-5.1")]
+        PERF_RECORD_KSYMBOL => cur.skip(16).and_then(|_| cur.str()).and_then(|_| sample_id(cur)),
+        // type, flags, id, tag
+        #[cfg(feature = "linux-5.1")]
+        PERF_RECORD_BPF_EVENT => cur
+            .skip(8 + BPF_TAG_SIZE as usize)
+            .and_then(|_| sample_id(cur)),
+        // id, path
+        #[cfg(feature = "linux-5.7")]
+        PERF_RECORD_CGROUP => cur.skip(8).and_then(|_| cur.str()).and_then(|_| sample_id(cur)),
+        // addr, old_len, new_len, bytes
+        #[cfg(feature = "linux-5.9")]
+        PERF_RECORD_TEXT_POKE => (|| {
+            cur.skip(8)?;
+            let len = cur.u16()? as usize + cur.u16()? as usize;
+            cur.skip(len)?;
+            cur.align()?;
+            sample_id(cur)
+        })(),
+        #[cfg(feature = "linux-5.16")]
+        PERF_RECORD_AUX_OUTPUT_HW_ID => cur.skip(size_of::<super::aux_output_hw_id::Body>()),
+        _ => return Fit::UnknownType,
+    };
+    match fits {
+        Some(_) => Fit::Fits,
+        None => Fit::Malformed,
+    }
+}
@@ -0,0 +1,199 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+
+use crate::sampling::record::read_format::Layout;
+use crate::sampling::record::{
+    ParseContext, Record, RecordBody, SampleId, RECORD_AUX_OUTPUT_HW_ID,
+};
+use crate::sampling::{MemberCount, SamplerGroupStat};
+use crate::syscall::bindings::*;
+use std::ffi::CStr;
+use std::mem::size_of;
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum EncodeError {
+    #[error("Record size {0} exceeds the limit of perf_event_header.size")]
+    TooLarge(usize),
+}
+
+/// Writes records in the layout of the kernel
+#[derive(Default)]
+pub struct Writer {
+    buf: Vec<u8>,
+}
+
+impl Writer {
+    pub fn u64(&mut self, val: u64) {
+        self.buf.extend_from_slice(&val.to_ne_bytes());
+    }
+
+    pub fn u32(&mut self, val: u32) {
+        self.buf.extend_from_slice(&val.to_ne_bytes());
+    }
+
+    pub fn u16(&mut self, val: u16) {
+        self.buf.extend_from_slice(&val.to_ne_bytes());
+    }
+
+    pub fn bytes(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    /// Pad with 0 to 64-bit alignment
+    pub fn align(&mut self) {
+        let padding = (8 - self.buf.len() % 8) % 8;
+        self.buf.resize(self.buf.len() + padding, 0);
+    }
+
+    /// Zero terminated string, rounded up to 64-bit in size
+    pub fn str(&mut self, str: &CStr) {
+        self.bytes(str.to_bytes_with_nul());
+        self.align();
+    }
+
+    /// `struct read_format` in the layout of `read_format`, members are written
+    /// in the order of their ids. Without `PERF_FORMAT_GROUP` only the first one is written.
+    pub fn read_format(&mut self, stat: &SamplerGroupStat, read_format: u64) {
+        let layout = Layout::new(read_format);
+        let zero = MemberCount {
+            event_count: 0,
+            #[cfg(feature = "linux-6.0")]
+            event_lost: 0,
+        };
+        let mut members: Vec<_> = stat
+            .member_counts
+            .iter()
+            .map(|(id, it)| (*id, it))
+            .collect();
+        members.sort_unstable_by_key(|(id, _)| *id);
+
+        if layout.group {
+            self.u64(members.len() as _);
+        } else {
+            // the event itself
+            members.resize(1, (0, &zero));
+            self.u64(members[0].1.event_count);
+        }
+        if layout.time_enabled {
+            self.u64(stat.time_enabled);
+        }
+        if layout.time_running {
+            self.u64(stat.time_running);
+        }
+        for (id, member_count) in members {
+            if layout.group {
+                self.u64(member_count.event_count);
+            }
+            if layout.id {
+                self.u64(id);
+            }
+            if layout.lost {
+                #[cfg(feature = "linux-6.0")]
+                self.u64(member_count.event_lost);
+                #[cfg(not(feature = "linux-6.0"))]
+                self.u64(0);
+            }
+        }
+    }
+
+    /// `struct sample_id` if `sample_id_all` is set, missing fields are written as 0
+    pub fn sample_id(&mut self, sample_id: &Option<SampleId>, ctx: &ParseContext) {
+        if !ctx.sample_id_all {
+            return;
+        }
+        #[rustfmt::skip]
+        let default = SampleId {
+            pid: None, tid: None, time: None, id_1: None, stream_id: None, cpu: None,
+            #[cfg(feature = "linux-3.12")]
+            id_2: None,
+        };
+        let sample_id = sample_id.as_ref().unwrap_or(&default);
+        sample_id.write_to(self, ctx.sample_type);
+    }
+}
+
+impl Record {
+    /// Encode this record, including `perf_event_header`, in the layout
+    /// the kernel writes for the event of `ctx`, so that `Record::parse`
+    /// decodes it back.
+    ///
+    /// Fields not enabled by `ctx` are skipped, and enabled fields which are `None`
+    /// are written as 0. Records without `sample_id` in their body, e.g. `Mmap`,
+    /// get a trailing `struct sample_id` of 0 if `sample_id_all` is set.
+    /// The direction flags in `misc` of switch records are set from their bodies.
+    pub fn encode(&self, ctx: &ParseContext) -> Result<Vec<u8>, EncodeError> {
+        let w = &mut Writer::default();
+        // header, the type, misc and size are filled later
+        w.u32(0);
+        w.u16(0);
+        w.u16(0);
+
+        #[rustfmt::skip]
+        let type_ = match &self.body {
+            RecordBody::Mmap(body)        => { body.write_to(w, ctx); PERF_RECORD_MMAP }
+            RecordBody::Lost(body)        => { body.write_to(w, ctx); PERF_RECORD_LOST }
+            RecordBody::Comm(body)        => { body.write_to(w, ctx); PERF_RECORD_COMM }
+            RecordBody::Exit(body)        => { body.write_to(w, ctx); PERF_RECORD_EXIT }
+            RecordBody::Throttle(body)    => { body.write_to(w, ctx); PERF_RECORD_THROTTLE }
+            RecordBody::Unthrottle(body)  => { body.write_to(w, ctx); PERF_RECORD_UNTHROTTLE }
+            RecordBody::Fork(body)        => { body.write_to(w, ctx); PERF_RECORD_FORK }
+            RecordBody::Read(body)        => { body.write_to(w, ctx); PERF_RECORD_READ }
+            RecordBody::Sample(body)      => { body.write_to(w, ctx); PERF_RECORD_SAMPLE }
+            #[cfg(feature = "linux-3.12")]
+            RecordBody::Mmap2(body)       => { body.write_to(w, ctx); PERF_RECORD_MMAP2 }
+            #[cfg(feature = "linux-4.1")]
+            RecordBody::Aux(body)         => { body.write_to(w, ctx); PERF_RECORD_AUX }
+            #[cfg(feature = "linux-4.1")]
+            RecordBody::ItraceStart(body) => { body.write_to(w, ctx); PERF_RECORD_ITRACE_START }
+            #[cfg(feature = "linux-4.2")]
+            RecordBody::LostSamples(body) => { body.write_to(w, ctx); PERF_RECORD_LOST_SAMPLES }
+            #[cfg(feature = "linux-4.3")]
+            RecordBody::Switch(body)      => { body.write_to(w, ctx); PERF_RECORD_SWITCH }
+            #[cfg(feature = "linux-4.3")]
+            RecordBody::SwitchCpuWide(body) => {
+                body.write_to(w, ctx);
+                PERF_RECORD_SWITCH_CPU_WIDE
+            }
+            #[cfg(feature = "linux-4.12")]
+            RecordBody::Namespaces(body)  => { body.write_to(w, ctx); PERF_RECORD_NAMESPACES }
+            #[cfg(feature = "linux-5.1")]
+            RecordBody::Ksymbol(body)     => { body.write_to(w, ctx); PERF_RECORD_KSYMBOL }
+            #[cfg(feature = "linux-5.1")]
+            RecordBody::BpfEvent(body)    => { body.write_to(w, ctx); PERF_RECORD_BPF_EVENT }
+            #[cfg(feature = "linux-5.7")]
+            RecordBody::Cgroup(body)      => { body.write_to(w, ctx); PERF_RECORD_CGROUP }
+            #[cfg(feature = "linux-5.9")]
+            RecordBody::TextPoke(body)    => { body.write_to(w, ctx); PERF_RECORD_TEXT_POKE }
+            RecordBody::AuxOutputHwId(_)  => RECORD_AUX_OUTPUT_HW_ID,
+            RecordBody::Unknown { type_, bytes, .. } => { w.bytes(bytes); *type_ }
+        };
+
+        // the direction of switch records is in `misc`
+        let misc = match &self.body {
+            #[cfg(feature = "linux-4.3")]
+            RecordBody::Switch(body) => body.misc(self.misc),
+            #[cfg(feature = "linux-4.3")]
+            RecordBody::SwitchCpuWide(body) => body.misc(self.misc),
+            _ => self.misc,
+        };
+
+        let mut buf = std::mem::take(&mut w.buf);
+        buf[4..6].copy_from_slice(&misc.to_ne_bytes());
+        let size = u16::try_from(buf.len()).map_err(|_| EncodeError::TooLarge(buf.len()))?;
+        buf[..size_of::<u32>()].copy_from_slice(&type_.to_ne_bytes());
+        buf[6..8].copy_from_slice(&size.to_ne_bytes());
+        Ok(buf)
+    }
+}
@@ -0,0 +1,143 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+use crate::syscall::bindings::*;
+
+/*
+The low 3 bits of `misc` are the CPU mode, the high bits are flags which
+share positions across record types:
+
+bit 13: MMAP_DATA (mmap), COMM_EXEC (comm), FORK_EXEC (fork), SWITCH_OUT (switch)
+bit 14: EXACT_IP (sample), SWITCH_OUT_PREEMPT (switch), MMAP_BUILD_ID (mmap2)
+*/
+
+/// Mode of the CPU when the record was generated, i.e. `misc & PERF_RECORD_MISC_CPUMODE_MASK`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CpuMode {
+    Unknown,
+    Kernel,
+    User,
+    Hypervisor,
+    GuestKernel,
+    GuestUser,
+}
+
+impl CpuMode {
+    pub const fn from_misc(misc: u16) -> Self {
+        match misc as u32 & PERF_RECORD_MISC_CPUMODE_MASK {
+            PERF_RECORD_MISC_KERNEL => Self::Kernel,
+            PERF_RECORD_MISC_USER => Self::User,
+            PERF_RECORD_MISC_HYPERVISOR => Self::Hypervisor,
+            PERF_RECORD_MISC_GUEST_KERNEL => Self::GuestKernel,
+            PERF_RECORD_MISC_GUEST_USER => Self::GuestUser,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+/// Flags of `misc`, whose meaning depends on the record type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MiscFlags {
+    Mmap {
+        /// `PERF_RECORD_MISC_MMAP_DATA`, the mapping is not executable
+        data: bool,
+        /// `PERF_RECORD_MISC_PROC_MAP_PARSE_TIMEOUT`, set by perf tools
+        /// if parsing `/proc/<pid>/maps` timed out
+        proc_map_parse_timeout: bool,
+    },
+    #[cfg(feature = "linux-3.12")]
+    Mmap2 {
+        /// `PERF_RECORD_MISC_MMAP_DATA`
+        data: bool,
+        /// `PERF_RECORD_MISC_MMAP_BUILD_ID`, see `mmap2::AnonEnum::BuildId`
+        #[cfg(feature = "linux-5.12")]
+        build_id: bool,
+    },
+    Comm {
+        /// `PERF_RECORD_MISC_COMM_EXEC`, renamed by exec
+        exec: bool,
+    },
+    Fork {
+        /// `PERF_RECORD_MISC_FORK_EXEC`, set by perf tools only
+        exec: bool,
+    },
+    Sample {
+        /// `PERF_RECORD_MISC_EXACT_IP`, the ip points to the instruction
+        /// which caused the event, see `SampleIpSkid`
+        exact_ip: bool,
+    },
+    #[cfg(feature = "linux-4.3")]
+    Switch {
+        /// `PERF_RECORD_MISC_SWITCH_OUT`, switched out of the CPU, otherwise in
+        out: bool,
+        /// `PERF_RECORD_MISC_SWITCH_OUT_PREEMPT`, preempted while runnable
+        #[cfg(feature = "linux-4.17")]
+        preempt: bool,
+    },
+    /// Record types without flags
+    None,
+}
+
+const fn has(misc: u16, flag: u32) -> bool {
+    misc as u32 & flag != 0
+}
+
+/// Typed view of `perf_event_header.misc`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Misc {
+    pub cpu_mode: CpuMode,
+    pub flags: MiscFlags,
+    /// `PERF_RECORD_MISC_EXT_RESERVED`
+    pub ext_reserved: bool,
+}
+
+impl Misc {
+    /// Decode `misc` of a record of `type_`, i.e. `PERF_RECORD_*`
+    pub const fn new(type_: u32, misc: u16) -> Self {
+        // `PERF_RECORD_MISC_FORK_EXEC` is missing from older headers
+        let fork_exec = PERF_RECORD_MISC_COMM_EXEC;
+        let flags = match type_ {
+            PERF_RECORD_MMAP => MiscFlags::Mmap {
+                data: has(misc, PERF_RECORD_MISC_MMAP_DATA),
+                proc_map_parse_timeout: has(misc, PERF_RECORD_MISC_PROC_MAP_PARSE_TIMEOUT),
+            },
+            #[cfg(feature = "linux-3.12")]
+            PERF_RECORD_MMAP2 => MiscFlags::Mmap2 {
+                data: has(misc, PERF_RECORD_MISC_MMAP_DATA),
+                #[cfg(feature = "linux-5.12")]
+                build_id: has(misc, PERF_RECORD_MISC_MMAP_BUILD_ID),
+            },
+            PERF_RECORD_COMM => MiscFlags::Comm {
+                exec: has(misc, PERF_RECORD_MISC_COMM_EXEC),
+            },
+            PERF_RECORD_FORK => MiscFlags::Fork {
+                exec: has(misc, fork_exec),
+            },
+            PERF_RECORD_SAMPLE => MiscFlags::Sample {
+                exact_ip: has(misc, PERF_RECORD_MISC_EXACT_IP),
+            },
+            #[cfg(feature = "linux-4.3")]
+            PERF_RECORD_SWITCH | PERF_RECORD_SWITCH_CPU_WIDE => MiscFlags::Switch {
+                out: has(misc, PERF_RECORD_MISC_SWITCH_OUT),
+                #[cfg(feature = "linux-4.17")]
+                preempt: has(misc, PERF_RECORD_MISC_SWITCH_OUT_PREEMPT),
+            },
+            _ => MiscFlags::None,
+        };
+        Self {
+            cpu_mode: CpuMode::from_misc(misc),
+            flags,
+            ext_reserved: has(misc, PERF_RECORD_MISC_EXT_RESERVED),
+        }
+    }
+}
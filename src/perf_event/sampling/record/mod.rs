@@ -13,18 +13,37 @@
 // see <https://www.gnu.org/licenses/>.
 
 mod body;
+mod check;
+mod encode;
+mod misc;
+mod parse;
+mod read_format;
+mod record_ref;
 mod sample_id;
+#[cfg(test)]
+mod tests;
 
 pub use body::*;
+pub use encode::EncodeError;
+pub use misc::*;
+pub use parse::*;
+pub use record_ref::*;
 pub use sample_id::*;
 
-#[derive(Debug, Clone)]
+use crate::syscall::bindings::*;
+
+/// `PERF_RECORD_AUX_OUTPUT_HW_ID`, missing in the headers before Linux 5.16
+const RECORD_AUX_OUTPUT_HW_ID: u32 = 21;
+#[cfg(feature = "linux-5.16")]
+const _: () = assert!(RECORD_AUX_OUTPUT_HW_ID == PERF_RECORD_AUX_OUTPUT_HW_ID);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Record {
     pub misc: u16,
     pub body: RecordBody,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum RecordBody {
     Mmap(Box<mmap::Body>),
     Lost(Box<lost::Body>),
@@ -58,4 +77,99 @@ pub enum RecordBody {
     #[cfg(feature = "linux-5.9")]
     TextPoke(Box<text_poke::Body>),
     AuxOutputHwId(Box<aux_output_hw_id::Body>), // TODO: missing docs in manual
+    /// Record of a type unknown to this crate, or which is malformed
+    Unknown {
+        type_: u32,
+        misc: u16,
+        /// Body of the record, without `perf_event_header`
+        bytes: Vec<u8>,
+    },
+}
+
+impl RecordBody {
+    /// `PERF_RECORD_*` of this record
+    pub const fn type_(&self) -> u32 {
+        match self {
+            Self::Mmap(_) => PERF_RECORD_MMAP,
+            Self::Lost(_) => PERF_RECORD_LOST,
+            Self::Comm(_) => PERF_RECORD_COMM,
+            Self::Exit(_) => PERF_RECORD_EXIT,
+            Self::Throttle(_) => PERF_RECORD_THROTTLE,
+            Self::Unthrottle(_) => PERF_RECORD_UNTHROTTLE,
+            Self::Fork(_) => PERF_RECORD_FORK,
+            Self::Read(_) => PERF_RECORD_READ,
+            Self::Sample(_) => PERF_RECORD_SAMPLE,
+            #[cfg(feature = "linux-3.12")]
+            Self::Mmap2(_) => PERF_RECORD_MMAP2,
+            #[cfg(feature = "linux-4.1")]
+            Self::Aux(_) => PERF_RECORD_AUX,
+            #[cfg(feature = "linux-4.1")]
+            Self::ItraceStart(_) => PERF_RECORD_ITRACE_START,
+            #[cfg(feature = "linux-4.2")]
+            Self::LostSamples(_) => PERF_RECORD_LOST_SAMPLES,
+            #[cfg(feature = "linux-4.3")]
+            Self::Switch(_) => PERF_RECORD_SWITCH,
+            #[cfg(feature = "linux-4.3")]
+            Self::SwitchCpuWide(_) => PERF_RECORD_SWITCH_CPU_WIDE,
+            #[cfg(feature = "linux-4.12")]
+            Self::Namespaces(_) => PERF_RECORD_NAMESPACES,
+            #[cfg(feature = "linux-5.1")]
+            Self::Ksymbol(_) => PERF_RECORD_KSYMBOL,
+            #[cfg(feature = "linux-5.1")]
+            Self::BpfEvent(_) => PERF_RECORD_BPF_EVENT,
+            #[cfg(feature = "linux-5.7")]
+            Self::Cgroup(_) => PERF_RECORD_CGROUP,
+            #[cfg(feature = "linux-5.9")]
+            Self::TextPoke(_) => PERF_RECORD_TEXT_POKE,
+            Self::AuxOutputHwId(_) => RECORD_AUX_OUTPUT_HW_ID,
+            Self::Unknown { type_, .. } => *type_,
+        }
+    }
+}
+
+impl Record {
+    /// Typed view of `misc`, which depends on the type of the body
+    pub const fn typed_misc(&self) -> Misc {
+        Misc::new(self.body.type_(), self.misc)
+    }
+
+    pub const fn cpu_mode(&self) -> CpuMode {
+        CpuMode::from_misc(self.misc)
+    }
+
+    /// Time of the record, from `PERF_SAMPLE_TIME` of samples, the body of
+    /// fork, exit and throttle records, or `sample_id` of other records.
+    pub fn time(&self) -> Option<u64> {
+        let sample_id = match &self.body {
+            RecordBody::Sample(body) => return body.time,
+            RecordBody::Fork(body) => return Some(body.time),
+            RecordBody::Exit(body) => return Some(body.time),
+            RecordBody::Throttle(body) | RecordBody::Unthrottle(body) => return Some(body.time),
+            RecordBody::Lost(body) => &body.sample_id,
+            RecordBody::Comm(body) => &body.sample_id,
+            RecordBody::Read(body) => &body.sample_id,
+            #[cfg(feature = "linux-3.12")]
+            RecordBody::Mmap2(body) => &body.sample_id,
+            #[cfg(feature = "linux-4.1")]
+            RecordBody::Aux(body) => &body.sample_id,
+            #[cfg(feature = "linux-4.2")]
+            RecordBody::LostSamples(body) => &body.sample_id,
+            #[cfg(feature = "linux-4.3")]
+            RecordBody::Switch(body) => &body.sample_id,
+            #[cfg(feature = "linux-4.3")]
+            RecordBody::SwitchCpuWide(body) => &body.sample_id,
+            #[cfg(feature = "linux-4.12")]
+            RecordBody::Namespaces(body) => &body.sample_id,
+            #[cfg(feature = "linux-5.1")]
+            RecordBody::Ksymbol(body) => &body.sample_id,
+            #[cfg(feature = "linux-5.1")]
+            RecordBody::BpfEvent(body) => &body.sample_id,
+            #[cfg(feature = "linux-5.7")]
+            RecordBody::Cgroup(body) => &body.sample_id,
+            #[cfg(feature = "linux-5.9")]
+            RecordBody::TextPoke(body) => &body.sample_id,
+            _ => return None,
+        };
+        sample_id.as_ref().and_then(|it| it.time)
+    }
 }
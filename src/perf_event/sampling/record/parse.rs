@@ -0,0 +1,115 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+
+use crate::perf_event::PerfEventAttr;
+use crate::sampling::record::check::{check_body, Fit};
+use crate::sampling::record::sample::RegMask;
+use crate::sampling::record::{Record, RecordRef};
+use crate::syscall::bindings::perf_event_header;
+#[cfg(feature = "linux-5.7")]
+use crate::syscall::bindings::PERF_SAMPLE_BRANCH_HW_INDEX;
+use std::mem::{align_of, size_of};
+use std::slice;
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    #[error("Buffer is shorter than the record header")]
+    TooShort,
+    #[error("Record size {size} does not match buffer length {len}")]
+    SizeMismatch { size: usize, len: usize },
+    #[error("Record of type {0} is malformed")]
+    Malformed(u32),
+}
+
+/// What the decoders need to know about the layout of records,
+/// which is decided by the `PerfEventAttr` of the event.
+#[derive(Debug, Clone, Copy)]
+pub struct ParseContext {
+    pub(crate) sample_type: u64,
+    pub(crate) sample_id_all: bool,
+    /// `perf_event_attr.read_format`, the layout of `struct read_format`
+    pub(crate) read_format: u64,
+    /// `u64 hw_idx` is in the branch stack
+    pub(crate) branch_hw_index: bool,
+    pub(crate) regs_user: RegMask,
+    #[cfg(feature = "linux-3.19")]
+    pub(crate) regs_intr: RegMask,
+}
+
+impl ParseContext {
+    pub fn new(perf_event_attr: &PerfEventAttr) -> Self {
+        #[cfg(feature = "linux-5.7")]
+        let branch_hw_index =
+            perf_event_attr.branch_sample_type & PERF_SAMPLE_BRANCH_HW_INDEX as u64 > 0;
+        #[cfg(not(feature = "linux-5.7"))]
+        let branch_hw_index = false;
+
+        Self {
+            sample_type: perf_event_attr.sample_type,
+            sample_id_all: perf_event_attr.sample_id_all() > 0,
+            read_format: perf_event_attr.read_format,
+            branch_hw_index,
+            regs_user: RegMask::from_bits(perf_event_attr.sample_regs_user),
+            #[cfg(feature = "linux-3.19")]
+            regs_intr: RegMask::from_bits(perf_event_attr.sample_regs_intr),
+        }
+    }
+}
+
+impl From<&PerfEventAttr> for ParseContext {
+    fn from(perf_event_attr: &PerfEventAttr) -> Self {
+        Self::new(perf_event_attr)
+    }
+}
+
+impl Record {
+    /// Decode one record, starting with `perf_event_header`, from `buf`
+    /// which must be exactly `perf_event_header.size` long.
+    ///
+    /// Records of types unknown to this crate are kept as `RecordBody::Unknown`.
+    pub fn parse(buf: &[u8], ctx: &ParseContext) -> Result<Self, ParseError> {
+        let header_len = size_of::<perf_event_header>();
+        if buf.len() < header_len {
+            return Err(ParseError::TooShort);
+        }
+        let type_ = u32::from_ne_bytes([buf[0], buf[1], buf[2], buf[3]]);
+        let size = u16::from_ne_bytes([buf[6], buf[7]]) as usize;
+        if size != buf.len() {
+            return Err(ParseError::SizeMismatch {
+                size,
+                len: buf.len(),
+            });
+        }
+        if matches!(check_body(type_, &buf[header_len..], ctx), Fit::Malformed) {
+            return Err(ParseError::Malformed(type_));
+        }
+
+        // the decoders read through typed pointers, so copy out unaligned records
+        let aligned: Vec<u64>;
+        let bytes = if buf.as_ptr().align_offset(align_of::<u64>()) == 0 {
+            buf
+        } else {
+            let mut words = vec![0_u64; buf.len().div_ceil(8)];
+            let words_bytes =
+                unsafe { slice::from_raw_parts_mut(words.as_mut_ptr() as *mut u8, buf.len()) };
+            words_bytes.copy_from_slice(buf);
+            aligned = words;
+            unsafe { slice::from_raw_parts(aligned.as_ptr() as *const u8, buf.len()) }
+        };
+
+        let record_ref = RecordRef { bytes, ctx: *ctx };
+        Ok(record_ref.to_record())
+    }
+}
@@ -0,0 +1,122 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+
+/*
+struct read_format {
+  { u64 value;
+    { u64 time_enabled; } && PERF_FORMAT_TOTAL_TIME_ENABLED
+    { u64 time_running; } && PERF_FORMAT_TOTAL_TIME_RUNNING
+    { u64 id;           } && PERF_FORMAT_ID
+    { u64 lost;         } && PERF_FORMAT_LOST
+  } && !PERF_FORMAT_GROUP
+
+  { u64 nr;
+    { u64 time_enabled; } && PERF_FORMAT_TOTAL_TIME_ENABLED
+    { u64 time_running; } && PERF_FORMAT_TOTAL_TIME_RUNNING
+    { u64 value;
+      { u64 id;           } && PERF_FORMAT_ID
+      { u64 lost;         } && PERF_FORMAT_LOST
+    } cntr[nr];
+  } && PERF_FORMAT_GROUP
+};
+*/
+
+use crate::sampling::{MemberCount, SamplerGroupStat};
+use crate::syscall::bindings::*;
+use std::slice;
+
+/// `PERF_FORMAT_LOST`, which is missing in the headers before Linux 6.0
+const FORMAT_LOST: u64 = 1 << 4;
+
+/// Layout of `struct read_format`, decided by `perf_event_attr.read_format`
+#[derive(Debug, Clone, Copy)]
+pub struct Layout {
+    pub group: bool,
+    pub time_enabled: bool,
+    pub time_running: bool,
+    pub id: bool,
+    pub lost: bool,
+}
+
+impl Layout {
+    #[allow(clippy::unnecessary_cast)] // mask may be u64 or u32 in different linux headers
+    pub const fn new(read_format: u64) -> Self {
+        Self {
+            group: read_format & PERF_FORMAT_GROUP as u64 > 0,
+            time_enabled: read_format & PERF_FORMAT_TOTAL_TIME_ENABLED as u64 > 0,
+            time_running: read_format & PERF_FORMAT_TOTAL_TIME_RUNNING as u64 > 0,
+            id: read_format & PERF_FORMAT_ID as u64 > 0,
+            lost: read_format & FORMAT_LOST > 0,
+        }
+    }
+
+    /// Words of one value, i.e. `value`, `id` and `lost`
+    const fn value_len(&self) -> usize {
+        1 + self.id as usize + self.lost as usize
+    }
+
+    /// Words of the struct with `nr` values, `nr` is only read with `PERF_FORMAT_GROUP`
+    pub fn len(&self, nr: u64) -> Option<usize> {
+        let times = self.time_enabled as usize + self.time_running as usize;
+        if !self.group {
+            return Some(times + self.value_len());
+        }
+        let values = usize::try_from(nr).ok()?.checked_mul(self.value_len())?;
+        values.checked_add(1 + times)
+    }
+
+    /// # Safety
+    /// `ptr` must point to a complete `struct read_format` of this layout,
+    /// which lives for `'a` and is 8 bytes aligned
+    pub unsafe fn words<'a>(&self, ptr: *const u8) -> &'a [u64] {
+        let ptr = ptr as *const u64;
+        let nr = if self.group { *ptr } else { 1 };
+        slice::from_raw_parts(ptr, self.len(nr).unwrap_or_default())
+    }
+
+    /// Fields not in this layout are 0. Without `PERF_FORMAT_GROUP` the only member is
+    /// the event itself, and without `PERF_FORMAT_ID` all members are keyed by 0.
+    pub fn decode(&self, words: &[u64]) -> SamplerGroupStat {
+        let mut words = words.iter().copied();
+        let mut next = |on: bool| {
+            if on {
+                words.next().unwrap_or_default()
+            } else {
+                0
+            }
+        };
+
+        let mut stat = SamplerGroupStat::default();
+        let (nr, mut value) = match self.group {
+            true => (next(true), 0),
+            false => (1, next(true)),
+        };
+        stat.time_enabled = next(self.time_enabled);
+        stat.time_running = next(self.time_running);
+        for _ in 0..nr {
+            if self.group {
+                value = next(true);
+            }
+            let id = next(self.id);
+            let _lost = next(self.lost);
+            let member_count = MemberCount {
+                event_count: value,
+                #[cfg(feature = "linux-6.0")]
+                event_lost: _lost,
+            };
+            stat.member_counts.insert(id, member_count);
+        }
+        stat
+    }
+}
@@ -0,0 +1,295 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+
+use crate::infra::{WrapBox, WrapOption};
+use crate::sampling::record::check::{check_body, Fit};
+use crate::sampling::record::*;
+use crate::syscall::bindings::*;
+use std::mem::size_of;
+
+/// Borrowed view of one record, which lives in the ring buffer
+/// or in the scratch buffer of the sampler if it wraps around.
+///
+/// The record stays valid until the next call of `next_record_ref`,
+/// call `to_record` to keep it longer.
+#[derive(Clone, Copy)]
+pub struct RecordRef<'a> {
+    /// Whole record, including `perf_event_header`
+    pub(crate) bytes: &'a [u8],
+    pub(crate) ctx: ParseContext,
+}
+
+impl<'a> RecordRef<'a> {
+    #[inline]
+    const fn header(&self) -> &'a perf_event_header {
+        unsafe { &*(self.bytes.as_ptr() as *const perf_event_header) }
+    }
+
+    #[inline]
+    const fn body_ptr(&self) -> *const u8 {
+        unsafe { (self.bytes.as_ptr() as *const perf_event_header).add(1) as _ }
+    }
+
+    /// `PERF_RECORD_*` of this record
+    pub const fn type_(&self) -> u32 {
+        self.header().type_
+    }
+
+    pub const fn misc(&self) -> u16 {
+        self.header().misc
+    }
+
+    /// Typed view of `misc`, which depends on the type of this record
+    pub const fn typed_misc(&self) -> Misc {
+        Misc::new(self.type_(), self.misc())
+    }
+
+    pub const fn cpu_mode(&self) -> CpuMode {
+        CpuMode::from_misc(self.misc())
+    }
+
+    /// Raw bytes of this record, including the header
+    pub const fn bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// Whether the body is large enough for what its decoder reads
+    fn fits(&self) -> bool {
+        let body = &self.bytes[size_of::<perf_event_header>()..];
+        matches!(check_body(self.type_(), body, &self.ctx), Fit::Fits)
+    }
+
+    /// Sample view if this is a well-formed `PERF_RECORD_SAMPLE`
+    pub fn sample(&self) -> Option<sample::SampleRef<'a>> {
+        if self.type_() != PERF_RECORD_SAMPLE || !self.fits() {
+            return None;
+        }
+        unsafe {
+            sample::SampleRef::from_ptr(
+                self.body_ptr(),
+                self.ctx.sample_type,
+                self.ctx.read_format,
+                self.ctx.branch_hw_index,
+                self.ctx.regs_user,
+                #[cfg(feature = "linux-3.19")]
+                self.ctx.regs_intr,
+            )
+        }
+        .wrap_some()
+    }
+
+    /// Copy this record out, unknown or malformed records are kept as
+    /// `RecordBody::Unknown`
+    pub fn to_record(&self) -> Record {
+        if !self.fits() {
+            return Record {
+                misc: self.misc(),
+                body: RecordBody::Unknown {
+                    type_: self.type_(),
+                    misc: self.misc(),
+                    bytes: self.bytes[size_of::<perf_event_header>()..].to_vec(),
+                },
+            };
+        }
+
+        let follow_mem_ptr = self.body_ptr();
+        let body = unsafe {
+            match self.type_() {
+                PERF_RECORD_MMAP => {
+                    let record = mmap::Body::from_ptr(follow_mem_ptr);
+                    RecordBody::Mmap(record.wrap_box())
+                }
+                PERF_RECORD_LOST => {
+                    let record = lost::Body::from_ptr(
+                        follow_mem_ptr,
+                        self.ctx.sample_type,
+                        self.ctx.sample_id_all,
+                    );
+                    RecordBody::Lost(record.wrap_box())
+                }
+                PERF_RECORD_COMM => {
+                    let record = comm::Body::from_ptr(
+                        follow_mem_ptr,
+                        self.ctx.sample_type,
+                        self.ctx.sample_id_all,
+                    );
+                    RecordBody::Comm(record.wrap_box())
+                }
+                PERF_RECORD_EXIT => {
+                    let record = exit::Body::from_ptr(
+                        follow_mem_ptr,
+                        self.ctx.sample_type,
+                        self.ctx.sample_id_all,
+                    );
+                    RecordBody::Exit(record.wrap_box())
+                }
+                PERF_RECORD_THROTTLE => {
+                    let record = throttle::Body::from_ptr(
+                        follow_mem_ptr,
+                        self.ctx.sample_type,
+                        self.ctx.sample_id_all,
+                    );
+                    RecordBody::Throttle(record.wrap_box())
+                }
+                PERF_RECORD_UNTHROTTLE => {
+                    let record = unthrottle::Body::from_ptr(
+                        follow_mem_ptr,
+                        self.ctx.sample_type,
+                        self.ctx.sample_id_all,
+                    );
+                    RecordBody::Unthrottle(record.wrap_box())
+                }
+                PERF_RECORD_FORK => {
+                    let record = fork::Body::from_ptr(
+                        follow_mem_ptr,
+                        self.ctx.sample_type,
+                        self.ctx.sample_id_all,
+                    );
+                    RecordBody::Fork(record.wrap_box())
+                }
+                PERF_RECORD_READ => {
+                    let record = read::Body::from_ptr(
+                        follow_mem_ptr,
+                        self.ctx.sample_type,
+                        self.ctx.read_format,
+                        self.ctx.sample_id_all,
+                    );
+                    RecordBody::Read(record.wrap_box())
+                }
+                PERF_RECORD_SAMPLE => {
+                    let record = sample::Body::from_ptr(
+                        follow_mem_ptr,
+                        self.ctx.sample_type,
+                        self.ctx.read_format,
+                        self.ctx.branch_hw_index,
+                        self.ctx.regs_user,
+                        #[cfg(feature = "linux-3.19")]
+                        self.ctx.regs_intr,
+                    );
+                    RecordBody::Sample(record.wrap_box())
+                }
+                #[cfg(feature = "linux-3.12")]
+                PERF_RECORD_MMAP2 => {
+                    let record = mmap2::Body::from_ptr(
+                        follow_mem_ptr,
+                        self.misc(),
+                        self.ctx.sample_type,
+                        self.ctx.sample_id_all,
+                    );
+                    RecordBody::Mmap2(record.wrap_box())
+                }
+                #[cfg(feature = "linux-4.1")]
+                PERF_RECORD_AUX => {
+                    let record = aux::Body::from_ptr(
+                        follow_mem_ptr,
+                        self.ctx.sample_type,
+                        self.ctx.sample_id_all,
+                    );
+                    RecordBody::Aux(record.wrap_box())
+                }
+                #[cfg(feature = "linux-4.1")]
+                PERF_RECORD_ITRACE_START => {
+                    let ptr = follow_mem_ptr as *const intrace_start::Body;
+                    RecordBody::ItraceStart(ptr.read().wrap_box())
+                }
+                #[cfg(feature = "linux-4.2")]
+                PERF_RECORD_LOST_SAMPLES => {
+                    let record = lost_samples::Body::from_ptr(
+                        follow_mem_ptr,
+                        self.ctx.sample_type,
+                        self.ctx.sample_id_all,
+                    );
+                    RecordBody::LostSamples(record.wrap_box())
+                }
+                #[cfg(feature = "linux-4.3")]
+                PERF_RECORD_SWITCH => {
+                    let record = switch::Body::from_ptr(
+                        follow_mem_ptr,
+                        self.misc(),
+                        self.ctx.sample_type,
+                        self.ctx.sample_id_all,
+                    );
+                    RecordBody::Switch(record.wrap_box())
+                }
+                #[cfg(feature = "linux-4.3")]
+                PERF_RECORD_SWITCH_CPU_WIDE => {
+                    let record = switch_cpu_wide::Body::from_ptr(
+                        follow_mem_ptr,
+                        self.misc(),
+                        self.ctx.sample_type,
+                        self.ctx.sample_id_all,
+                    );
+                    RecordBody::SwitchCpuWide(record.wrap_box())
+                }
+                #[cfg(feature = "linux-4.12")]
+                PERF_RECORD_NAMESPACES => {
+                    let record = namespaces::Body::from_ptr(
+                        follow_mem_ptr,
+                        self.ctx.sample_type,
+                        self.ctx.sample_id_all,
+                    );
+                    RecordBody::Namespaces(record.wrap_box())
+                }
+                #[cfg(feature = "linux-5.1")]
+                PERF_RECORD_KSYMBOL => {
+                    let record = ksymbol::Body::from_ptr(
+                        follow_mem_ptr,
+                        self.ctx.sample_type,
+                        self.ctx.sample_id_all,
+                    );
+                    RecordBody::Ksymbol(record.wrap_box())
+                }
+                #[cfg(feature = "linux-5.1")]
+                PERF_RECORD_BPF_EVENT => {
+                    let record = bpf_event::Body::from_ptr(
+                        follow_mem_ptr,
+                        self.ctx.sample_type,
+                        self.ctx.sample_id_all,
+                    );
+                    RecordBody::BpfEvent(record.wrap_box())
+                }
+                #[cfg(feature = "linux-5.7")]
+                PERF_RECORD_CGROUP => {
+                    let record = cgroup::Body::from_ptr(
+                        follow_mem_ptr,
+                        self.ctx.sample_type,
+                        self.ctx.sample_id_all,
+                    );
+                    RecordBody::Cgroup(record.wrap_box())
+                }
+                #[cfg(feature = "linux-5.9")]
+                PERF_RECORD_TEXT_POKE => {
+                    let record = text_poke::Body::from_ptr(
+                        follow_mem_ptr,
+                        self.ctx.sample_type,
+                        self.ctx.sample_id_all,
+                    );
+                    RecordBody::TextPoke(record.wrap_box())
+                }
+                #[cfg(feature = "linux-5.16")]
+                PERF_RECORD_AUX_OUTPUT_HW_ID => {
+                    let ptr = follow_mem_ptr as *const aux_output_hw_id::Body;
+                    RecordBody::AuxOutputHwId(ptr.read().wrap_box())
+                }
+                // checked by `fits`
+                _ => unreachable!(),
+            }
+        };
+
+        Record {
+            misc: self.misc(),
+            body,
+        }
+    }
+}
@@ -14,7 +14,10 @@
 
 mod raw;
 
-#[derive(Debug, Clone)]
+use crate::sampling::record::encode::Writer;
+use crate::syscall::bindings::*;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SampleId {
     pub pid: Option<u32>,
     pub tid: Option<u32>,
@@ -44,4 +47,32 @@ impl SampleId {
             id_2: raw.id_2().cloned(),
         }
     }
+
+    pub(crate) fn write_to(&self, w: &mut Writer, sample_type: u64) {
+        // mask may be u64 or u32 in different linux headers
+        #[allow(clippy::unnecessary_cast)]
+        let enabled = |mask: perf_event_sample_format| (sample_type & mask as u64) > 0;
+
+        if enabled(PERF_SAMPLE_TID) {
+            w.u32(self.pid.unwrap_or_default());
+            w.u32(self.tid.unwrap_or_default());
+        }
+        if enabled(PERF_SAMPLE_TIME) {
+            w.u64(self.time.unwrap_or_default());
+        }
+        if enabled(PERF_SAMPLE_ID) {
+            w.u64(self.id_1.unwrap_or_default());
+        }
+        if enabled(PERF_SAMPLE_STREAM_ID) {
+            w.u64(self.stream_id.unwrap_or_default());
+        }
+        if enabled(PERF_SAMPLE_CPU) {
+            w.u32(self.cpu.unwrap_or_default());
+            w.u32(0); // res
+        }
+        #[cfg(feature = "linux-3.12")]
+        if enabled(PERF_SAMPLE_IDENTIFIER) {
+            w.u64(self.id_2.unwrap_or_default());
+        }
+    }
 }
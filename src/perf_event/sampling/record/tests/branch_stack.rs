@@ -0,0 +1,180 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+
+use crate::sampling::record::sample::{BranchEntry, BranchSampleType};
+use crate::sampling::record::tests::{as_bytes, gen_ctx, gen_record};
+use crate::sampling::record::{ParseContext, RecordBody, RecordRef};
+use crate::sampling::{Config, ExtraConfig, OverflowBy, SampleRecordFields};
+use crate::syscall::bindings::*;
+use crate::{Event, EventScope, HardwareEvent};
+
+const SAMPLE_TYPE: u64 = (PERF_SAMPLE_IP | PERF_SAMPLE_BRANCH_STACK | PERF_SAMPLE_DATA_SRC) as _;
+
+// mispred, predicted, in_tx, abort, cycles:16, type:4 from the lowest bit
+const FLAGS: [u64; 2] = [0b1001 | 300 << 4 | 4 << 20, 0b0110 | 7 << 4 | 6 << 20];
+
+fn gen_ctx_with(hw_index: bool) -> ParseContext {
+    ParseContext {
+        branch_hw_index: hw_index,
+        ..gen_ctx(SAMPLE_TYPE)
+    }
+}
+
+/// ip, the branch stack and data_src
+fn gen_sample(hw_idx: Option<u64>, entries: &[[u64; 3]]) -> Vec<u64> {
+    let mut words = vec![0x1000, entries.len() as u64];
+    words.extend(hw_idx);
+    entries.iter().for_each(|it| words.extend(it));
+    words.push(0x42);
+    let body: Vec<u8> = words.iter().flat_map(|it| it.to_ne_bytes()).collect();
+    gen_record(PERF_RECORD_SAMPLE, 0, &body)
+}
+
+#[cfg(target_endian = "little")]
+#[test]
+fn test_entries() {
+    let entries = [[0x10, 0x20, FLAGS[0]], [0x30, 0x40, FLAGS[1]]];
+    for hw_idx in [None, Some(5)] {
+        let words = gen_sample(hw_idx, &entries);
+        let record_ref = RecordRef {
+            bytes: as_bytes(&words),
+            ctx: gen_ctx_with(hw_idx.is_some()),
+        };
+
+        let from_ref = record_ref.sample().unwrap();
+        assert_eq!(from_ref.data_src().unwrap().to_raw(), 0x42);
+
+        let RecordBody::Sample(body) = record_ref.to_record().body else {
+            panic!("Not a sample");
+        };
+        assert_eq!(body.ip, Some(0x1000));
+        assert_eq!(body.data_src.unwrap().to_raw(), 0x42);
+
+        let stack = body.branch_stack.unwrap();
+        assert_eq!(from_ref.branch_stack().unwrap(), stack);
+        assert_eq!(stack.hw_idx, hw_idx);
+        assert_eq!(stack.entries.len(), 2);
+
+        let BranchEntry {
+            from,
+            to,
+            mispred,
+            predicted,
+            in_tx,
+            abort,
+            cycles,
+            ..
+        } = stack.entries[0];
+        assert_eq!((from, to), (0x10, 0x20));
+        assert_eq!(
+            (mispred, predicted, in_tx, abort),
+            (true, false, false, true)
+        );
+        assert_eq!(cycles, 300);
+
+        let entry = &stack.entries[1];
+        assert_eq!((entry.from, entry.to), (0x30, 0x40));
+        assert_eq!(
+            (entry.mispred, entry.predicted, entry.in_tx, entry.abort),
+            (false, true, true, false)
+        );
+        assert_eq!(entry.cycles, 7);
+
+        #[cfg(feature = "linux-4.14")]
+        {
+            use crate::sampling::record::sample::BranchType;
+            assert_eq!(stack.entries[0].type_, BranchType::Call);
+            assert_eq!(stack.entries[1].type_, BranchType::Ret);
+        }
+    }
+}
+
+#[cfg(target_endian = "little")]
+#[test]
+fn test_round_trip_flags() {
+    // spec:2, new_type:4, priv:3 and the reserved bits above type
+    let flags = FLAGS[0] | 0b10 << 24 | 0b1001 << 26 | 0b101 << 30 | u64::MAX << 33;
+    let words = gen_sample(Some(5), &[[0x10, 0x20, flags]]);
+    let record_ref = RecordRef {
+        bytes: as_bytes(&words),
+        ctx: gen_ctx_with(true),
+    };
+
+    let record = record_ref.to_record();
+    let RecordBody::Sample(body) = &record.body else {
+        panic!("Not a sample");
+    };
+    let entry = &body.branch_stack.as_ref().unwrap().entries[0];
+    assert_eq!(entry.raw_flags, flags);
+    assert_eq!(entry.cycles, 300);
+    assert_eq!(
+        record.encode(&gen_ctx_with(true)).unwrap(),
+        as_bytes(&words)
+    );
+}
+
+#[test]
+fn test_empty_and_truncated() {
+    let words = gen_sample(None, &[]);
+    let record_ref = RecordRef {
+        bytes: as_bytes(&words),
+        ctx: gen_ctx_with(false),
+    };
+    let stack = record_ref.sample().unwrap().branch_stack().unwrap();
+    assert!(stack.entries.is_empty());
+    assert_eq!(stack.hw_idx, None);
+
+    // bnr claims more entries than the record holds
+    let mut words = gen_sample(None, &[[0x10, 0x20, 0]]);
+    words[2] = 2;
+    let record_ref = RecordRef {
+        bytes: as_bytes(&words),
+        ctx: gen_ctx_with(false),
+    };
+    assert!(record_ref.sample().is_none());
+    let body = record_ref.to_record().body;
+    assert!(matches!(body, RecordBody::Unknown { .. }));
+}
+
+#[test]
+fn test_config() {
+    let filter = BranchSampleType::USER | BranchSampleType::ANY_CALL;
+    let extra_config = ExtraConfig {
+        sample_record_fields: SampleRecordFields {
+            branch_stack: Some(filter),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let ev = Event::from(HardwareEvent::CpuCycles);
+    let overflow_by = OverflowBy::Period(1000);
+    let cfg = Config::extra_new(&ev, &EventScope::all(), &overflow_by, &extra_config);
+    let attr = cfg.as_raw();
+    assert_eq!(attr.branch_sample_type, filter.bits());
+    assert!(attr.sample_type & PERF_SAMPLE_BRANCH_STACK as u64 > 0);
+
+    #[cfg(feature = "linux-5.7")]
+    {
+        let filter = filter | BranchSampleType::HW_INDEX;
+        let extra_config = ExtraConfig {
+            sample_record_fields: SampleRecordFields {
+                branch_stack: Some(filter),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let cfg = Config::extra_new(&ev, &EventScope::all(), &overflow_by, &extra_config);
+        assert!(ParseContext::new(cfg.as_raw()).branch_hw_index);
+    }
+}
@@ -0,0 +1,112 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+
+use crate::sampling::record::sample::{DataSrc, MemLvl, MemOp, MemSnoop};
+use crate::syscall::bindings::*;
+
+fn raw(op: u32, lvl: u32, snoop: u32, lock: u32, dtlb: u32) -> u64 {
+    (op as u64) << PERF_MEM_OP_SHIFT
+        | (lvl as u64) << PERF_MEM_LVL_SHIFT
+        | (snoop as u64) << PERF_MEM_SNOOP_SHIFT
+        | (lock as u64) << PERF_MEM_LOCK_SHIFT
+        | (dtlb as u64) << PERF_MEM_TLB_SHIFT
+}
+
+#[test]
+fn test_combined_bits() {
+    let data_src = DataSrc::from_raw(raw(
+        PERF_MEM_OP_LOAD,
+        PERF_MEM_LVL_HIT | PERF_MEM_LVL_L1 | PERF_MEM_LVL_L2,
+        PERF_MEM_SNOOP_HIT | PERF_MEM_SNOOP_HITM,
+        PERF_MEM_LOCK_NA,
+        PERF_MEM_TLB_L1 | PERF_MEM_TLB_L2 | PERF_MEM_TLB_HIT,
+    ));
+    assert_eq!(data_src.mem_op, MemOp::LOAD);
+    assert_eq!(data_src.mem_lvl, MemLvl::HIT | MemLvl::L1 | MemLvl::L2);
+    assert_eq!(data_src.mem_snoop, MemSnoop::HIT | MemSnoop::HITM);
+    assert_eq!(data_src.access(), "L1 or L2 hit");
+    assert_eq!(data_src.snoop(), "Hit or HitM");
+    assert_eq!(data_src.tlb(), "L1 or L2 hit");
+    assert_eq!(data_src.locked(), "N/A");
+    assert_eq!(data_src.blocked(), "N/A");
+    assert_eq!(
+        data_src.to_string(),
+        "|OP LOAD|LVL L1 or L2 hit|SNP Hit or HitM|TLB L1 or L2 hit|LCK N/A|BLK  N/A"
+    );
+}
+
+#[test]
+fn test_unset() {
+    let data_src = DataSrc::from_raw(0);
+    assert_eq!(data_src.op(), "N/A");
+    assert_eq!(data_src.access(), "N/A");
+    assert_eq!(data_src.snoop(), "N/A");
+    assert_eq!(data_src.tlb(), "N/A");
+    assert_eq!(data_src.locked(), "No");
+    assert_eq!(data_src.blocked(), "N/A");
+
+    let data_src = DataSrc::from_raw(raw(0, PERF_MEM_LVL_NA | PERF_MEM_LVL_MISS, 0, 0, 0));
+    assert_eq!(data_src.access(), "N/A");
+
+    // Neither HIT nor MISS
+    let data_src = DataSrc::from_raw(raw(0, PERF_MEM_LVL_L3, 0, 0, 0));
+    assert_eq!(data_src.access(), "L3");
+}
+
+#[cfg(feature = "linux-6.0")]
+#[test]
+fn test_lvl_num() {
+    use crate::sampling::record::sample::{MemBlk, MemHops, MemLvlNum, MemSnoopX};
+
+    let bits = raw(
+        PERF_MEM_OP_STORE,
+        PERF_MEM_LVL_HIT,
+        PERF_MEM_SNOOP_NONE,
+        PERF_MEM_LOCK_LOCKED,
+        PERF_MEM_TLB_WK | PERF_MEM_TLB_MISS,
+    ) | (PERF_MEM_LVLNUM_L3 as u64) << PERF_MEM_LVLNUM_SHIFT
+        | (PERF_MEM_REMOTE_REMOTE as u64) << PERF_MEM_REMOTE_SHIFT
+        | (PERF_MEM_SNOOPX_FWD as u64) << PERF_MEM_SNOOPX_SHIFT
+        | ((PERF_MEM_BLK_DATA | PERF_MEM_BLK_ADDR) as u64) << PERF_MEM_BLK_SHIFT
+        | (PERF_MEM_HOPS_2 as u64) << PERF_MEM_HOPS_SHIFT;
+    let data_src = DataSrc::from_raw(bits);
+    assert_eq!(data_src.mem_lvl_num, Some(MemLvlNum::L3));
+    assert!(data_src.mem_remote);
+    assert_eq!(data_src.mem_snoopx, MemSnoopX::FWD);
+    assert_eq!(data_src.mem_blk, MemBlk::DATA | MemBlk::ADDR);
+    assert_eq!(data_src.mem_hops, Some(MemHops::Hops2));
+    assert_eq!(data_src.to_raw(), bits);
+
+    assert_eq!(data_src.access(), "Remote socket, same board L3 hit");
+    assert_eq!(data_src.snoop(), "None or Fwd");
+    assert_eq!(data_src.tlb(), "Walker miss");
+    assert_eq!(data_src.locked(), "Yes");
+    assert_eq!(data_src.blocked(), "Data Addr");
+    assert_eq!(
+        data_src.to_string(),
+        "|OP STORE|LVL Remote socket, same board L3 hit|SNP None or Fwd|TLB Walker miss|LCK Yes|BLK  Data Addr"
+    );
+
+    // `mem_lvl_num` takes over `mem_lvl` unless it is N/A
+    let na = (PERF_MEM_LVLNUM_NA as u64) << PERF_MEM_LVLNUM_SHIFT;
+    let data_src =
+        DataSrc::from_raw(raw(0, PERF_MEM_LVL_MISS | PERF_MEM_LVL_LOC_RAM, 0, 0, 0) | na);
+    assert_eq!(data_src.mem_lvl_num, Some(MemLvlNum::Na));
+    assert_eq!(data_src.access(), "Local RAM miss");
+
+    let unknown = 7_u64 << PERF_MEM_LVLNUM_SHIFT;
+    let data_src = DataSrc::from_raw(raw(0, PERF_MEM_LVL_HIT, 0, 0, 0) | unknown);
+    assert_eq!(data_src.mem_lvl_num, Some(MemLvlNum::Unknown(7)));
+    assert_eq!(data_src.access(), "Unknown level 7 hit");
+}
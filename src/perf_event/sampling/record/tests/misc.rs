@@ -0,0 +1,113 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+
+use crate::sampling::record::{CpuMode, Misc, MiscFlags};
+use crate::syscall::bindings::*;
+
+#[test]
+fn test_cpu_mode() {
+    let modes = [
+        (PERF_RECORD_MISC_CPUMODE_UNKNOWN, CpuMode::Unknown),
+        (PERF_RECORD_MISC_KERNEL, CpuMode::Kernel),
+        (PERF_RECORD_MISC_USER, CpuMode::User),
+        (PERF_RECORD_MISC_HYPERVISOR, CpuMode::Hypervisor),
+        (PERF_RECORD_MISC_GUEST_KERNEL, CpuMode::GuestKernel),
+        (PERF_RECORD_MISC_GUEST_USER, CpuMode::GuestUser),
+        (7, CpuMode::Unknown),
+    ];
+    for (misc, mode) in modes {
+        let misc = misc | PERF_RECORD_MISC_EXACT_IP;
+        assert_eq!(CpuMode::from_misc(misc as _), mode);
+    }
+}
+
+#[test]
+fn test_flags_by_type() {
+    // the same bit means different things
+    let misc = (PERF_RECORD_MISC_USER | 1 << 13) as u16;
+    let flags = |type_| Misc::new(type_, misc).flags;
+    assert_eq!(
+        flags(PERF_RECORD_MMAP),
+        MiscFlags::Mmap {
+            data: true,
+            proc_map_parse_timeout: false
+        }
+    );
+    assert_eq!(flags(PERF_RECORD_COMM), MiscFlags::Comm { exec: true });
+    assert_eq!(flags(PERF_RECORD_FORK), MiscFlags::Fork { exec: true });
+    assert_eq!(
+        flags(PERF_RECORD_SAMPLE),
+        MiscFlags::Sample { exact_ip: false }
+    );
+    assert_eq!(flags(PERF_RECORD_LOST), MiscFlags::None);
+    #[cfg(feature = "linux-4.3")]
+    assert!(matches!(
+        flags(PERF_RECORD_SWITCH),
+        MiscFlags::Switch { out: true, .. }
+    ));
+
+    let misc = Misc::new(PERF_RECORD_SAMPLE, misc | PERF_RECORD_MISC_EXACT_IP as u16);
+    assert_eq!(misc.cpu_mode, CpuMode::User);
+    assert_eq!(misc.flags, MiscFlags::Sample { exact_ip: true });
+    assert!(!misc.ext_reserved);
+}
+
+#[cfg(feature = "linux-4.3")]
+#[test]
+fn test_switch_out() {
+    use crate::sampling::record::tests::{as_bytes, gen_ctx, gen_record};
+    use crate::sampling::record::{Record, RecordBody};
+
+    let ctx = gen_ctx(0);
+    for misc in [0, PERF_RECORD_MISC_SWITCH_OUT] {
+        let misc = (misc | PERF_RECORD_MISC_KERNEL) as u16;
+        let words = gen_record(PERF_RECORD_SWITCH, misc, &[]);
+        let record = Record::parse(as_bytes(&words), &ctx).unwrap();
+        assert_eq!(record.cpu_mode(), CpuMode::Kernel);
+        let RecordBody::Switch(body) = &record.body else {
+            panic!("Unexpected record: {:?}", record);
+        };
+        assert_eq!(
+            body.is_out(),
+            misc as u32 & PERF_RECORD_MISC_SWITCH_OUT != 0
+        );
+        assert!(matches!(
+            record.typed_misc().flags,
+            MiscFlags::Switch { out, .. } if out == (misc as u32 & PERF_RECORD_MISC_SWITCH_OUT != 0)
+        ));
+        assert_eq!(
+            record.typed_misc(),
+            Misc::new(PERF_RECORD_SWITCH, record.misc)
+        );
+
+        // the encoded `misc` follows the body
+        let flipped = Record {
+            misc: misc ^ PERF_RECORD_MISC_SWITCH_OUT as u16,
+            ..record.clone()
+        };
+        assert_eq!(flipped.encode(&ctx).unwrap(), as_bytes(&words));
+    }
+
+    #[cfg(feature = "linux-4.17")]
+    {
+        let misc = (PERF_RECORD_MISC_SWITCH_OUT | PERF_RECORD_MISC_SWITCH_OUT_PREEMPT) as u16;
+        let words = gen_record(PERF_RECORD_SWITCH_CPU_WIDE, misc, &[0; 8]);
+        let record = Record::parse(as_bytes(&words), &ctx).unwrap();
+        let RecordBody::SwitchCpuWide(body) = &record.body else {
+            panic!("Unexpected record: {:?}", record);
+        };
+        assert!(body.is_out() && body.is_preempt());
+        assert_eq!(record.encode(&ctx).unwrap(), as_bytes(&words));
+    }
+}
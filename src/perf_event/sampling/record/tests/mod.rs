@@ -0,0 +1,170 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+
+mod branch_stack;
+mod data_src;
+mod encode;
+mod misc;
+mod parse;
+mod regs;
+#[cfg(feature = "linux-3.13")]
+mod transaction;
+
+use crate::sampling::record::sample::{Abi, DataSrc, RegMask};
+use crate::sampling::record::{ParseContext, Record, RecordBody, RecordRef};
+use crate::syscall::bindings::*;
+
+/// Record with header, as 8 bytes aligned words
+pub fn gen_record(type_: u32, misc: u16, body: &[u8]) -> Vec<u64> {
+    let size = 8 + body.len() as u16;
+    let mut bytes = vec![];
+    bytes.extend_from_slice(&type_.to_ne_bytes());
+    bytes.extend_from_slice(&misc.to_ne_bytes());
+    bytes.extend_from_slice(&size.to_ne_bytes());
+    bytes.extend_from_slice(body);
+    assert_eq!(bytes.len() % 8, 0);
+    bytes
+        .chunks(8)
+        .map(|it| u64::from_ne_bytes(it.try_into().unwrap()))
+        .collect()
+}
+
+pub fn as_bytes(words: &[u64]) -> &[u8] {
+    unsafe { std::slice::from_raw_parts(words.as_ptr() as _, words.len() * 8) }
+}
+
+pub fn gen_ctx(sample_type: u64) -> ParseContext {
+    ParseContext {
+        sample_type,
+        sample_id_all: false,
+        read_format: 0,
+        branch_hw_index: false,
+        regs_user: RegMask::empty(),
+        #[cfg(feature = "linux-3.19")]
+        regs_intr: RegMask::empty(),
+    }
+}
+
+fn to_record(words: &[u64], sample_type: u64) -> Record {
+    RecordRef {
+        bytes: as_bytes(words),
+        ctx: gen_ctx(sample_type),
+    }
+    .to_record()
+}
+
+#[test]
+fn test_unknown_type() {
+    let words = gen_record(0xffff, 1, &[7; 16]);
+    let Record { misc, body } = to_record(&words, 0);
+    assert_eq!(misc, 1);
+    match body {
+        RecordBody::Unknown { type_, misc, bytes } => {
+            assert_eq!(type_, 0xffff);
+            assert_eq!(misc, 1);
+            assert_eq!(bytes, vec![7; 16]);
+        }
+        body => panic!("Unexpected record: {:?}", body),
+    }
+}
+
+#[test]
+fn test_comm() {
+    let mut body = vec![];
+    body.extend_from_slice(&1_u32.to_ne_bytes());
+    body.extend_from_slice(&2_u32.to_ne_bytes());
+    body.extend_from_slice(b"comm\0\0\0\0");
+    let words = gen_record(PERF_RECORD_COMM, 0, &body);
+    match to_record(&words, 0).body {
+        RecordBody::Comm(body) => {
+            assert_eq!(body.pid, 1);
+            assert_eq!(body.tid, 2);
+            assert_eq!(body.comm.to_bytes(), b"comm");
+        }
+        body => panic!("Unexpected record: {:?}", body),
+    }
+
+    // not zero terminated
+    body.truncate(8);
+    body.extend_from_slice(b"commcomm");
+    let words = gen_record(PERF_RECORD_COMM, 0, &body);
+    let body = to_record(&words, 0).body;
+    assert!(matches!(body, RecordBody::Unknown { .. }));
+}
+
+#[test]
+fn test_truncated_sample() {
+    let sample_type = (PERF_SAMPLE_IP | PERF_SAMPLE_CALLCHAIN) as u64;
+    // ip, nr and 2 ips
+    let mut body = vec![];
+    for word in [0x1000_u64, 2, 0x2000, 0x3000] {
+        body.extend_from_slice(&word.to_ne_bytes());
+    }
+    let words = gen_record(PERF_RECORD_SAMPLE, 0, &body);
+    match to_record(&words, sample_type).body {
+        RecordBody::Sample(body) => {
+            assert_eq!(body.ip, Some(0x1000));
+            assert_eq!(body.ips, Some(vec![0x2000, 0x3000]));
+        }
+        body => panic!("Unexpected record: {:?}", body),
+    }
+
+    // nr is larger than the record
+    body[8..16].copy_from_slice(&u64::MAX.to_ne_bytes());
+    let words = gen_record(PERF_RECORD_SAMPLE, 0, &body);
+    let record_ref = RecordRef {
+        bytes: as_bytes(&words),
+        ctx: gen_ctx(sample_type),
+    };
+    assert!(record_ref.sample().is_none());
+    let body = record_ref.to_record().body;
+    assert!(matches!(body, RecordBody::Unknown { .. }));
+}
+
+#[cfg(feature = "linux-5.9")]
+#[test]
+fn test_text_poke() {
+    let mut body = vec![];
+    body.extend_from_slice(&0x1000_u64.to_ne_bytes());
+    body.extend_from_slice(&2_u16.to_ne_bytes());
+    body.extend_from_slice(&3_u16.to_ne_bytes());
+    body.extend_from_slice(&[0, 1, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0]);
+    let words = gen_record(PERF_RECORD_TEXT_POKE, 0, &body);
+    match to_record(&words, 0).body {
+        RecordBody::TextPoke(body) => {
+            assert_eq!(body.addr, 0x1000);
+            assert_eq!(body.bytes, vec![0, 1, 0, 2, 0]);
+        }
+        body => panic!("Unexpected record: {:?}", body),
+    }
+}
+
+#[test]
+fn test_unknown_bits() {
+    assert_eq!(Abi::from_raw(42), Abi::Unknown(42));
+
+    let data_src = DataSrc::from_raw(0);
+    assert!(data_src.mem_op.is_empty());
+    assert!(data_src.mem_lvl.is_empty());
+    assert!(data_src.mem_snoop.is_empty());
+    assert!(data_src.mem_lock.is_empty());
+    assert!(data_src.mem_dtlb.is_empty());
+    assert_eq!(data_src.mem_lvl_num, None);
+    assert_eq!(data_src.mem_hops, None);
+
+    let data_src = DataSrc::from_raw(u64::MAX);
+    assert_eq!(data_src.mem_lvl.bits(), 0x3fff);
+    assert_eq!(data_src.mem_rsvd, 0x3ffff);
+    assert_eq!(data_src.to_raw(), u64::MAX);
+}
@@ -0,0 +1,187 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+
+use crate::config::{Cpu, Process};
+use crate::sampling::record::sample::RegMask;
+use crate::sampling::record::tests::{as_bytes, gen_ctx, gen_record};
+use crate::sampling::record::{ParseContext, ParseError, Record, RecordBody};
+use crate::sampling::{Config, ExtraConfig, OverflowBy, SampleRecordFields, Sampler};
+use crate::syscall::bindings::*;
+use crate::test::cpu_workload;
+use crate::{Event, EventScope, SoftwareEvent};
+
+#[test]
+fn test_parse_errors() {
+    let ctx = gen_ctx(0);
+    assert_eq!(
+        Record::parse(&[0; 4], &ctx).unwrap_err(),
+        ParseError::TooShort
+    );
+
+    let words = gen_record(PERF_RECORD_LOST, 0, &[0; 16]);
+    let bytes = as_bytes(&words);
+    assert_eq!(
+        Record::parse(&bytes[..16], &ctx).unwrap_err(),
+        ParseError::SizeMismatch { size: 24, len: 16 }
+    );
+
+    let words = gen_record(PERF_RECORD_LOST, 0, &[0; 8]);
+    assert_eq!(
+        Record::parse(as_bytes(&words), &ctx).unwrap_err(),
+        ParseError::Malformed(PERF_RECORD_LOST)
+    );
+
+    let words = gen_record(0xffff, 0, &[0; 8]);
+    let record = Record::parse(as_bytes(&words), &ctx).unwrap();
+    assert!(matches!(
+        record.body,
+        RecordBody::Unknown { type_: 0xffff, .. }
+    ));
+}
+
+#[test]
+fn test_parse_unaligned() {
+    let mut body = vec![];
+    body.extend_from_slice(&1_u64.to_ne_bytes());
+    body.extend_from_slice(&2_u64.to_ne_bytes());
+    let words = gen_record(PERF_RECORD_LOST, 0, &body);
+
+    let mut buf = vec![0_u8];
+    buf.extend_from_slice(as_bytes(&words));
+    match Record::parse(&buf[1..], &gen_ctx(0)).unwrap().body {
+        RecordBody::Lost(body) => {
+            assert_eq!(body.id, 1);
+            assert_eq!(body.lost, 2);
+        }
+        body => panic!("Unexpected record: {:?}", body),
+    }
+}
+
+/// `struct read_format` is walked by the bits of `read_format`,
+/// a wrong length would misplace the callchain after it
+#[test]
+fn test_parse_read_format() {
+    let sample_type = (PERF_SAMPLE_READ | PERF_SAMPLE_CALLCHAIN) as u64;
+    // `PERF_FORMAT_LOST` is 1 << 4, even with headers before Linux 6.0
+    let cases = [
+        // value, time_enabled, id, lost
+        (
+            (PERF_FORMAT_TOTAL_TIME_ENABLED | PERF_FORMAT_ID) as u64 | 1 << 4,
+            vec![7, 100, 42, 3],
+            (100, 0, 42, 7, 3),
+        ),
+        // value
+        (0, vec![7], (0, 0, 0, 7, 0)),
+        // nr, time_running, value
+        (
+            (PERF_FORMAT_GROUP | PERF_FORMAT_TOTAL_TIME_RUNNING) as u64,
+            vec![1, 5, 9],
+            (0, 5, 0, 9, 0),
+        ),
+    ];
+    for (read_format, mut words, expected) in cases {
+        let ctx = ParseContext {
+            read_format,
+            ..gen_ctx(sample_type)
+        };
+        words.extend([1, 0xdead]);
+        let record_words = gen_record(PERF_RECORD_SAMPLE, 0, as_bytes(&words));
+        let bytes = as_bytes(&record_words);
+
+        let record = Record::parse(bytes, &ctx).unwrap();
+        let RecordBody::Sample(body) = &record.body else {
+            panic!("Unexpected record: {:?}", record);
+        };
+        let (time_enabled, time_running, id, event_count, lost) = expected;
+        let v = body.v.as_ref().unwrap();
+        assert_eq!(
+            (v.time_enabled, v.time_running),
+            (time_enabled, time_running)
+        );
+        assert_eq!(v.member_counts.len(), 1);
+        let member_count = &v.member_counts[&id];
+        assert_eq!(member_count.event_count, event_count);
+        #[cfg(feature = "linux-6.0")]
+        assert_eq!(member_count.event_lost, lost);
+        assert_eq!(body.ips.as_deref(), Some(&[0xdead][..]));
+
+        // `lost` is written as 0 without `MemberCount::event_lost`
+        if cfg!(feature = "linux-6.0") || lost == 0 {
+            assert_eq!(record.encode(&ctx).unwrap(), bytes);
+        }
+    }
+}
+
+/// Random records must be decoded or rejected, without panics
+#[test]
+fn test_parse_random() {
+    let sample_type = (PERF_SAMPLE_IP
+        | PERF_SAMPLE_TID
+        | PERF_SAMPLE_TIME
+        | PERF_SAMPLE_READ
+        | PERF_SAMPLE_CALLCHAIN
+        | PERF_SAMPLE_RAW
+        | PERF_SAMPLE_REGS_USER
+        | PERF_SAMPLE_STACK_USER
+        | PERF_SAMPLE_DATA_SRC) as u64;
+    let ctx = ParseContext {
+        sample_id_all: true,
+        // group with all optional fields
+        read_format: 0x1f,
+        regs_user: RegMask::from_bits(0b111),
+        ..gen_ctx(sample_type)
+    };
+
+    for _ in 0..100000 {
+        let len = 8 + rand::random::<usize>() % 256;
+        let mut buf: Vec<u8> = (0..len).map(|_| rand::random::<u8>() % 4).collect();
+        buf[0..4].copy_from_slice(&(rand::random::<u32>() % 24).to_ne_bytes());
+        buf[6..8].copy_from_slice(&(len as u16).to_ne_bytes());
+        let _ = Record::parse(&buf, &ctx);
+    }
+}
+
+#[test]
+fn test_parse_sampler_records() {
+    let scopes = EventScope::all();
+    let ev = Event::from(SoftwareEvent::CpuClock);
+    let overflow_by = OverflowBy::Period(100000);
+    let extra_config = ExtraConfig {
+        comm: true,
+        sample_record_fields: SampleRecordFields {
+            ip: true,
+            pid_and_tid: true,
+            time: true,
+            ips: Some(16),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let cfg = Config::extra_new(&ev, &scopes, &overflow_by, &extra_config);
+    let ctx = ParseContext::new(cfg.as_raw());
+    let mut sampler = Sampler::new(&Process::Current, &Cpu::Any, 1 + 16, &cfg).unwrap();
+
+    sampler.enable().unwrap();
+    cpu_workload();
+    sampler.disable().unwrap();
+
+    let mut count = 0;
+    while let Some(record_ref) = sampler.next_record_ref() {
+        let parsed = Record::parse(record_ref.bytes(), &ctx).unwrap();
+        let record = record_ref.to_record();
+        assert_eq!(format!("{:?}", parsed), format!("{:?}", record));
+        count += 1;
+    }
+    assert!(count > 0);
+}
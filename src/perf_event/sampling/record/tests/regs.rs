@@ -0,0 +1,105 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+
+use crate::config::{Cpu, Process};
+use crate::sampling::record::sample::*;
+use crate::sampling::record::{ParseContext, RecordBody};
+use crate::sampling::{Config, ExtraConfig, OverflowBy, SampleRecordFields, Sampler};
+use crate::test::cpu_workload;
+use crate::{Event, EventScope, SoftwareEvent};
+
+#[test]
+fn test_mask() {
+    let mask = RegMask::of([X86_64Reg::Ip, X86_64Reg::Sp, X86_64Reg::Bp]);
+    assert_eq!(mask.bits(), 1 << 8 | 1 << 7 | 1 << 6);
+    assert_eq!(mask.len(), 3);
+    assert!(mask.contains(X86_64Reg::Sp));
+    assert!(!mask.contains(X86_64Reg::Ax));
+    assert_eq!(mask.with(X86_64Reg::R15).bits(), mask.bits() | 1 << 23);
+
+    let mask = RegMask::of([Aarch64Reg::Pc, Aarch64Reg::Sp, Aarch64Reg::Lr]);
+    assert_eq!(mask.bits(), 0b111 << 30);
+    let mask = RegMask::of([Riscv64Reg::Pc, Riscv64Reg::Sp]);
+    assert_eq!(mask.bits(), 0b101);
+    assert!(RegMask::of::<X86Reg>([]).is_empty());
+}
+
+#[test]
+fn test_view() {
+    let mask = RegMask::of([X86_64Reg::Ax, X86_64Reg::Sp, X86_64Reg::Ip, X86_64Reg::R8]);
+    let values = [0x1_0000_0001, 2, 3, 4];
+    let view = RegsView::new(Abi::Abi64, mask, &values);
+    assert_eq!(view.get(X86_64Reg::Ax), Some(0x1_0000_0001));
+    assert_eq!(view.get(X86_64Reg::Sp), Some(2));
+    assert_eq!(view.get(X86_64Reg::Ip), Some(3));
+    assert_eq!(view.get(X86_64Reg::R8), Some(4));
+    assert_eq!(view.get(X86_64Reg::Bp), None);
+
+    // 32-bit tasks have no R8 and their registers are 32-bit
+    let view = RegsView::new(Abi::Abi32, mask, &values);
+    assert_eq!(view.get(X86_64Reg::Ax), Some(1));
+    assert_eq!(view.get(X86Reg::Sp), Some(2));
+    assert_eq!(view.get(X86_64Reg::R8), None);
+
+    let view = RegsView::new(Abi::AbiNone, mask, &[]);
+    assert_eq!(view.get(X86_64Reg::Ip), None);
+
+    let mask = RegMask::of([Aarch64Reg::X0, Aarch64Reg::X20, Aarch64Reg::Sp]);
+    let view = RegsView::new(Abi::Abi32, mask, &[1, 2, 3]);
+    assert_eq!(view.get(Aarch64Reg::X0), Some(1));
+    assert_eq!(view.get(Aarch64Reg::X20), None);
+    assert_eq!(view.get(Aarch64Reg::Sp), Some(3));
+}
+
+#[cfg(any(
+    target_arch = "x86_64",
+    target_arch = "x86",
+    target_arch = "aarch64",
+    target_arch = "riscv64"
+))]
+#[test]
+fn test_sampled_regs() {
+    let ev = Event::from(SoftwareEvent::CpuClock);
+    let overflow_by = OverflowBy::Period(100000);
+    let extra_config = ExtraConfig {
+        sample_record_fields: SampleRecordFields {
+            ip: true,
+            abi_and_regs_user: Some(RegMask::of([Reg::Ip, Reg::Sp])),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let cfg = Config::extra_new(&ev, &[EventScope::User], &overflow_by, &extra_config);
+    let ctx = ParseContext::new(cfg.as_raw());
+    let mut sampler = Sampler::new(&Process::Current, &Cpu::Any, 1 + 16, &cfg).unwrap();
+
+    sampler.enable().unwrap();
+    cpu_workload();
+    sampler.disable().unwrap();
+
+    let mut samples = 0;
+    for record in sampler.iter() {
+        let RecordBody::Sample(body) = record.body else {
+            continue;
+        };
+        let regs = body.abi_and_regs_user.as_ref().unwrap();
+        assert_eq!(regs.mask, ctx.regs_user);
+        let view = regs.view();
+        // user-only sampling, the ip is the user one
+        assert_eq!(view.get(Reg::Ip), body.ip);
+        assert!(view.get(Reg::Sp).unwrap() > 0);
+        samples += 1;
+    }
+    assert!(samples > 0);
+}
@@ -0,0 +1,40 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+
+use crate::sampling::record::sample::{Transaction, TxnFlags};
+use crate::syscall::bindings::*;
+
+#[test]
+fn test_transaction() {
+    let raw = 0xdead_u64 << PERF_TXN_ABORT_SHIFT
+        | PERF_TXN_TRANSACTION
+        | PERF_TXN_SYNC
+        | PERF_TXN_CONFLICT
+        | PERF_TXN_RETRY;
+    let txn = Transaction::from_raw(raw);
+    assert_eq!(txn.abort_code, 0xdead);
+    assert_eq!(
+        txn.flags,
+        TxnFlags::TRANSACTION | TxnFlags::SYNC | TxnFlags::CONFLICT | TxnFlags::RETRY
+    );
+    assert!(!txn.flags.contains(TxnFlags::ELISION));
+    assert_eq!(txn.to_raw(), raw);
+
+    // unknown bits survive
+    let raw = PERF_TXN_MAX | PERF_TXN_CAPACITY_READ;
+    let txn = Transaction::from_raw(raw);
+    assert!(txn.flags.contains(TxnFlags::CAPACITY_READ));
+    assert_eq!(txn.abort_code, 0);
+    assert_eq!(txn.to_raw(), raw);
+}
@@ -0,0 +1,252 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+#[cfg(test)]
+mod tests;
+
+use crate::config;
+use crate::config::{Cpu, Error, Process};
+use crate::perf_event::PerfEventAttr;
+use crate::sampling::record::{ParseContext, Record, RecordRef};
+use crate::sampling::{Config, Pollable, Sampler};
+use crate::syscall::bindings::*;
+use crate::syscall::{ioctl_wrapped, perf_event_open_wrapped};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::os::fd::{AsRawFd, FromRawFd, RawFd};
+
+/*
+Records of all events are written into the ring buffer of the first event,
+`PERF_SAMPLE_IDENTIFIER` and `sample_id_all` are enabled for every event,
+so the event id is at a fixed position of every record:
+the first u64 of the body for `PERF_RECORD_SAMPLE`, the last u64 otherwise.
+*/
+
+struct Member {
+    /// `None` for the first event, which is owned by the output sampler
+    file: Option<File>,
+    event_id: u64,
+    ctx: ParseContext,
+}
+
+/// Handle of an event in [`SharedSampler`], in the order the events were opened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MemberHandle(usize);
+
+/// Events writing into one ring buffer via `PERF_EVENT_IOC_SET_OUTPUT`,
+/// records are tagged with the event id of their origin.
+///
+/// Every event must be opened on the same CPU, or for the same task if `Cpu::Any`.
+pub struct SharedSampler {
+    output: Sampler,
+    pid: i32,
+    cpu: i32,
+    /// Members are in the group of the first event
+    group: bool,
+    /// Indexed by `MemberHandle`
+    members: Vec<Member>,
+    /// Map of event id -> handle
+    handles: HashMap<u64, MemberHandle>,
+}
+
+fn with_identifier(cfg: &Config) -> PerfEventAttr {
+    let mut perf_event_attr = cfg.as_raw().clone();
+    perf_event_attr.sample_type |= PERF_SAMPLE_IDENTIFIER as u64;
+    perf_event_attr.set_sample_id_all(1);
+    perf_event_attr
+}
+
+impl SharedSampler {
+    /// Open the first event, which owns the ring buffer of `mmap_pages`.
+    pub fn new(
+        process: &Process,
+        cpu: &Cpu,
+        mmap_pages: usize,
+        cfg: &Config,
+    ) -> config::Result<Self> {
+        Self::open(process, cpu, mmap_pages, cfg, false)
+    }
+
+    /// Like `new`, but the members are in the group of the first event,
+    /// so they are scheduled and enabled together.
+    pub fn new_group(
+        process: &Process,
+        cpu: &Cpu,
+        mmap_pages: usize,
+        cfg: &Config,
+    ) -> config::Result<Self> {
+        Self::open(process, cpu, mmap_pages, cfg, true)
+    }
+
+    fn open(
+        process: &Process,
+        cpu: &Cpu,
+        mmap_pages: usize,
+        cfg: &Config,
+        group: bool,
+    ) -> config::Result<Self> {
+        let (pid, cpu) = match (process.as_i32()?, cpu.as_i32()) {
+            (-1, -1) => return Err(Error::InvalidProcessCpu),
+            (pid, cpu) => (pid, cpu),
+        };
+        let perf_event_attr = with_identifier(cfg);
+        let fd = unsafe { perf_event_open_wrapped(&perf_event_attr, pid, cpu, -1, 0) }
+            .map_err(Error::SyscallFailed)?;
+        let file = unsafe { File::from_raw_fd(fd) };
+        let output =
+            Sampler::from_file(file, mmap_pages, &perf_event_attr).map_err(Error::SyscallFailed)?;
+
+        let event_id = output.event_id().map_err(Error::SyscallFailed)?;
+        let member = Member {
+            file: None,
+            event_id,
+            ctx: output.parse_context(),
+        };
+        Ok(Self {
+            output,
+            pid,
+            cpu,
+            group,
+            members: vec![member],
+            handles: HashMap::from([(event_id, Self::OUTPUT)]),
+        })
+    }
+
+    /// Handle of the first event, which owns the ring buffer.
+    pub const OUTPUT: MemberHandle = MemberHandle(0);
+
+    /// Open another event on the same process and CPU, which writes into
+    /// the shared ring buffer.
+    pub fn add_member(&mut self, cfg: &Config) -> config::Result<MemberHandle> {
+        self.open_member(self.pid, cfg)
+    }
+
+    /// Like `add_member`, but for another process, e.g. samplers of several
+    /// processes on one CPU sharing a ring buffer.
+    ///
+    /// The kernel only allows this if the ring buffer is on a CPU, i.e. not `Cpu::Any`,
+    /// and the events are not in a group.
+    pub fn add_member_for(
+        &mut self,
+        process: &Process,
+        cfg: &Config,
+    ) -> config::Result<MemberHandle> {
+        self.open_member(process.as_i32()?, cfg)
+    }
+
+    fn open_member(&mut self, pid: i32, cfg: &Config) -> config::Result<MemberHandle> {
+        let perf_event_attr = with_identifier(cfg);
+        let group_fd = match self.group {
+            true => self.output.as_raw_fd(),
+            false => -1,
+        };
+        let fd = unsafe { perf_event_open_wrapped(&perf_event_attr, pid, self.cpu, group_fd, 0) }
+            .map_err(Error::SyscallFailed)?;
+        let file = unsafe { File::from_raw_fd(fd) };
+
+        let raw_fd = self.output.as_raw_fd() as i64;
+        ioctl_wrapped(&file, PERF_EVENT_IOCTL_SET_OUTPUT, Some(raw_fd))
+            .map_err(Error::SyscallFailed)?;
+        let mut event_id = 0_u64;
+        ioctl_wrapped(&file, PERF_EVENT_IOCTL_ID, Some(&mut event_id))
+            .map_err(Error::SyscallFailed)?;
+
+        let handle = MemberHandle(self.members.len());
+        self.members.push(Member {
+            file: Some(file),
+            event_id,
+            ctx: ParseContext::new(&perf_event_attr),
+        });
+        self.handles.insert(event_id, handle);
+        Ok(handle)
+    }
+
+    /// Handles of all events, including the first one.
+    pub fn handles(&self) -> impl Iterator<Item = MemberHandle> {
+        (0..self.members.len()).map(MemberHandle)
+    }
+
+    /// Event id of the member, panics if `handle` is not from this sampler.
+    pub fn event_id(&self, handle: MemberHandle) -> u64 {
+        self.members[handle.0].event_id
+    }
+
+    /// The first event, which owns the ring buffer.
+    pub const fn output(&self) -> &Sampler {
+        &self.output
+    }
+
+    fn ioctl_all(&self, request: perf_event_ioctls) -> io::Result<()> {
+        if self.group {
+            return ioctl_wrapped(&self.output.file, request, Some(PERF_IOC_FLAG_GROUP));
+        }
+        self.members
+            .iter()
+            .map(|it| it.file.as_ref().unwrap_or(&self.output.file))
+            .try_for_each(|file| ioctl_wrapped::<()>(file, request, None))
+    }
+
+    pub fn enable(&self) -> io::Result<()> {
+        self.ioctl_all(PERF_EVENT_IOCTL_ENABLE)
+    }
+
+    pub fn disable(&self) -> io::Result<()> {
+        self.ioctl_all(PERF_EVENT_IOCTL_DISABLE)
+    }
+
+    pub fn reset(&self) -> io::Result<()> {
+        self.ioctl_all(PERF_EVENT_IOCTL_RESET)
+    }
+
+    /// Next record with the handle of its origin, which is `None` if the event id
+    /// is unknown, e.g. `PERF_RECORD_LOST` of the ring buffer.
+    pub fn next_record(&mut self) -> Option<(Option<MemberHandle>, Record)> {
+        let record = self.output.next_record_ref()?;
+        let bytes = record.bytes();
+        let id_offset = match record.type_() {
+            PERF_RECORD_SAMPLE => 8,
+            _ => bytes.len() - 8,
+        };
+        let handle = bytes
+            .get(id_offset..id_offset + 8)
+            .filter(|_| bytes.len() >= 16)
+            .and_then(|it| {
+                self.handles
+                    .get(&u64::from_ne_bytes(it.try_into().unwrap()))
+            })
+            .copied();
+
+        let record = match handle {
+            Some(handle) => RecordRef {
+                ctx: self.members[handle.0].ctx,
+                ..record
+            }
+            .to_record(),
+            None => record.to_record(),
+        };
+        Some((handle, record))
+    }
+}
+
+impl AsRawFd for SharedSampler {
+    fn as_raw_fd(&self) -> RawFd {
+        self.output.as_raw_fd()
+    }
+}
+
+impl Pollable for SharedSampler {
+    fn next_record(&mut self) -> Option<Record> {
+        self.next_record().map(|(_, record)| record)
+    }
+}
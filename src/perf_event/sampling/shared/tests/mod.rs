@@ -0,0 +1,161 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+use crate::config::{Cpu, Process};
+use crate::counting::Counter;
+use crate::sampling::record::RecordBody;
+use crate::sampling::{
+    Config, ExtraConfig, OverflowBy, SampleRecordFields, Sampler, SharedSampler,
+};
+use crate::{Event, EventScope, SoftwareEvent};
+use std::collections::HashMap;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+fn gen_cfg(ev: SoftwareEvent, sample_record_fields: SampleRecordFields) -> Config {
+    let scopes = EventScope::all();
+    let ev = Event::from(ev);
+    let overflow_by = OverflowBy::Period(100000);
+    let extra_config = ExtraConfig {
+        sample_record_fields,
+        ..Default::default()
+    };
+    Config::extra_new(&ev, &scopes, &overflow_by, &extra_config)
+}
+
+fn workload() {
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_millis(10) {
+        std::hint::black_box(rand::random::<u64>());
+    }
+}
+
+/// Events with different sample types are decoded with their own layouts.
+fn test_demux(group: bool) {
+    let time_cfg = gen_cfg(
+        SoftwareEvent::CpuClock,
+        SampleRecordFields {
+            time: true,
+            ..Default::default()
+        },
+    );
+    let ip_cfg = gen_cfg(
+        SoftwareEvent::TaskClock,
+        SampleRecordFields {
+            ip: true,
+            pid_and_tid: true,
+            ..Default::default()
+        },
+    );
+    let open = match group {
+        true => SharedSampler::new_group,
+        false => SharedSampler::new,
+    };
+    let mut shared = open(&Process::Current, &Cpu::Any, 1 + 16, &time_cfg).unwrap();
+    let time_handle = SharedSampler::OUTPUT;
+    let ip_handle = shared.add_member(&ip_cfg).unwrap();
+    assert_eq!(
+        shared.handles().collect::<Vec<_>>(),
+        vec![time_handle, ip_handle]
+    );
+    assert_eq!(
+        shared.event_id(time_handle),
+        shared.output().event_id().unwrap()
+    );
+
+    shared.enable().unwrap();
+    workload();
+    shared.disable().unwrap();
+
+    let mut counts = HashMap::new();
+    while let Some((handle, record)) = shared.next_record() {
+        let RecordBody::Sample(body) = record.body else {
+            continue;
+        };
+        let handle = handle.unwrap();
+        assert_eq!(body.sample_id, Some(shared.event_id(handle)));
+        if handle == time_handle {
+            assert!(body.time.is_some());
+            assert!(body.ip.is_none());
+        } else {
+            assert_eq!(handle, ip_handle);
+            assert!(body.time.is_none());
+            assert!(body.ip.is_some());
+            assert_eq!(body.pid, Some(std::process::id()));
+        }
+        *counts.entry(handle).or_insert(0) += 1;
+    }
+    assert!(counts[&time_handle] > 0);
+    assert!(counts[&ip_handle] > 0);
+}
+
+#[test]
+fn test_shared() {
+    test_demux(false);
+}
+
+#[test]
+fn test_shared_group() {
+    test_demux(true);
+}
+
+/// Samples of a counter with a sample period go to the buffer of the sampler.
+#[test]
+fn test_counter_set_output() {
+    let cfg = gen_cfg(SoftwareEvent::CpuClock, Default::default());
+    let mut sampler = Sampler::new(&Process::Current, &Cpu::Any, 1 + 16, &cfg).unwrap();
+
+    let ev = Event::from(SoftwareEvent::TaskClock);
+    let mut counting_cfg = crate::counting::Config::new(&ev, &EventScope::all());
+    let attr = counting_cfg.as_raw_mut();
+    attr.__bindgen_anon_1.sample_period = 100000;
+    attr.sample_type = cfg.as_raw().sample_type;
+    let counter = Counter::new(&Process::Current, &Cpu::Any, &mut counting_cfg).unwrap();
+    let run_counter = || {
+        counter.enable().unwrap();
+        workload();
+        counter.disable().unwrap();
+    };
+
+    // the sampler itself is not enabled
+    counter.set_output(&sampler).unwrap();
+    run_counter();
+    let mut records = 0;
+    while sampler.next_record_ref().is_some() {
+        records += 1;
+    }
+    assert!(records > 0);
+
+    counter.ignore_output().unwrap();
+    run_counter();
+    assert!(sampler.next_record_ref().is_none());
+}
+
+#[test]
+fn test_add_member_for() {
+    let cfg = gen_cfg(SoftwareEvent::CpuClock, Default::default());
+    let mut child = Command::new("sleep").arg("1").spawn().unwrap();
+    let process = Process::Pid(child.id());
+
+    // events of different tasks only share a ring buffer on one CPU
+    let mut shared = SharedSampler::new(&Process::Current, &Cpu::Any, 1 + 16, &cfg).unwrap();
+    assert!(shared.add_member_for(&process, &cfg).is_err());
+
+    let mut shared = SharedSampler::new(&Process::Current, &Cpu::Id(0), 1 + 16, &cfg).unwrap();
+    let handle = shared.add_member_for(&process, &cfg).unwrap();
+    assert_ne!(handle, SharedSampler::OUTPUT);
+    assert_eq!(shared.handles().count(), 2);
+
+    child.kill().unwrap();
+    child.wait().unwrap();
+}
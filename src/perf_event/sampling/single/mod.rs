@@ -15,30 +15,41 @@
 mod into_iter;
 mod iter;
 mod next_record;
+#[cfg(feature = "linux-4.7")]
+mod snapshot;
 mod stat;
 #[cfg(test)]
 mod tests;
 
 use crate::config;
 use crate::infra::WrapResult;
+use crate::perf_event::PerfEventAttr;
 use crate::sampling::record::*;
-use crate::sampling::single::next_record::next_record;
-use crate::sampling::Config;
+use crate::sampling::single::next_record::{load_data_head, next_record, next_record_ref};
+#[cfg(feature = "linux-4.7")]
+use crate::sampling::single::snapshot::{snapshot, snapshot_since};
+use crate::sampling::{wait_fd, Config, Readiness};
 use crate::syscall::bindings::*;
 use crate::syscall::{ioctl_wrapped, perf_event_open_wrapped};
-use memmap2::{MmapMut, MmapOptions};
+use memmap2::{MmapOptions, MmapRaw};
 use std::fs::File;
 use std::io;
-use std::os::fd::FromRawFd;
+use std::os::fd::{AsRawFd, FromRawFd, RawFd};
+use std::sync::atomic::AtomicU64;
+#[cfg(feature = "linux-4.7")]
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use crate::config::{Cpu, Error, Process};
 use crate::sampling::single::stat::sampler_stat;
 pub use into_iter::*;
 pub use iter::*;
+pub(super) use stat::file_stat;
 pub use stat::SamplerStat;
 
 pub struct Sampler {
-    pub(crate) mmap: MmapMut,
+    pub(crate) mmap: MmapRaw,
     pub(crate) file: File,
 
     /*
@@ -53,12 +64,25 @@ pub struct Sampler {
     /// i.e. `perf_event_mmap_page.data_offset`
     pub(crate) data_offset: u64,
 
-    pub(crate) sample_type: u64,
-    pub(crate) sample_id_all: bool,
+    /// Head of the current batch, which is read from
+    /// `perf_event_mmap_page.data_head` with acquire ordering
+    pub(crate) data_head: u64,
+    /// Monotonic read position, which is published to
+    /// `perf_event_mmap_page.data_tail` with release ordering after each batch
+    pub(crate) data_tail: u64,
+    /// Holds the record which wraps around the end of ring buffer,
+    /// as words to keep it 8 bytes aligned
+    pub(crate) scratch: Vec<u64>,
+    /// Bytes skipped after corrupt record headers, shared with the stat handles
+    pub(crate) dropped_bytes: Arc<AtomicU64>,
 
-    pub(crate) regs_user_len: usize,
-    #[cfg(feature = "linux-3.19")]
-    pub(crate) regs_intr_len: usize,
+    pub(crate) parse_ctx: ParseContext,
+    /// The ring buffer is in overwrite mode and mapped read-only,
+    /// i.e. `perf_event_attr.write_backward`
+    pub(crate) write_backward: bool,
+    /// Output is paused by `pause`, which the kernel does not report
+    #[cfg(feature = "linux-4.7")]
+    pub(crate) paused: AtomicBool,
 }
 
 impl Sampler {
@@ -78,25 +102,43 @@ impl Sampler {
             .map_err(Error::SyscallFailed)?;
         let file = unsafe { File::from_raw_fd(fd) };
 
-        let mmap = unsafe {
-            MmapOptions::new()
-                .len(page_size::get() * mmap_pages)
-                .map_mut(&file)
-        }
-        .unwrap();
+        Self::from_file(file, mmap_pages, perf_event_attr).map_err(Error::SyscallFailed)
+    }
 
+    /// Map `1 + 2^n` pages of ring buffer for the opened event.
+    pub(crate) fn from_file(
+        file: File,
+        mmap_pages: usize,
+        perf_event_attr: &PerfEventAttr,
+    ) -> io::Result<Self> {
         let page_size = page_size::get();
+        #[cfg(feature = "linux-4.7")]
+        let write_backward = perf_event_attr.write_backward() > 0;
+        #[cfg(not(feature = "linux-4.7"))]
+        let write_backward = false;
+
+        // A read-only mapping tells the kernel that `data_tail` is not maintained,
+        // which is required by the overwrite mode.
+        let mut options = MmapOptions::new();
+        options.len(page_size * mmap_pages);
+        let mmap = match write_backward {
+            true => options.map_raw_read_only(&file),
+            false => options.map_raw(&file),
+        }?;
 
         Self {
             mmap,
             file,
             data_size: ((mmap_pages - 1) * page_size) as _,
             data_offset: page_size as _,
-            sample_type: perf_event_attr.sample_type,
-            sample_id_all: perf_event_attr.sample_id_all() > 0,
-            regs_user_len: perf_event_attr.sample_regs_user.count_ones() as _,
-            #[cfg(feature = "linux-3.19")]
-            regs_intr_len: perf_event_attr.sample_regs_intr.count_ones() as _,
+            data_head: 0,
+            data_tail: 0,
+            scratch: vec![],
+            dropped_bytes: Arc::new(AtomicU64::new(0)),
+            parse_ctx: ParseContext::new(perf_event_attr),
+            write_backward,
+            #[cfg(feature = "linux-4.7")]
+            paused: AtomicBool::new(false),
         }
         .wrap_ok()
     }
@@ -115,12 +157,16 @@ impl Sampler {
 
     #[cfg(feature = "linux-4.7")]
     pub fn pause(&self) -> io::Result<()> {
-        ioctl_wrapped(&self.file, PERF_EVENT_IOCTL_PAUSE_OUTPUT, Some(1i32))
+        ioctl_wrapped(&self.file, PERF_EVENT_IOCTL_PAUSE_OUTPUT, Some(1i32))?;
+        self.paused.store(true, Ordering::Relaxed);
+        Ok(())
     }
 
     #[cfg(feature = "linux-4.7")]
     pub fn resume(&self) -> io::Result<()> {
-        ioctl_wrapped(&self.file, PERF_EVENT_IOCTL_PAUSE_OUTPUT, Some(0i32))
+        ioctl_wrapped(&self.file, PERF_EVENT_IOCTL_PAUSE_OUTPUT, Some(0i32))?;
+        self.paused.store(false, Ordering::Relaxed);
+        Ok(())
     }
 
     pub fn refresh(&self, refresh: i32) -> io::Result<()> {
@@ -131,10 +177,69 @@ impl Sampler {
         ioctl_wrapped(&self.file, PERF_EVENT_IOCTL_PERIOD, Some(&new))
     }
 
+    /// Always returns `None` for samplers in overwrite mode (`write_backward`),
+    /// use `snapshot` to read them instead.
     pub fn next_record(&mut self) -> Option<Record> {
         next_record(self)
     }
 
+    /// Wait until records are available or the monitored task exits,
+    /// `None` waits forever. Returns immediately if records are already available.
+    pub fn wait(&self, timeout: Option<Duration>) -> io::Result<Readiness> {
+        let deadline = timeout.map(|it| Instant::now() + it);
+        loop {
+            if self.has_pending() {
+                return Ok(Readiness {
+                    readable: true,
+                    hup: false,
+                });
+            }
+            let timeout = deadline.map(|it| it.saturating_duration_since(Instant::now()));
+            let readiness = wait_fd(self.file.as_raw_fd(), timeout)?;
+            // the wakeup may be for records which are already read
+            if self.write_backward || readiness.hup || !readiness.readable {
+                return Ok(readiness);
+            }
+        }
+    }
+
+    fn has_pending(&self) -> bool {
+        !self.write_backward
+            && (self.data_tail != self.data_head || load_data_head(self) != self.data_tail)
+    }
+
+    /// Context to decode records of this sampler with `Record::parse`
+    pub const fn parse_context(&self) -> ParseContext {
+        self.parse_ctx
+    }
+
+    /// Zero-copy version of `next_record`, the record is borrowed
+    /// until the next call.
+    pub fn next_record_ref(&mut self) -> Option<RecordRef<'_>> {
+        next_record_ref(self)
+    }
+
+    /// Read the newest records of a sampler in overwrite mode (`write_backward`),
+    /// oldest first. The ring buffer is paused while reading for a consistent snapshot,
+    /// and stays paused afterwards if it was paused before.
+    #[cfg(feature = "linux-4.7")]
+    pub fn snapshot(&mut self) -> io::Result<Vec<Record>> {
+        snapshot(self)
+    }
+
+    /// Like `snapshot`, but split into records written before and after
+    /// `data_head` was `mark` (see `data_head`), both from newest to oldest.
+    #[cfg(feature = "linux-4.7")]
+    pub(crate) fn snapshot_since(&self, mark: u64) -> io::Result<(Vec<Record>, Vec<Record>)> {
+        snapshot_since(self, Some(mark))
+    }
+
+    /// Current `perf_event_mmap_page.data_head`
+    #[cfg(feature = "linux-4.7")]
+    pub(crate) fn data_head(&self) -> u64 {
+        load_data_head(self)
+    }
+
     #[cfg(feature = "linux-3.12")]
     pub fn event_id(&self) -> io::Result<u64> {
         let mut id = 0_u64;
@@ -146,3 +251,9 @@ impl Sampler {
         sampler_stat(self)
     }
 }
+
+impl AsRawFd for Sampler {
+    fn as_raw_fd(&self) -> RawFd {
+        self.file.as_raw_fd()
+    }
+}
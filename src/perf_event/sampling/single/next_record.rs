@@ -12,261 +12,133 @@
 // You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
 // see <https://www.gnu.org/licenses/>.
 
-use crate::infra::{SizedExt, WrapBox, WrapOption};
+use crate::infra::WrapOption;
 use crate::sampling::record::*;
 use crate::sampling::Sampler;
 use crate::syscall::bindings::*;
-use std::alloc::{alloc, dealloc, Layout};
+use std::mem::size_of;
+use std::ptr::{addr_of, addr_of_mut};
 use std::slice;
+use std::sync::atomic::{AtomicU64, Ordering};
 
-#[inline]
-pub fn next_record(sampler: &mut Sampler) -> Option<Record> {
-    let metapage =
-        unsafe { (sampler.mmap.as_mut_ptr() as *mut perf_event_mmap_page).as_mut() }.unwrap();
-    let data_size = sampler.data_size;
-    let data_head = metapage.data_head % data_size;
-    let data_tail = metapage.data_tail;
-
-    if data_tail == data_head {
-        return None;
-    }
+/*
+The ring buffer protocol, see: `include/uapi/linux/perf_event.h`
 
-    let data_ptr = unsafe { sampler.mmap.as_mut_ptr().add(sampler.data_offset as _) };
+The kernel writes records before publishing `data_head` with release ordering,
+the reader loads `data_head` with acquire ordering before reading records,
+and stores `data_tail` with release ordering after reading them, so the kernel
+will not overwrite records still being read or borrowed.
 
-    let record_len = match data_tail as isize + 8 - data_size as isize {
-        left if left <= 0 => {
-            let offset = (data_tail + 6) as _;
-            let ptr = unsafe { data_ptr.add(offset) } as *const u16;
-            unsafe { *ptr }
-        }
-        1 => unsafe {
-            let mut buf = <[u8; 2]>::uninit();
-            buf[0] = *(data_ptr.add((data_size - 1) as _) as *const u8);
-            buf[1] = *(data_ptr as *const u8);
-            std::mem::transmute(buf)
-        },
-        left => unsafe {
-            let ptr = data_ptr.add((left - 2) as _) as *const u16;
-            *ptr
-        },
-    } as usize;
+Both `data_head` and `data_tail` are monotonically increasing,
+the position in the ring buffer is them modulo `data_size`.
+*/
 
-    let mut dealloc_record_buf = false;
-    let record_buf = match data_tail as isize + record_len as isize - data_size as isize {
-        left if left > 0 => unsafe {
-            let buf = {
-                dealloc_record_buf = true;
-                let layout = Layout::array::<u8>(record_len).unwrap();
-                alloc(layout)
-            };
+#[inline]
+pub fn load_data_head(sampler: &Sampler) -> u64 {
+    let metapage = sampler.mmap.as_ptr() as *const perf_event_mmap_page;
+    let ptr = unsafe { addr_of!((*metapage).data_head) } as *const AtomicU64;
+    unsafe { &*ptr }.load(Ordering::Acquire)
+}
 
-            let ring_end_part_ptr = data_ptr.add(data_tail as _);
-            let ring_end_part_len = (data_size - data_tail) as usize;
-            std::ptr::copy_nonoverlapping(ring_end_part_ptr, buf, ring_end_part_len);
+#[inline]
+fn store_data_tail(sampler: &Sampler) {
+    let metapage = sampler.mmap.as_mut_ptr() as *mut perf_event_mmap_page;
+    let ptr = unsafe { addr_of_mut!((*metapage).data_tail) } as *const AtomicU64;
+    unsafe { &*ptr }.store(sampler.data_tail, Ordering::Release);
+}
 
-            let ring_start_part_ptr = data_ptr;
-            let ring_start_part_len = left as _;
-            std::ptr::copy_nonoverlapping(
-                ring_start_part_ptr,
-                buf.add(ring_end_part_len),
-                ring_start_part_len,
-            );
+/// Slice `len` bytes at `offset` of the ring buffer data area,
+/// the record which wraps around the end is copied into `scratch`.
+///
+/// # Safety
+/// `data_ptr` must point to `data_size` readable bytes which outlive the returned slice,
+/// `offset` must be 8 bytes aligned and `len` must be a multiple of 8 within `data_size`.
+#[inline]
+pub unsafe fn ring_slice(
+    data_ptr: *const u8,
+    data_size: u64,
+    offset: u64,
+    len: usize,
+    scratch: &mut Vec<u64>,
+) -> &[u8] {
+    match offset as usize + len {
+        end if end > data_size as usize => {
+            let ring_end_part_len = (data_size - offset) as usize;
+            scratch.clear();
+            scratch.resize(len / 8, 0);
+            let buf = slice::from_raw_parts_mut(scratch.as_mut_ptr() as *mut u8, len);
+            buf[..ring_end_part_len].copy_from_slice(slice::from_raw_parts(
+                data_ptr.add(offset as _),
+                ring_end_part_len,
+            ));
+            buf[ring_end_part_len..]
+                .copy_from_slice(slice::from_raw_parts(data_ptr, len - ring_end_part_len));
+            &*buf
+        }
+        _ => slice::from_raw_parts(data_ptr.add(offset as _), len),
+    }
+}
 
-            slice::from_raw_parts(buf, record_len)
-        },
-        _ => unsafe { slice::from_raw_parts(data_ptr.add(data_tail as _), record_len) },
-    };
+#[inline]
+pub fn next_record_ref(sampler: &mut Sampler) -> Option<RecordRef<'_>> {
+    // records of overwrite mode are read by `snapshot`
+    if sampler.write_backward {
+        return None;
+    }
 
-    let record_header =
-        unsafe { (record_buf.as_ptr() as *const perf_event_header).as_ref() }.unwrap();
-    let record_body = unsafe {
-        let follow_mem_ptr = (record_header as *const perf_event_header).add(1) as *const _;
-        match record_header.type_ {
-            PERF_RECORD_MMAP => {
-                let record = mmap::Body::from_ptr(follow_mem_ptr);
-                RecordBody::Mmap(record.wrap_box())
-            }
-            PERF_RECORD_LOST => {
-                let record = lost::Body::from_ptr(
-                    follow_mem_ptr,
-                    sampler.sample_type,
-                    sampler.sample_id_all,
-                );
-                RecordBody::Lost(record.wrap_box())
-            }
-            PERF_RECORD_COMM => {
-                let record = comm::Body::from_ptr(
-                    follow_mem_ptr,
-                    sampler.sample_type,
-                    sampler.sample_id_all,
-                );
-                RecordBody::Comm(record.wrap_box())
-            }
-            PERF_RECORD_EXIT => {
-                let record = exit::Body::from_ptr(
-                    follow_mem_ptr,
-                    sampler.sample_type,
-                    sampler.sample_id_all,
-                );
-                RecordBody::Exit(record.wrap_box())
-            }
-            PERF_RECORD_THROTTLE => {
-                let record = throttle::Body::from_ptr(
-                    follow_mem_ptr,
-                    sampler.sample_type,
-                    sampler.sample_id_all,
-                );
-                RecordBody::Throttle(record.wrap_box())
-            }
-            PERF_RECORD_UNTHROTTLE => {
-                let record = unthrottle::Body::from_ptr(
-                    follow_mem_ptr,
-                    sampler.sample_type,
-                    sampler.sample_id_all,
-                );
-                RecordBody::Unthrottle(record.wrap_box())
-            }
-            PERF_RECORD_FORK => {
-                let record = fork::Body::from_ptr(
-                    follow_mem_ptr,
-                    sampler.sample_type,
-                    sampler.sample_id_all,
-                );
-                RecordBody::Fork(record.wrap_box())
-            }
-            PERF_RECORD_READ => {
-                let record = read::Body::from_ptr(
-                    follow_mem_ptr,
-                    sampler.sample_type,
-                    sampler.sample_id_all,
-                );
-                RecordBody::Read(record.wrap_box())
-            }
-            PERF_RECORD_SAMPLE => {
-                let record = sample::Body::from_ptr(
-                    follow_mem_ptr,
-                    sampler.sample_type,
-                    sampler.regs_user_len,
-                    #[cfg(feature = "linux-3.19")]
-                    sampler.regs_intr_len,
-                );
-                RecordBody::Sample(record.wrap_box())
-            }
-            #[cfg(feature = "linux-3.12")]
-            PERF_RECORD_MMAP2 => {
-                let record = mmap2::Body::from_ptr(
-                    follow_mem_ptr,
-                    record_header.misc,
-                    sampler.sample_type,
-                    sampler.sample_id_all,
-                );
-                RecordBody::Mmap2(record.wrap_box())
-            }
-            #[cfg(feature = "linux-4.1")]
-            PERF_RECORD_AUX => {
-                let record =
-                    aux::Body::from_ptr(follow_mem_ptr, sampler.sample_type, sampler.sample_id_all);
-                RecordBody::Aux(record.wrap_box())
-            }
-            #[cfg(feature = "linux-4.1")]
-            PERF_RECORD_ITRACE_START => {
-                let ptr = follow_mem_ptr as *const intrace_start::Body;
-                RecordBody::ItraceStart(ptr.read().wrap_box())
-            }
-            #[cfg(feature = "linux-4.2")]
-            PERF_RECORD_LOST_SAMPLES => {
-                let record = lost_samples::Body::from_ptr(
-                    follow_mem_ptr,
-                    sampler.sample_type,
-                    sampler.sample_id_all,
-                );
-                RecordBody::LostSamples(record.wrap_box())
-            }
-            #[cfg(feature = "linux-4.3")]
-            PERF_RECORD_SWITCH => {
-                let record = switch::Body::from_ptr(
-                    follow_mem_ptr,
-                    sampler.sample_type,
-                    sampler.sample_id_all,
-                );
-                RecordBody::Switch(record.wrap_box())
-            }
-            #[cfg(feature = "linux-4.3")]
-            PERF_RECORD_SWITCH_CPU_WIDE => {
-                let record = switch_cpu_wide::Body::from_ptr(
-                    follow_mem_ptr,
-                    sampler.sample_type,
-                    sampler.sample_id_all,
-                );
-                RecordBody::SwitchCpuWide(record.wrap_box())
-            }
-            #[cfg(feature = "linux-4.12")]
-            PERF_RECORD_NAMESPACES => {
-                let record = namespaces::Body::from_ptr(
-                    follow_mem_ptr,
-                    sampler.sample_type,
-                    sampler.sample_id_all,
-                );
-                RecordBody::Namespaces(record.wrap_box())
-            }
-            #[cfg(feature = "linux-5.1")]
-            PERF_RECORD_KSYMBOL => {
-                let record = ksymbol::Body::from_ptr(
-                    follow_mem_ptr,
-                    sampler.sample_type,
-                    sampler.sample_id_all,
-                );
-                RecordBody::Ksymbol(record.wrap_box())
-            }
-            #[cfg(feature = "linux-5.1")]
-            PERF_RECORD_BPF_EVENT => {
-                let record = bpf_event::Body::from_ptr(
-                    follow_mem_ptr,
-                    sampler.sample_type,
-                    sampler.sample_id_all,
-                );
-                RecordBody::BpfEvent(record.wrap_box())
-            }
-            #[cfg(feature = "linux-5.7")]
-            PERF_RECORD_CGROUP => {
-                let record = cgroup::Body::from_ptr(
-                    follow_mem_ptr,
-                    sampler.sample_type,
-                    sampler.sample_id_all,
-                );
-                RecordBody::Cgroup(record.wrap_box())
-            }
-            #[cfg(feature = "linux-5.9")]
-            PERF_RECORD_TEXT_POKE => {
-                let record = text_poke::Body::from_ptr(
-                    follow_mem_ptr,
-                    sampler.sample_type,
-                    sampler.sample_id_all,
-                );
-                RecordBody::TextPoke(record.wrap_box())
-            }
-            #[cfg(feature = "linux-5.16")]
-            PERF_RECORD_AUX_OUTPUT_HW_ID => {
-                let ptr = follow_mem_ptr as *const aux_output_hw_id::Body;
-                RecordBody::AuxOutputHwId(ptr.read().wrap_box())
+    let data_size = sampler.data_size;
+    let data_ptr = unsafe { sampler.mmap.as_ptr().add(sampler.data_offset as _) };
+    let (offset, record_len) = loop {
+        /*
+        Records of the last call may still be borrowed until this call,
+        so the tail is published at the start of the next batch.
+        */
+        if sampler.data_tail == sampler.data_head {
+            store_data_tail(sampler);
+            sampler.data_head = load_data_head(sampler);
+            if sampler.data_tail == sampler.data_head {
+                return None;
             }
-            _ => unreachable!(),
         }
-    };
 
-    if dealloc_record_buf {
-        let layout = Layout::array::<u8>(record_len).unwrap();
-        #[allow(clippy::as_ptr_cast_mut)]
-        unsafe {
-            dealloc(record_buf.as_ptr() as _, layout)
+        let offset = sampler.data_tail % data_size;
+        // records are 8 bytes aligned, so the header never wraps
+        let record_len = {
+            let ptr = unsafe { data_ptr.add(offset as _) } as *const perf_event_header;
+            unsafe { (*ptr).size }
+        } as usize;
+        if record_len < size_of::<perf_event_header>()
+            || record_len & 0b111 != 0
+            || record_len as u64 > sampler.data_head - sampler.data_tail
+        {
+            // corrupt header, the next record can't be located, so drop the rest of the batch
+            let dropped = sampler.data_head - sampler.data_tail;
+            sampler.dropped_bytes.fetch_add(dropped, Ordering::Relaxed);
+            sampler.data_tail = sampler.data_head;
+            continue;
         }
-    }
+        break (offset, record_len);
+    };
+    sampler.data_tail += record_len as u64;
 
-    metapage.data_tail = (data_tail + record_len as u64) % data_size;
+    let bytes = unsafe {
+        ring_slice(
+            data_ptr,
+            data_size,
+            offset,
+            record_len,
+            &mut sampler.scratch,
+        )
+    };
 
-    Record {
-        misc: record_header.misc,
-        body: record_body,
+    RecordRef {
+        bytes,
+        ctx: sampler.parse_ctx,
     }
     .wrap_some()
 }
+
+#[inline]
+pub fn next_record(sampler: &mut Sampler) -> Option<Record> {
+    next_record_ref(sampler).map(|it| it.to_record())
+}
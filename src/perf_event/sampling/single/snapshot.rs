@@ -0,0 +1,106 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+
+use crate::sampling::record::*;
+use crate::sampling::single::next_record::{load_data_head, ring_slice};
+use crate::sampling::Sampler;
+use crate::syscall::bindings::*;
+use std::io;
+use std::mem::size_of;
+use std::sync::atomic::Ordering;
+
+/*
+In overwrite mode (`write_backward`), the kernel writes each record right before
+the previous one, and `data_head` is decreasing from 0 (as a wrapping u64),
+so the records starting from `data_head` are ordered from newest to oldest.
+`data_tail` is ignored and the oldest records are overwritten when full.
+See: `perf_mmap__read_init` in `tools/lib/perf/mmap.c`
+*/
+
+pub fn snapshot(sampler: &Sampler) -> io::Result<Vec<Record>> {
+    let (mut records, _) = snapshot_since(sampler, None)?;
+    records.reverse();
+    Ok(records)
+}
+
+/// Records written before and after `data_head` was `mark`, both from newest to oldest.
+/// All records are written before if `mark` is `None`.
+pub fn snapshot_since(
+    sampler: &Sampler,
+    mark: Option<u64>,
+) -> io::Result<(Vec<Record>, Vec<Record>)> {
+    if !sampler.write_backward {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "snapshot requires a sampler in overwrite mode (write_backward)",
+        ));
+    }
+
+    // the kernel skips writing to a paused ring buffer, so `data_head` is stable
+    let was_paused = sampler.paused.load(Ordering::Relaxed);
+    if !was_paused {
+        sampler.pause()?;
+    }
+    let data_head = load_data_head(sampler);
+    // `data_head` is decreasing, so this is the bytes written after `mark`
+    let after_len = mark.map_or(0, |it| it.wrapping_sub(data_head));
+    let records = read_backward(sampler, data_head);
+    if !was_paused {
+        sampler.resume()?;
+    }
+
+    let (after, before) = records
+        .into_iter()
+        .partition::<Vec<_>, _>(|(pos, _)| *pos < after_len);
+    let strip = |it: Vec<(u64, Record)>| it.into_iter().map(|(_, it)| it).collect();
+    Ok((strip(before), strip(after)))
+}
+
+/// Records from newest to oldest, with their positions relative to `data_head`.
+fn read_backward(sampler: &Sampler, data_head: u64) -> Vec<(u64, Record)> {
+    let data_size = sampler.data_size;
+    let data_ptr = unsafe { sampler.mmap.as_ptr().add(sampler.data_offset as _) };
+
+    // bytes written so far, at most one ring buffer of them are still there
+    let available = data_head.wrapping_neg().min(data_size);
+
+    let mut records = vec![];
+    let mut scratch = vec![];
+    let mut pos = 0;
+    while pos + size_of::<perf_event_header>() as u64 <= available {
+        let offset = data_head.wrapping_add(pos) % data_size;
+        let record_len = {
+            let ptr = unsafe { data_ptr.add(offset as _) } as *const perf_event_header;
+            unsafe { (*ptr).size }
+        } as usize;
+        // the oldest record may be partially overwritten by the newest one
+        if record_len < size_of::<perf_event_header>()
+            || record_len & 0b111 != 0
+            || pos + record_len as u64 > available
+        {
+            break;
+        }
+
+        let bytes = unsafe { ring_slice(data_ptr, data_size, offset, record_len, &mut scratch) };
+        let record = RecordRef {
+            bytes,
+            ctx: sampler.parse_ctx,
+        }
+        .to_record();
+        records.push((pos, record));
+        pos += record_len as u64;
+    }
+
+    records
+}
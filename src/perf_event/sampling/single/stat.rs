@@ -14,9 +14,11 @@
 
 use crate::infra::{SizedExt, WrapResult};
 use crate::sampling::{ReadFormatHead, ReadFormatValue, Sampler};
+use std::fs::File;
 use std::io;
 use std::io::Read;
 use std::mem::size_of;
+use std::sync::atomic::{AtomicU64, Ordering};
 
 #[derive(Debug, Clone)]
 pub struct SamplerStat {
@@ -26,10 +28,18 @@ pub struct SamplerStat {
     pub event_lost: u64,
     pub time_enabled: u64,
     pub time_running: u64,
+    /// Bytes of the ring buffer skipped by `next_record`, since they follow
+    /// a corrupt record header and the records in them can't be located
+    pub dropped_bytes: u64,
 }
 
 #[inline]
-pub fn sampler_stat(sampler: &mut Sampler) -> io::Result<SamplerStat> {
+pub fn sampler_stat(sampler: &Sampler) -> io::Result<SamplerStat> {
+    file_stat(&sampler.file, &sampler.dropped_bytes)
+}
+
+/// Read the stat of a sampler, or of a duplicate of its fd and its `dropped_bytes`.
+pub fn file_stat(mut file: &File, dropped_bytes: &AtomicU64) -> io::Result<SamplerStat> {
     #[repr(C)]
     struct Layout {
         head: ReadFormatHead,
@@ -37,7 +47,7 @@ pub fn sampler_stat(sampler: &mut Sampler) -> io::Result<SamplerStat> {
     }
 
     let mut buf = unsafe { <[u8; size_of::<Layout>()]>::uninit() };
-    sampler.file.read_exact(&mut buf)?;
+    file.read_exact(&mut buf)?;
 
     let layout = unsafe { &*(buf.as_ptr() as *const Layout) };
     SamplerStat {
@@ -47,6 +57,7 @@ pub fn sampler_stat(sampler: &mut Sampler) -> io::Result<SamplerStat> {
         event_lost: layout.value.event_lost,
         time_enabled: layout.head.time_enabled,
         time_running: layout.head.time_running,
+        dropped_bytes: dropped_bytes.load(Ordering::Relaxed),
     }
     .wrap_ok()
 }
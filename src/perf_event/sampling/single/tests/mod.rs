@@ -13,8 +13,12 @@
 // see <https://www.gnu.org/licenses/>.
 
 mod hardware;
+mod record_ref;
 mod sample_record_fields;
+#[cfg(feature = "linux-4.7")]
+mod snapshot;
 mod software;
+mod stress;
 
 use crate::config::{Cpu, Process};
 use crate::sampling::record::{Record, RecordBody};
@@ -0,0 +1,150 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+
+use crate::config::{Cpu, Process};
+use crate::sampling::record::{lost, Record, RecordBody};
+use crate::sampling::{Config, ExtraConfig, OverflowBy, SampleRecordFields, Sampler};
+use crate::syscall::bindings::*;
+use crate::{Event, EventScope, SoftwareEvent};
+use memmap2::MmapOptions;
+
+fn workload() {
+    for _ in 0..1000 {
+        std::hint::black_box(rand::random::<usize>());
+    }
+}
+
+/// Borrowed views must agree with the owned records,
+/// including records wrapping around the end of the small ring buffer.
+#[test]
+fn test_next_record_ref() {
+    let scopes = EventScope::all();
+    let ev = Event::from(SoftwareEvent::CpuClock);
+    let overflow_by = OverflowBy::Period(10000);
+    let extra_config = ExtraConfig {
+        sample_record_fields: SampleRecordFields {
+            ip: true,
+            pid_and_tid: true,
+            time: true,
+            cpu: true,
+            period: true,
+            ips: Some(16),
+            data_stack_user: Some(72),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let cfg = Config::extra_new(&ev, &scopes, &overflow_by, &extra_config);
+    let mmap_pages = 1 + 2;
+    let mut sampler = Sampler::new(&Process::Current, &Cpu::Any, mmap_pages, &cfg).unwrap();
+    let data_size = (sampler.data_size) as usize;
+
+    sampler.enable().unwrap();
+    let mut read_len = 0;
+    let mut sample_count = 0;
+    for _ in 0..2000 {
+        workload();
+        while let Some(record) = sampler.next_record_ref() {
+            read_len += record.bytes().len();
+            let Record { misc, body } = record.to_record();
+            assert_eq!(misc, record.misc());
+
+            let (sample_ref, body) = match body {
+                RecordBody::Sample(body) => (record.sample().unwrap(), body),
+                _ => {
+                    assert_ne!(record.type_(), PERF_RECORD_SAMPLE);
+                    assert!(record.sample().is_none());
+                    continue;
+                }
+            };
+            sample_count += 1;
+            assert_eq!(sample_ref.ip(), body.ip);
+            assert_eq!(sample_ref.pid(), body.pid);
+            assert_eq!(sample_ref.tid(), body.tid);
+            assert_eq!(sample_ref.time(), body.time);
+            assert_eq!(sample_ref.addr(), None);
+            assert_eq!(sample_ref.cpu(), body.cpu);
+            assert_eq!(sample_ref.period(), body.period);
+            assert_eq!(sample_ref.ips(), body.ips.as_deref());
+            assert_eq!(
+                sample_ref.data_stack_user(),
+                body.data_stack_user.as_deref()
+            );
+            assert_eq!(sample_ref.pid(), Some(std::process::id()));
+        }
+    }
+    sampler.disable().unwrap();
+
+    assert!(sample_count > 0);
+    // the ring buffer wrapped at least once
+    assert!(read_len > data_size);
+}
+
+/// Write `bytes` at `pos` of a fake ring buffer and publish them
+fn write_ring(sampler: &Sampler, pos: usize, bytes: &[u8]) {
+    let data_ptr = unsafe { sampler.mmap.as_mut_ptr().add(sampler.data_offset as _) };
+    unsafe { std::ptr::copy_nonoverlapping(bytes.as_ptr(), data_ptr.add(pos), bytes.len()) };
+    let metapage = sampler.mmap.as_mut_ptr() as *mut perf_event_mmap_page;
+    unsafe { (*metapage).data_head = (pos + bytes.len()) as _ };
+}
+
+fn lost_ids(sampler: &mut Sampler) -> Vec<u64> {
+    std::iter::from_fn(|| sampler.next_record())
+        .map(|it| match it.body {
+            RecordBody::Lost(body) => body.id,
+            body => panic!("Unexpected record: {:?}", body),
+        })
+        .collect()
+}
+
+/// The rest of the batch after a corrupt header is dropped and counted
+#[test]
+fn test_corrupt_header() {
+    let scopes = EventScope::all();
+    let ev = Event::from(SoftwareEvent::Dummy);
+    let cfg = Config::new(&ev, &scopes, &OverflowBy::Period(1));
+    let mut sampler = Sampler::new(&Process::Current, &Cpu::Any, 1 + 1, &cfg).unwrap();
+    let ctx = sampler.parse_context();
+    let lost = |id| {
+        let body = lost::Body {
+            id,
+            lost: 1,
+            sample_id: None,
+        };
+        let record = Record {
+            misc: 0,
+            body: RecordBody::Lost(Box::new(body)),
+        };
+        record.encode(&ctx).unwrap()
+    };
+    // the kernel maps the data pages read-only, so records are written to a fake ring buffer
+    let mmap_len = (sampler.data_offset + sampler.data_size) as usize;
+    sampler.mmap = MmapOptions::new().len(mmap_len).map_anon().unwrap().into();
+
+    let mut data = lost(1);
+    let corrupt_at = data.len();
+    data.extend(lost(2));
+    data.extend(lost(3));
+    // `perf_event_header.size`
+    data[corrupt_at + 6..corrupt_at + 8].copy_from_slice(&3_u16.to_ne_bytes());
+    write_ring(&sampler, 0, &data);
+    assert_eq!(lost_ids(&mut sampler), [1]);
+    let dropped_bytes = (data.len() - corrupt_at) as u64;
+    assert_eq!(sampler.stat().unwrap().dropped_bytes, dropped_bytes);
+
+    // records after the dropped batch are read again
+    write_ring(&sampler, data.len(), &lost(4));
+    assert_eq!(lost_ids(&mut sampler), [4]);
+    assert_eq!(sampler.stat().unwrap().dropped_bytes, dropped_bytes);
+}
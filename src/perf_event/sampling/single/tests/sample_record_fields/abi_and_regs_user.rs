@@ -13,6 +13,7 @@
 // see <https://www.gnu.org/licenses/>.
 
 use crate::config::{Cpu, Process};
+use crate::sampling::record::sample::RegMask;
 use crate::sampling::record::{Record, RecordBody};
 use crate::sampling::{Config, ExtraConfig, OverflowBy, Sampler};
 use crate::test::cpu_workload;
@@ -25,7 +26,8 @@ fn gen_sampler(cfg: &Config) -> Sampler {
 
 fn gen_cfg(sample_regs_user: u64) -> Config {
     let mut extra_config = ExtraConfig::default();
-    extra_config.sample_record_fields.abi_and_regs_user = Some(sample_regs_user);
+    extra_config.sample_record_fields.abi_and_regs_user =
+        Some(RegMask::from_bits(sample_regs_user));
 
     let event = HardwareEvent::CpuCycles;
     let scopes = EventScope::all();
@@ -13,7 +13,7 @@
 // see <https://www.gnu.org/licenses/>.
 
 use crate::config::{Cpu, Process};
-use crate::sampling::record::sample::WeightRepr;
+use crate::sampling::record::sample::{RegMask, WeightRepr};
 use crate::sampling::record::{Record, RecordBody};
 use crate::sampling::{Config, ExtraConfig, OverflowBy, SampleRecordFields, Sampler};
 use crate::test::cpu_workload;
@@ -48,14 +48,15 @@ fn test() {
         v: true,
         ips: Some(1),
         data_raw: true,
-        abi_and_regs_user: Some(1),
+        branch_stack: None, // not all hardware supports it
+        abi_and_regs_user: Some(RegMask::from_bits(1)),
         data_stack_user: Some(2_u16.pow(3)),
         weight: Some(WeightRepr::Full),
         data_src: true,
         #[cfg(feature = "linux-3.13")]
         transaction: true,
         #[cfg(feature = "linux-3.19")]
-        abi_and_regs_intr: Some(1),
+        abi_and_regs_intr: Some(RegMask::from_bits(1)),
         #[cfg(feature = "linux-4.14")]
         phys_addr: true,
         #[cfg(feature = "linux-5.7")]
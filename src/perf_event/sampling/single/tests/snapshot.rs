@@ -0,0 +1,128 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+use crate::config::{Cpu, Process};
+use crate::sampling::record::{Record, RecordBody};
+use crate::sampling::{Config, ExtraConfig, ExtraRecord, OverflowBy, Sampler};
+use crate::{Event, EventScope, SoftwareEvent};
+use memmap2::MmapOptions;
+
+const PAGE: u64 = 4096;
+
+fn gen_sampler(write_backward: bool) -> Sampler {
+    let scopes = EventScope::all();
+    let ev = Event::from(SoftwareEvent::Dummy);
+    let overflow_by = OverflowBy::Period(1);
+    let extra_config = ExtraConfig {
+        mmap_data: true,
+        write_backward,
+        extra_record_types: vec![ExtraRecord::Mmap],
+        ..Default::default()
+    };
+    let cfg = Config::extra_new(&ev, &scopes, &overflow_by, &extra_config);
+    let mmap_pages = 1 + 1;
+    Sampler::new(&Process::Current, &Cpu::Any, mmap_pages, &cfg).unwrap()
+}
+
+/// Map anonymous memory of `(i + 1)` pages, so the `PERF_RECORD_MMAP` records are numbered.
+fn workload(range: std::ops::Range<u64>) {
+    for i in range {
+        let len = (i + 1) * PAGE;
+        let mmap = MmapOptions::new().len(len as _).map_anon().unwrap();
+        std::hint::black_box(mmap);
+    }
+}
+
+fn mmap_numbers(records: &[Record]) -> Vec<u64> {
+    records
+        .iter()
+        .filter_map(|it| match &it.body {
+            RecordBody::Mmap(body) if body.len % PAGE == 0 => Some(body.len / PAGE - 1),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_snapshot_not_full() {
+    let mut sampler = gen_sampler(true);
+    assert!(sampler.snapshot().unwrap().is_empty());
+
+    sampler.enable().unwrap();
+    workload(0..10);
+    sampler.disable().unwrap();
+
+    assert!(sampler.next_record().is_none());
+    let records = sampler.snapshot().unwrap();
+    assert_eq!(records.len(), 10);
+    assert_eq!(mmap_numbers(&records), (0..10).collect::<Vec<_>>());
+
+    // reading again returns the same records
+    assert_eq!(sampler.snapshot().unwrap(), records);
+}
+
+#[test]
+fn test_snapshot_overwrite() {
+    let mut sampler = gen_sampler(true);
+    let data_size = sampler.data_size as usize;
+
+    sampler.enable().unwrap();
+    workload(0..1000);
+    let first = mmap_numbers(&sampler.snapshot().unwrap());
+    workload(1000..2000);
+    sampler.disable().unwrap();
+    let second = mmap_numbers(&sampler.snapshot().unwrap());
+
+    for (numbers, last) in [(first, 999), (second, 1999)] {
+        // the newest records are kept in time order without gaps
+        assert_eq!(numbers.last(), Some(&last));
+        assert!(numbers.windows(2).all(|it| it[0] + 1 == it[1]));
+        // most of the ring buffer is filled, each record is less than 64 bytes
+        assert!(numbers.len() > data_size / 64);
+    }
+}
+
+#[test]
+fn test_snapshot_forward() {
+    let mut sampler = gen_sampler(false);
+    sampler.enable().unwrap();
+    workload(0..10);
+    sampler.disable().unwrap();
+
+    assert!(sampler.snapshot().is_err());
+    let records: Vec<_> = std::iter::from_fn(|| sampler.next_record()).collect();
+    assert_eq!(mmap_numbers(&records), (0..10).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_snapshot_keeps_paused() {
+    let mut sampler = gen_sampler(true);
+    sampler.enable().unwrap();
+    workload(0..10);
+
+    sampler.pause().unwrap();
+    assert_eq!(sampler.snapshot().unwrap().len(), 10);
+    // still paused, so nothing is written
+    workload(10..20);
+    assert_eq!(
+        mmap_numbers(&sampler.snapshot().unwrap()),
+        (0..10).collect::<Vec<_>>()
+    );
+
+    sampler.resume().unwrap();
+    assert_eq!(sampler.snapshot().unwrap().len(), 10);
+    workload(20..30);
+    sampler.disable().unwrap();
+    let numbers = mmap_numbers(&sampler.snapshot().unwrap());
+    assert_eq!(numbers[10..], (20..30).collect::<Vec<_>>());
+}
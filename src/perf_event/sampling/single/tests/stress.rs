@@ -0,0 +1,105 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+
+use crate::config::{Cpu, Process};
+use crate::sampling::record::{Record, RecordBody};
+use crate::sampling::{Config, ExtraConfig, ExtraRecord, OverflowBy, Sampler};
+use crate::{Event, EventScope, SoftwareEvent};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+const MMAP_TIMES: usize = 2_000_000;
+const PAGE_SIZE: usize = 4096;
+
+fn mmap_len(i: usize) -> usize {
+    (i % 7 + 1) * PAGE_SIZE
+}
+
+/// Every anonymous mmap emits `PERF_RECORD_MMAP`, which is not throttled like samples.
+fn workload() {
+    for i in 0..MMAP_TIMES {
+        unsafe {
+            let len = mmap_len(i);
+            let addr = libc::mmap(
+                std::ptr::null_mut(),
+                len,
+                libc::PROT_READ,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            );
+            assert_ne!(addr, libc::MAP_FAILED);
+            libc::munmap(addr, len);
+        }
+    }
+}
+
+/// Drain the ring buffer from another thread while records are being written,
+/// and check the records are intact and in order.
+#[test]
+fn test_ring_buffer_stress() {
+    let scopes = EventScope::all();
+    let ev = Event::from(SoftwareEvent::Dummy);
+    let overflow_by = OverflowBy::Period(u64::MAX >> 1);
+    let extra_config = ExtraConfig {
+        mmap_data: true,
+        extra_record_types: vec![ExtraRecord::Mmap],
+        ..Default::default()
+    };
+    let cfg = Config::extra_new(&ev, &scopes, &overflow_by, &extra_config);
+    let mut sampler = Sampler::new(&Process::Current, &Cpu::Any, 1 + (1 << 6), &cfg).unwrap();
+
+    let enabled = AtomicBool::new(false);
+    let done = AtomicBool::new(false);
+    let (mmap_count, lost_count) = thread::scope(|s| {
+        let reader = s.spawn(|| {
+            // enable here, so mmap of the stack of this thread is not recorded
+            sampler.enable().unwrap();
+            enabled.store(true, Ordering::Release);
+
+            let mut mmap_count = 0;
+            let mut lost_count = 0;
+            loop {
+                // records written before `done` are visible to the last drain
+                let last_drain = done.load(Ordering::Acquire);
+                while let Some(Record { body, .. }) = sampler.next_record() {
+                    match body {
+                        RecordBody::Mmap(body) => {
+                            let i = mmap_count + lost_count;
+                            assert_eq!(body.len as usize, mmap_len(i));
+                            assert_eq!(body.filename.to_bytes(), b"//anon");
+                            mmap_count += 1;
+                        }
+                        RecordBody::Lost(body) => lost_count += body.lost as usize,
+                        body => panic!("Unexpected record: {:?}", body),
+                    }
+                }
+                if last_drain {
+                    break (mmap_count, lost_count);
+                }
+            }
+        });
+
+        while !enabled.load(Ordering::Acquire) {
+            thread::yield_now();
+        }
+        workload();
+        done.store(true, Ordering::Release);
+        reader.join().unwrap()
+    });
+
+    dbg!(mmap_count, lost_count);
+    assert_eq!(mmap_count + lost_count, MMAP_TIMES);
+    assert!(mmap_count > MMAP_TIMES / 2);
+}
@@ -0,0 +1,105 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+#[cfg(test)]
+mod tests;
+
+use crate::sampling::record::Record;
+use crate::sampling::Pollable;
+use futures_core::Stream;
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use tokio::io::unix::AsyncFd;
+use tokio::io::Interest;
+
+/*
+The stream is driven by the kernel wakeups, which happen every `Wakeup::Events`
+samples or `Wakeup::Watermark` bytes, records below the threshold are yielded
+with the next wakeup.
+
+Records are read from the ring buffer only when polled, so a slow consumer
+makes the kernel drop new records and report them with `PERF_RECORD_LOST`
+records in the stream, instead of buffering them unboundedly.
+*/
+
+/// Records of a sampler, tracer or group member as an async `Stream`,
+/// which ends when the monitored task exits and the remaining records are read.
+///
+/// Must be created within a tokio runtime with IO enabled.
+pub struct RecordStream<T: Pollable> {
+    fd: AsyncFd<T>,
+    hup: bool,
+    error: Option<io::Error>,
+}
+
+impl<T: Pollable> RecordStream<T> {
+    pub fn new(handle: T) -> io::Result<Self> {
+        Ok(Self {
+            fd: AsyncFd::with_interest(handle, Interest::READABLE)?,
+            hup: false,
+            error: None,
+        })
+    }
+
+    pub fn get_ref(&self) -> &T {
+        self.fd.get_ref()
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        self.fd.get_mut()
+    }
+
+    pub fn into_inner(self) -> T {
+        self.fd.into_inner()
+    }
+
+    /// The error which ended the stream early, if any.
+    pub fn take_error(&mut self) -> Option<io::Error> {
+        std::mem::take(&mut self.error)
+    }
+}
+
+impl<T: Pollable + Unpin> Stream for RecordStream<T> {
+    type Item = Record;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(record) = this.fd.get_mut().next_record() {
+                return Poll::Ready(Some(record));
+            }
+            if this.hup || this.error.is_some() {
+                return Poll::Ready(None);
+            }
+
+            let mut guard = match ready!(this.fd.poll_read_ready_mut(cx)) {
+                Ok(guard) => guard,
+                Err(e) => {
+                    this.error = Some(e);
+                    continue;
+                }
+            };
+            if guard.ready().is_read_closed() {
+                // `POLLHUP`, drain the remaining records then end
+                this.hup = true;
+                continue;
+            }
+            // readiness set after the guard is taken is kept by `clear_ready`
+            match guard.get_inner_mut().next_record() {
+                Some(record) => return Poll::Ready(Some(record)),
+                None => guard.clear_ready(),
+            }
+        }
+    }
+}
@@ -0,0 +1,158 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+use crate::config::{Cpu, Process};
+use crate::sampling::record::{Record, RecordBody};
+use crate::sampling::{
+    Config, ExtraConfig, ExtraRecord, OverflowBy, Pollable, RecordStream, Sampler,
+};
+use crate::tracing::Tracer;
+use crate::{Event, EventScope, SoftwareEvent};
+use futures_core::Stream;
+use std::future::poll_fn;
+use std::pin::Pin;
+use std::process::Command;
+use std::time::{Duration, Instant};
+use tokio::time::timeout;
+
+fn gen_sampler(process: &Process, ev: SoftwareEvent, extra_config: &ExtraConfig) -> Sampler {
+    let scopes = EventScope::all();
+    let ev = Event::from(ev);
+    let overflow_by = OverflowBy::Period(10000);
+    let cfg = Config::extra_new(&ev, &scopes, &overflow_by, extra_config);
+    let mmap_pages = 1 + 1;
+    Sampler::new(process, &Cpu::Any, mmap_pages, &cfg).unwrap()
+}
+
+fn workload(duration: Duration) {
+    let start = Instant::now();
+    while start.elapsed() < duration {
+        std::hint::black_box(rand::random::<u64>());
+    }
+}
+
+async fn next<T: Pollable + Unpin>(stream: &mut RecordStream<T>) -> Option<Record> {
+    poll_fn(|cx| Pin::new(&mut *stream).poll_next(cx)).await
+}
+
+/// Read records until none arrives within 100ms.
+async fn drain<T: Pollable + Unpin>(stream: &mut RecordStream<T>) -> Vec<Record> {
+    let mut records = vec![];
+    while let Ok(Some(record)) = timeout(Duration::from_millis(100), next(stream)).await {
+        records.push(record);
+    }
+    records
+}
+
+#[tokio::test]
+async fn test_sampler_stream() {
+    let sampler = gen_sampler(
+        &Process::Current,
+        SoftwareEvent::CpuClock,
+        &Default::default(),
+    );
+    let mut stream = RecordStream::new(sampler).unwrap();
+
+    stream.get_ref().enable().unwrap();
+    workload(Duration::from_millis(1));
+    stream.get_ref().disable().unwrap();
+
+    let records = drain(&mut stream).await;
+    assert!(!records.is_empty());
+    assert!(records
+        .iter()
+        .all(|it| matches!(it.body, RecordBody::Sample(_))));
+    assert!(stream.take_error().is_none());
+}
+
+#[tokio::test]
+async fn test_tracer_stream() {
+    let ev = Event::from(SoftwareEvent::PageFaults);
+    let cfg = crate::tracing::Config::new(&ev, &EventScope::all());
+    let tracer = Tracer::new(&Process::Current, &Cpu::Any, 1 + 16, &cfg).unwrap();
+    let mut stream = RecordStream::new(tracer).unwrap();
+
+    stream.get_ref().enable().unwrap();
+    std::hint::black_box(vec![1_u8; 1 << 20]);
+    stream.get_ref().disable().unwrap();
+
+    assert!(!drain(&mut stream).await.is_empty());
+}
+
+/// The stream ends after the monitored task exits.
+#[tokio::test]
+async fn test_stream_hup() {
+    let mut child = Command::new("sleep").arg("0.1").spawn().unwrap();
+    let extra_config = ExtraConfig {
+        extra_record_types: vec![ExtraRecord::ForkAndExit],
+        ..Default::default()
+    };
+    let sampler = gen_sampler(
+        &Process::Pid(child.id()),
+        SoftwareEvent::Dummy,
+        &extra_config,
+    );
+    sampler.enable().unwrap();
+    let mut stream = RecordStream::new(sampler).unwrap();
+
+    let mut records = vec![];
+    let collect = async {
+        while let Some(record) = next(&mut stream).await {
+            records.push(record);
+        }
+    };
+    timeout(Duration::from_secs(10), collect).await.unwrap();
+    child.wait().unwrap();
+
+    assert!(records
+        .iter()
+        .any(|it| matches!(it.body, RecordBody::Exit(_))));
+    // ended
+    assert!(next(&mut stream).await.is_none());
+}
+
+/// Records not read in time are reported by `PERF_RECORD_LOST`.
+#[tokio::test]
+async fn test_stream_backpressure() {
+    let sampler = gen_sampler(
+        &Process::Current,
+        SoftwareEvent::CpuClock,
+        &Default::default(),
+    );
+    let mut stream = RecordStream::new(sampler).unwrap();
+
+    stream.get_ref().enable().unwrap();
+    workload(Duration::from_millis(200));
+    stream.get_ref().disable().unwrap();
+    let mut records = drain(&mut stream).await;
+
+    // the kernel writes `PERF_RECORD_LOST` with the next record when there is space
+    stream.get_ref().enable().unwrap();
+    workload(Duration::from_millis(1));
+    stream.get_ref().disable().unwrap();
+    records.extend(drain(&mut stream).await);
+
+    let lost: u64 = records
+        .iter()
+        .filter_map(|it| match &it.body {
+            RecordBody::Lost(body) => Some(body.lost),
+            _ => None,
+        })
+        .sum();
+    assert!(lost > 0);
+    let samples = records
+        .iter()
+        .filter(|it| matches!(it.body, RecordBody::Sample(_)))
+        .count();
+    assert!(samples > 0);
+}
@@ -0,0 +1,157 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+
+use crate::counting::CounterStat;
+use crate::infra::WrapResult;
+use crate::sampling::SamplerStat;
+use crate::topology::{CpuTopology, Topology};
+use std::collections::BTreeMap;
+use std::io;
+
+/// Like `--per-core`, `--per-die`, `--per-socket` and `--per-node` of `perf stat`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AggrMode {
+    /// SMT siblings
+    Core,
+    Die,
+    Socket,
+    Node,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum AggrId {
+    Core {
+        socket_id: u32,
+        die_id: u32,
+        core_id: u32,
+    },
+    Die {
+        socket_id: u32,
+        die_id: u32,
+    },
+    Socket {
+        socket_id: u32,
+    },
+    Node {
+        node_id: u32,
+    },
+}
+
+impl CpuTopology {
+    pub const fn aggr_id(&self, mode: &AggrMode) -> AggrId {
+        match mode {
+            AggrMode::Core => AggrId::Core {
+                socket_id: self.socket_id,
+                die_id: self.die_id,
+                core_id: self.core_id,
+            },
+            AggrMode::Die => AggrId::Die {
+                socket_id: self.socket_id,
+                die_id: self.die_id,
+            },
+            AggrMode::Socket => AggrId::Socket {
+                socket_id: self.socket_id,
+            },
+            AggrMode::Node => AggrId::Node {
+                node_id: self.node_id,
+            },
+        }
+    }
+}
+
+/// Sum of per-CPU stats
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AggrStat {
+    /// CPUs contributed to this stat
+    pub cpus: Vec<u32>,
+    pub event_count: u64,
+    pub time_enabled: u64,
+    pub time_running: u64,
+}
+
+impl AggrStat {
+    fn add(&mut self, cpu: u32, other: Self) {
+        self.cpus.push(cpu);
+        self.event_count += other.event_count;
+        self.time_enabled += other.time_enabled;
+        self.time_running += other.time_running;
+    }
+}
+
+impl From<CounterStat> for AggrStat {
+    fn from(stat: CounterStat) -> Self {
+        Self {
+            cpus: vec![],
+            event_count: stat.event_count,
+            time_enabled: stat.time_enabled,
+            time_running: stat.time_running,
+        }
+    }
+}
+
+impl From<SamplerStat> for AggrStat {
+    fn from(stat: SamplerStat) -> Self {
+        Self {
+            cpus: vec![],
+            event_count: stat.event_count,
+            time_enabled: stat.time_enabled,
+            time_running: stat.time_running,
+        }
+    }
+}
+
+impl Topology {
+    /// Group per-CPU items such as `(cpu, Counter)` or `(cpu, Sampler)` by `mode`.
+    pub fn group<T>(
+        &self,
+        mode: &AggrMode,
+        per_cpu: impl IntoIterator<Item = (u32, T)>,
+    ) -> io::Result<BTreeMap<AggrId, Vec<(u32, T)>>> {
+        let mut groups = BTreeMap::<_, Vec<_>>::new();
+        for (cpu, it) in per_cpu {
+            let Some(topology) = self.cpu(cpu) else {
+                let msg = format!("CPU {} is not online", cpu);
+                return Err(io::Error::new(io::ErrorKind::NotFound, msg));
+            };
+            groups
+                .entry(topology.aggr_id(mode))
+                .or_default()
+                .push((cpu, it));
+        }
+        groups.wrap_ok()
+    }
+
+    /// Sum per-CPU stats such as `(cpu, CounterStat)` or `(cpu, SamplerStat)` by `mode`.
+    pub fn aggregate<S>(
+        &self,
+        mode: &AggrMode,
+        per_cpu: impl IntoIterator<Item = (u32, S)>,
+    ) -> io::Result<BTreeMap<AggrId, AggrStat>>
+    where
+        S: Into<AggrStat>,
+    {
+        let groups = self.group(mode, per_cpu)?;
+        groups
+            .into_iter()
+            .map(|(id, stats)| {
+                let mut aggr = AggrStat::default();
+                stats
+                    .into_iter()
+                    .for_each(|(cpu, stat)| aggr.add(cpu, stat.into()));
+                (id, aggr)
+            })
+            .collect::<BTreeMap<_, _>>()
+            .wrap_ok()
+    }
+}
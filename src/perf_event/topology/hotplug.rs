@@ -0,0 +1,323 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+
+use crate::config;
+use crate::counting::Counter;
+use crate::infra::WrapResult;
+use crate::sampling::Sampler;
+use crate::topology::{online_cpus_from_root, AggrStat};
+use crate::tracing::Tracer;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io;
+use std::io::Read;
+use std::mem::{size_of, zeroed};
+use std::os::fd::{AsRawFd, FromRawFd, RawFd};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Event that can be a member of [`PerCpuSet`].
+pub trait PerCpuMember {
+    fn enable(&self) -> io::Result<()>;
+    fn disable(&self) -> io::Result<()>;
+    fn stat(&mut self) -> io::Result<AggrStat>;
+}
+
+impl PerCpuMember for Counter {
+    fn enable(&self) -> io::Result<()> {
+        self.enable()
+    }
+
+    fn disable(&self) -> io::Result<()> {
+        self.disable()
+    }
+
+    fn stat(&mut self) -> io::Result<AggrStat> {
+        self.stat().map(Into::into)
+    }
+}
+
+impl PerCpuMember for Sampler {
+    fn enable(&self) -> io::Result<()> {
+        self.enable()
+    }
+
+    fn disable(&self) -> io::Result<()> {
+        self.disable()
+    }
+
+    fn stat(&mut self) -> io::Result<AggrStat> {
+        self.stat().map(Into::into)
+    }
+}
+
+impl PerCpuMember for Tracer {
+    fn enable(&self) -> io::Result<()> {
+        self.enable()
+    }
+
+    fn disable(&self) -> io::Result<()> {
+        self.disable()
+    }
+
+    fn stat(&mut self) -> io::Result<AggrStat> {
+        self.stat().map(Into::into)
+    }
+}
+
+/// CPUs changed since the last [`PerCpuSet::update`]
+#[derive(Debug)]
+pub struct HotplugChange<T> {
+    /// CPUs went online, events are opened on them
+    pub added: Vec<u32>,
+    /// CPUs went offline with their retired events,
+    /// samplers and tracers can still be drained
+    pub removed: Vec<(u32, T)>,
+}
+
+/// Kernel uevents of CPU hotplug, i.e. `NETLINK_KOBJECT_UEVENT` messages of
+/// the `cpu` subsystem, as sysfs does not notify changes of `/sys/devices/system/cpu/online`.
+///
+/// The fd is readable when uevents arrive, so it can be polled with other fds.
+pub struct HotplugWatch {
+    socket: File,
+}
+
+impl HotplugWatch {
+    pub fn new() -> io::Result<Self> {
+        let flags = libc::SOCK_DGRAM | libc::SOCK_CLOEXEC | libc::SOCK_NONBLOCK;
+        let fd = unsafe { libc::socket(libc::AF_NETLINK, flags, libc::NETLINK_KOBJECT_UEVENT) };
+        if fd == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        let socket = unsafe { File::from_raw_fd(fd) };
+
+        let mut addr: libc::sockaddr_nl = unsafe { zeroed() };
+        addr.nl_family = libc::AF_NETLINK as _;
+        // multicast group of uevents from the kernel
+        addr.nl_groups = 1;
+        let addr_ptr = &addr as *const libc::sockaddr_nl as *const libc::sockaddr;
+        let addr_len = size_of::<libc::sockaddr_nl>() as _;
+        if unsafe { libc::bind(fd, addr_ptr, addr_len) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Self { socket }.wrap_ok()
+    }
+
+    /// Consume the uevents received so far without blocking,
+    /// returns whether any of them is of CPU hotplug.
+    pub fn take(&self) -> io::Result<bool> {
+        // uevents are at most `UEVENT_BUFFER_SIZE` (2048) bytes
+        let mut buf = [0_u8; 4096];
+        let mut changed = false;
+        loop {
+            match (&self.socket).read(&mut buf) {
+                // e.g. `online@/devices/system/cpu/cpu1\0ACTION=online\0...\0SUBSYSTEM=cpu\0`
+                Ok(len) => {
+                    changed |= buf[..len]
+                        .split(|it| *it == 0)
+                        .any(|it| it == b"SUBSYSTEM=cpu");
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(changed),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Wait up to `timeout` for uevents, returns whether any of them is of CPU hotplug.
+    pub fn wait(&self, timeout: Duration) -> io::Result<bool> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let mut pollfd = libc::pollfd {
+                fd: self.socket.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            let left = deadline.saturating_duration_since(Instant::now());
+            let left = left.as_micros().div_ceil(1000).min(i32::MAX as _) as _;
+            match unsafe { libc::poll(&mut pollfd, 1, left) } {
+                -1 if io::Error::last_os_error().kind() == io::ErrorKind::Interrupted => {}
+                -1 => return Err(io::Error::last_os_error()),
+                0 => return Ok(false),
+                _ => {
+                    if self.take()? {
+                        return Ok(true);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl AsRawFd for HotplugWatch {
+    fn as_raw_fd(&self) -> RawFd {
+        self.socket.as_raw_fd()
+    }
+}
+
+/// One event per online CPU, which follows CPU hotplug.
+///
+/// Changes are applied by [`PerCpuSet::update`], which should be called periodically
+/// or when [`HotplugWatch`] reports changes, see: [`PerCpuSet::wait_change`].
+pub struct PerCpuSet<T> {
+    root: PathBuf,
+    /// Opened by the first `wait_change`
+    watch: Option<HotplugWatch>,
+    open: Box<dyn FnMut(u32) -> config::Result<T>>,
+    enabled: bool,
+    members: BTreeMap<u32, T>,
+    /// Sum of final stats of retired events
+    retired: AggrStat,
+    /// CPUs retired in the current interval
+    retired_cpus: BTreeSet<u32>,
+}
+
+impl<T: PerCpuMember> PerCpuSet<T> {
+    /// `open` opens the event on the given CPU, e.g.
+    /// `|cpu| Counter::new(&Process::Any, &Cpu::Id(cpu), &mut cfg)`
+    pub fn new(open: impl FnMut(u32) -> config::Result<T> + 'static) -> config::Result<Self> {
+        Self::from_root("/", open)
+    }
+
+    /// Watch sysfs under `root` instead of `/`, useful for fake hotplug.
+    pub fn from_root(
+        root: impl AsRef<Path>,
+        open: impl FnMut(u32) -> config::Result<T> + 'static,
+    ) -> config::Result<Self> {
+        let mut set = Self {
+            root: root.as_ref().to_path_buf(),
+            watch: None,
+            open: Box::new(open),
+            enabled: false,
+            members: BTreeMap::new(),
+            retired: AggrStat::default(),
+            retired_cpus: BTreeSet::new(),
+        };
+        set.update()?;
+        set.wrap_ok()
+    }
+
+    pub fn enable(&mut self) -> io::Result<()> {
+        self.members.values().try_for_each(|it| it.enable())?;
+        self.enabled = true;
+        Ok(())
+    }
+
+    pub fn disable(&mut self) -> io::Result<()> {
+        self.members.values().try_for_each(|it| it.disable())?;
+        self.enabled = false;
+        Ok(())
+    }
+
+    /// Open events on CPUs went online and retire events on CPUs went offline.
+    ///
+    /// On errors nothing is retired, and the update can be retried.
+    pub fn update(&mut self) -> config::Result<HotplugChange<T>> {
+        let online: BTreeSet<u32> = online_cpus_from_root(&self.root)
+            .map_err(config::Error::SyscallFailed)?
+            .into_iter()
+            .collect();
+
+        // members are only moved after everything fallible is done,
+        // so none of them is lost on errors
+
+        let offline: Vec<u32> = self
+            .members
+            .keys()
+            .filter(|cpu| !online.contains(cpu))
+            .copied()
+            .collect();
+        let mut final_stats = vec![];
+        for &cpu in &offline {
+            let member = self.members.get_mut(&cpu).unwrap();
+            // a disabled event keeps its count, which is the final one
+            member.disable().map_err(config::Error::SyscallFailed)?;
+            final_stats.push(member.stat().map_err(config::Error::SyscallFailed)?);
+        }
+
+        let mut opened = vec![];
+        for cpu in online {
+            if self.members.contains_key(&cpu) {
+                continue;
+            }
+            let member = (self.open)(cpu)?;
+            if self.enabled {
+                member.enable().map_err(config::Error::SyscallFailed)?;
+            }
+            opened.push((cpu, member));
+        }
+
+        let mut change = HotplugChange {
+            added: opened.iter().map(|(cpu, _)| *cpu).collect(),
+            removed: vec![],
+        };
+        for (cpu, stat) in offline.into_iter().zip(final_stats) {
+            self.retired.event_count += stat.event_count;
+            self.retired.time_enabled += stat.time_enabled;
+            self.retired.time_running += stat.time_running;
+            self.retired_cpus.insert(cpu);
+            let member = self.members.remove(&cpu).unwrap();
+            change.removed.push((cpu, member));
+        }
+        self.members.extend(opened);
+
+        change.wrap_ok()
+    }
+
+    /// Wait up to `timeout` for CPU hotplug uevents, then `update`.
+    ///
+    /// The sysfs is read again even on timeout, so changes missed by the watch,
+    /// e.g. made before the first call or under a fake root, are applied at the latest then.
+    pub fn wait_change(&mut self, timeout: Duration) -> config::Result<HotplugChange<T>> {
+        let watch = match &mut self.watch {
+            Some(watch) => watch,
+            None => self
+                .watch
+                .insert(HotplugWatch::new().map_err(config::Error::SyscallFailed)?),
+        };
+        watch.wait(timeout).map_err(config::Error::SyscallFailed)?;
+        self.update()
+    }
+
+    /// Map of `cpu` -> event on it
+    pub const fn members(&self) -> &BTreeMap<u32, T> {
+        &self.members
+    }
+
+    /// Map of `cpu` -> event on it, samplers and tracers can be drained here
+    pub fn members_mut(&mut self) -> &mut BTreeMap<u32, T> {
+        &mut self.members
+    }
+
+    /// Accumulated totals of current and retired events, `cpus` is the CPUs
+    /// contributed since the last call, i.e. current CPUs and those retired in the interval.
+    pub fn stat(&mut self) -> io::Result<AggrStat> {
+        let mut stat = AggrStat {
+            cpus: vec![],
+            ..self.retired.clone()
+        };
+        for member in self.members.values_mut() {
+            let member = member.stat()?;
+            stat.event_count += member.event_count;
+            stat.time_enabled += member.time_enabled;
+            stat.time_running += member.time_running;
+        }
+
+        let mut cpus = std::mem::take(&mut self.retired_cpus);
+        cpus.extend(self.members.keys());
+        stat.cpus = cpus.into_iter().collect();
+        stat.wrap_ok()
+    }
+}
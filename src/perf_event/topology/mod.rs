@@ -0,0 +1,155 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+
+mod aggr;
+mod hotplug;
+#[cfg(test)]
+mod tests;
+
+use crate::infra::WrapResult;
+pub use aggr::*;
+pub use hotplug::*;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+/// Location of one online CPU, see: `/sys/devices/system/cpu/cpu*/topology`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CpuTopology {
+    pub cpu: u32,
+    /// The content of `core_id`, unique only within the same die
+    pub core_id: u32,
+    /// The content of `die_id`, `0` before Linux 5.2
+    pub die_id: u32,
+    /// The content of `physical_package_id`
+    pub socket_id: u32,
+    /// NUMA node from `/sys/devices/system/node/node*/cpulist`,
+    /// `0` if the kernel is built without NUMA
+    pub node_id: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct Topology {
+    /// Sorted by `cpu`
+    cpus: Vec<CpuTopology>,
+}
+
+impl Topology {
+    pub fn new() -> io::Result<Self> {
+        Self::from_root("/")
+    }
+
+    /// Read sysfs under `root` instead of `/`, useful for fake topologies.
+    pub fn from_root(root: impl AsRef<Path>) -> io::Result<Self> {
+        let root = root.as_ref();
+        let cpu_dir = root.join("sys/devices/system/cpu");
+        let cpu_nodes = cpu_nodes(&root.join("sys/devices/system/node"))?;
+
+        let cpus = online_cpus_from_root(root)?
+            .into_iter()
+            .map(|cpu| {
+                let topology_dir = cpu_dir.join(format!("cpu{}/topology", cpu));
+                CpuTopology {
+                    cpu,
+                    core_id: read_id(&topology_dir.join("core_id"))?.unwrap_or(0),
+                    die_id: read_id(&topology_dir.join("die_id"))?.unwrap_or(0),
+                    socket_id: read_id(&topology_dir.join("physical_package_id"))?.unwrap_or(0),
+                    node_id: cpu_nodes.get(&cpu).copied().unwrap_or(0),
+                }
+                .wrap_ok()
+            })
+            .collect::<io::Result<_>>()?;
+
+        Self { cpus }.wrap_ok()
+    }
+
+    pub fn cpus(&self) -> &[CpuTopology] {
+        &self.cpus
+    }
+
+    pub fn cpu(&self, cpu: u32) -> Option<&CpuTopology> {
+        self.cpus
+            .binary_search_by_key(&cpu, |it| it.cpu)
+            .ok()
+            .map(|i| &self.cpus[i])
+    }
+}
+
+/// Get online CPUs from `/sys/devices/system/cpu/online`.
+pub fn online_cpus() -> io::Result<Vec<u32>> {
+    online_cpus_from_root(Path::new("/"))
+}
+
+pub(crate) fn online_cpus_from_root(root: &Path) -> io::Result<Vec<u32>> {
+    let online = fs::read_to_string(root.join("sys/devices/system/cpu/online"))?;
+    parse_cpu_list(&online)
+}
+
+/// Parse the CPU list format, e.g. `0-3,5,7-8`.
+pub(crate) fn parse_cpu_list(list: &str) -> io::Result<Vec<u32>> {
+    let list = list.trim();
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, list);
+
+    let mut cpus = vec![];
+    for range in list.split(',').filter(|it| !it.is_empty()) {
+        let (first, last) = range.split_once('-').unwrap_or((range, range));
+        let first: u32 = first.parse().map_err(|_| invalid())?;
+        let last: u32 = last.parse().map_err(|_| invalid())?;
+        cpus.extend(first..=last);
+    }
+    cpus.wrap_ok()
+}
+
+/// Returns `None` if the id file does not exist.
+fn read_id(path: &Path) -> io::Result<Option<u32>> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    // `die_id` and `physical_package_id` are `-1` if unknown
+    let id = content
+        .trim()
+        .parse::<i64>()
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, content.trim()))?;
+    Ok(Some(id.max(0) as _))
+}
+
+/// Map of `cpu` -> `node_id`, empty if the kernel is built without NUMA.
+fn cpu_nodes(node_dir: &Path) -> io::Result<HashMap<u32, u32>> {
+    let entries = match fs::read_dir(node_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(HashMap::new()),
+        Err(e) => return Err(e),
+    };
+
+    let mut cpu_nodes = HashMap::new();
+    for entry in entries {
+        let entry = entry?;
+        let name = entry.file_name();
+        let Some(node_id) = name
+            .to_str()
+            .and_then(|it| it.strip_prefix("node"))
+            .and_then(|it| it.parse::<u32>().ok())
+        else {
+            continue;
+        };
+
+        let cpulist: PathBuf = entry.path().join("cpulist");
+        for cpu in parse_cpu_list(&fs::read_to_string(cpulist)?)? {
+            cpu_nodes.insert(cpu, node_id);
+        }
+    }
+    cpu_nodes.wrap_ok()
+}
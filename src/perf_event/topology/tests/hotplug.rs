@@ -0,0 +1,167 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+
+use crate::config;
+use crate::config::{Cpu, Process};
+use crate::counting::Counter;
+use crate::sampling::{ExtraConfig, OverflowBy, Sampler};
+use crate::test::cpu_workload;
+use crate::topology::tests::gen_fake_root;
+use crate::topology::{HotplugWatch, PerCpuSet};
+use crate::{counting, sampling, Event, EventScope, SoftwareEvent};
+use std::cell::Cell;
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+fn set_online(root: &Path, online: &str) {
+    fs::write(root.join("sys/devices/system/cpu/online"), online).unwrap();
+}
+
+#[test]
+fn test_counter_hotplug() {
+    let root = gen_fake_root("counter_hotplug");
+    set_online(&root, "0\n");
+
+    let mut set = PerCpuSet::from_root(&root, |cpu| {
+        let scopes = EventScope::all();
+        let ev = Event::from(SoftwareEvent::CpuClock);
+        let mut cfg = counting::Config::new(&ev, &scopes);
+        Counter::new(&Process::Any, &Cpu::Id(cpu), &mut cfg)
+    })
+    .unwrap();
+    assert_eq!(set.members().keys().copied().collect::<Vec<_>>(), vec![0]);
+
+    set.enable().unwrap();
+    cpu_workload();
+    let first = set.stat().unwrap();
+    assert_eq!(first.cpus, vec![0]);
+    assert!(first.event_count > 0);
+
+    set_online(&root, "\n");
+    let change = set.update().unwrap();
+    assert!(change.added.is_empty());
+    assert_eq!(change.removed.len(), 1);
+    assert!(set.members().is_empty());
+
+    // retired CPU contributed to this interval
+    let retired = set.stat().unwrap();
+    assert_eq!(retired.cpus, vec![0]);
+    assert!(retired.event_count >= first.event_count);
+    assert!(set.stat().unwrap().cpus.is_empty());
+
+    set_online(&root, "0\n");
+    let change = set.update().unwrap();
+    assert_eq!(change.added, vec![0]);
+    cpu_workload();
+    // newly opened event is enabled too, and totals are kept
+    let reopened = set.stat().unwrap();
+    assert_eq!(reopened.cpus, vec![0]);
+    assert!(reopened.event_count > retired.event_count);
+
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn test_sampler_hotplug() {
+    let root = gen_fake_root("sampler_hotplug");
+    set_online(&root, "0\n");
+
+    let mut set = PerCpuSet::from_root(&root, |cpu| {
+        let scopes = EventScope::all();
+        let ev = Event::from(SoftwareEvent::CpuClock);
+        let overflow_by = OverflowBy::Period(1000);
+        let extra_config = ExtraConfig::default();
+        let cfg = sampling::Config::extra_new(&ev, &scopes, &overflow_by, &extra_config);
+        Sampler::new(&Process::Current, &Cpu::Id(cpu), 1 + (1 << 4), &cfg)
+    })
+    .unwrap();
+
+    set.enable().unwrap();
+    cpu_workload();
+    set.disable().unwrap();
+
+    set_online(&root, "\n");
+    let mut change = set.update().unwrap();
+    let (cpu, sampler) = change.removed.first_mut().unwrap();
+    assert_eq!(*cpu, 0);
+    assert!(sampler.next_record().is_some());
+
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn test_wait_change() {
+    let root = gen_fake_root("wait_change");
+    set_online(&root, "0\n");
+
+    let mut set = PerCpuSet::from_root(&root, |cpu| {
+        let scopes = EventScope::all();
+        let ev = Event::from(SoftwareEvent::CpuClock);
+        let mut cfg = counting::Config::new(&ev, &scopes);
+        Counter::new(&Process::Any, &Cpu::Id(cpu), &mut cfg)
+    })
+    .unwrap();
+
+    // no uevents of the fake root, the change is applied on timeout
+    set_online(&root, "\n");
+    let start = Instant::now();
+    let change = set.wait_change(Duration::from_millis(50)).unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(50));
+    assert_eq!(change.removed.len(), 1);
+    assert!(set.members().is_empty());
+
+    let watch = HotplugWatch::new().unwrap();
+    assert!(!watch.take().unwrap());
+    assert!(!watch.wait(Duration::ZERO).unwrap());
+
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn test_update_error() {
+    let root = gen_fake_root("update_error");
+    set_online(&root, "0\n");
+
+    // opening on CPU 1 fails until allowed
+    let allowed = Rc::new(Cell::new(false));
+    let mut set = PerCpuSet::from_root(&root, {
+        let allowed = allowed.clone();
+        move |cpu| {
+            if cpu == 1 && !allowed.get() {
+                return Err(config::Error::InvalidProcessCpu);
+            }
+            let scopes = EventScope::all();
+            let ev = Event::from(SoftwareEvent::CpuClock);
+            let mut cfg = counting::Config::new(&ev, &scopes);
+            Counter::new(&Process::Any, &Cpu::Id(0), &mut cfg)
+        }
+    })
+    .unwrap();
+
+    // CPU 0 is not retired since the update failed
+    set_online(&root, "1\n");
+    assert!(set.update().is_err());
+    assert_eq!(set.members().keys().copied().collect::<Vec<_>>(), vec![0]);
+
+    allowed.set(true);
+    let change = set.update().unwrap();
+    assert_eq!(change.added, vec![1]);
+    assert_eq!(change.removed.len(), 1);
+    assert_eq!(change.removed[0].0, 0);
+    assert_eq!(set.members().keys().copied().collect::<Vec<_>>(), vec![1]);
+
+    fs::remove_dir_all(root).unwrap();
+}
@@ -0,0 +1,184 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+
+mod hotplug;
+
+use crate::config::{Cpu, Process};
+use crate::counting::{Config, Counter, CounterStat};
+use crate::test::cpu_workload;
+use crate::topology::{parse_cpu_list, AggrId, AggrMode, CpuTopology, Topology};
+use crate::{Event, EventScope, SoftwareEvent};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 2 sockets * 2 cores * 2 SMT, one NUMA node per socket, `cpu7` is offline
+fn gen_fake_root(name: &str) -> PathBuf {
+    let root = std::env::temp_dir().join(format!(
+        "perf-event-rs-topology-{}-{}",
+        std::process::id(),
+        name
+    ));
+    let _ = fs::remove_dir_all(&root);
+
+    let write = |path: &str, content: &str| {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    };
+
+    write("sys/devices/system/cpu/online", "0-6\n");
+    for cpu in 0..7 {
+        let dir = format!("sys/devices/system/cpu/cpu{}/topology", cpu);
+        write(&format!("{}/core_id", dir), &format!("{}\n", cpu % 2));
+        write(&format!("{}/die_id", dir), "0\n");
+        write(
+            &format!("{}/physical_package_id", dir),
+            &format!("{}\n", (cpu / 2) % 2),
+        );
+    }
+    write("sys/devices/system/node/node0/cpulist", "0-1,4-5\n");
+    write("sys/devices/system/node/node1/cpulist", "2-3,6-7\n");
+    write("sys/devices/system/node/online", "0-1\n");
+
+    root
+}
+
+fn stat(event_count: u64) -> CounterStat {
+    CounterStat {
+        event_id: 0,
+        event_count,
+        time_enabled: 1,
+        time_running: 1,
+    }
+}
+
+#[test]
+fn test_parse_cpu_list() {
+    assert_eq!(parse_cpu_list("0\n").unwrap(), vec![0]);
+    assert_eq!(
+        parse_cpu_list("0-3,5,7-8").unwrap(),
+        vec![0, 1, 2, 3, 5, 7, 8]
+    );
+    assert_eq!(parse_cpu_list("\n").unwrap(), vec![]);
+    assert!(parse_cpu_list("0-a").is_err());
+}
+
+#[test]
+fn test_from_root() {
+    let root = gen_fake_root("from_root");
+    let topology = Topology::from_root(&root).unwrap();
+
+    assert_eq!(topology.cpus().len(), 7);
+    assert!(topology.cpu(7).is_none());
+    assert_eq!(
+        topology.cpu(6).unwrap(),
+        &CpuTopology {
+            cpu: 6,
+            core_id: 0,
+            die_id: 0,
+            socket_id: 1,
+            node_id: 1,
+        }
+    );
+
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn test_missing_die_and_node() {
+    let root = gen_fake_root("missing");
+    fs::remove_dir_all(root.join("sys/devices/system/node")).unwrap();
+    fs::remove_file(root.join("sys/devices/system/cpu/cpu3/topology/die_id")).unwrap();
+    let topology = Topology::from_root(&root).unwrap();
+
+    let cpu = topology.cpu(3).unwrap();
+    assert_eq!(cpu.die_id, 0);
+    assert_eq!(cpu.node_id, 0);
+
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn test_aggregate() {
+    let root = gen_fake_root("aggregate");
+    let topology = Topology::from_root(&root).unwrap();
+    let per_cpu = || (0..7).map(|cpu| (cpu, stat(cpu as u64 + 1)));
+
+    let per_core = topology.aggregate(&AggrMode::Core, per_cpu()).unwrap();
+    assert_eq!(per_core.len(), 4);
+    let core = &per_core[&AggrId::Core {
+        socket_id: 0,
+        die_id: 0,
+        core_id: 0,
+    }];
+    assert_eq!(core.cpus, vec![0, 4]);
+    assert_eq!(core.event_count, 1 + 5);
+    assert_eq!(core.time_enabled, 2);
+
+    let per_socket = topology.aggregate(&AggrMode::Socket, per_cpu()).unwrap();
+    assert_eq!(per_socket.len(), 2);
+    assert_eq!(
+        per_socket[&AggrId::Socket { socket_id: 1 }].cpus,
+        vec![2, 3, 6]
+    );
+
+    let per_die = topology.aggregate(&AggrMode::Die, per_cpu()).unwrap();
+    assert_eq!(per_die.len(), 2);
+
+    let per_node = topology.aggregate(&AggrMode::Node, per_cpu()).unwrap();
+    assert_eq!(
+        per_node[&AggrId::Node { node_id: 0 }].event_count,
+        1 + 2 + 5 + 6
+    );
+
+    assert!(topology.aggregate(&AggrMode::Core, [(7, stat(1))]).is_err());
+
+    fs::remove_dir_all(root).unwrap();
+}
+
+fn test_counters(root: &Path) {
+    let topology = Topology::from_root(root).unwrap();
+    let scopes = EventScope::all();
+    let ev = Event::from(SoftwareEvent::CpuClock);
+    let mut cfg = Config::new(&ev, &scopes);
+
+    let counters: Vec<_> = topology
+        .cpus()
+        .iter()
+        .map(|it| {
+            let counter = Counter::new(&Process::Any, &Cpu::Id(it.cpu), &mut cfg).unwrap();
+            (it.cpu, counter)
+        })
+        .collect();
+
+    counters.iter().for_each(|(_, it)| it.enable().unwrap());
+    cpu_workload();
+    counters.iter().for_each(|(_, it)| it.disable().unwrap());
+
+    let per_socket = topology.group(&AggrMode::Socket, counters).unwrap();
+    let stats = per_socket
+        .into_values()
+        .flatten()
+        .map(|(cpu, mut counter)| (cpu, counter.stat().unwrap()));
+    let per_node = topology.aggregate(&AggrMode::Node, stats).unwrap();
+
+    let cpus: usize = per_node.values().map(|it| it.cpus.len()).sum();
+    assert_eq!(cpus, topology.cpus().len());
+    assert!(per_node.values().any(|it| it.event_count > 0));
+}
+
+#[test]
+fn test_real() {
+    test_counters(Path::new("/"));
+}
@@ -14,7 +14,11 @@
 
 mod new;
 
+#[cfg(feature = "linux-5.13")]
+use crate::infra::WrapOption;
 use crate::perf_event::PerfEventAttr;
+#[cfg(feature = "linux-5.13")]
+use crate::CorePmu;
 use crate::{Event, EventScope};
 use std::ffi::CString;
 use std::rc::Rc;
@@ -60,4 +64,17 @@ impl Config {
     pub const fn as_raw(&self) -> &PerfEventAttr {
         &self.perf_event_attr
     }
+
+    /// This config on the given core PMU of hybrid CPUs, see: [`hybrid_pmus`](crate::hybrid_pmus)
+    ///
+    /// `None` if the event is not a hardware or cache event. The result should only be
+    /// traced on `pmu.cpus`, and once per core PMU to cover all CPUs.
+    #[cfg(feature = "linux-5.13")]
+    pub fn on_pmu(&self, pmu: &CorePmu) -> Option<Self> {
+        Self {
+            perf_event_attr: self.perf_event_attr.on_pmu(pmu)?,
+            ..self.clone()
+        }
+        .wrap_some()
+    }
 }
@@ -72,8 +72,8 @@ pub fn new<'t>(
         __bindgen_anon_3: perf_event_attr__bindgen_ty_3::default(),
         __bindgen_anon_4: perf_event_attr__bindgen_ty_4::default(),
 
-        branch_sample_type: 0, // TODO: Not all hardware supports this feature
-        sample_regs_user: sample_record_fields.abi_and_regs_user.unwrap_or(0),
+        branch_sample_type: sample_record_fields.branch_stack.map_or(0, |it| it.bits()),
+        sample_regs_user: sample_record_fields.abi_and_regs_user.map_or(0, u64::from),
         sample_stack_user: sample_record_fields.data_stack_user.unwrap_or(0) as _,
         #[rustfmt::skip]
         #[cfg(feature = "linux-4.1")]
@@ -85,7 +85,7 @@ pub fn new<'t>(
             ClockId::Tai          => CLOCK_TAI,
         }) as _,
         #[cfg(feature = "linux-3.19")]
-        sample_regs_intr: sample_record_fields.abi_and_regs_intr.unwrap_or(0),
+        sample_regs_intr: sample_record_fields.abi_and_regs_intr.map_or(0, u64::from),
         #[cfg(feature = "linux-4.1")]
         aux_watermark: 0, // TODO
         #[cfg(feature = "linux-4.8")]
@@ -162,7 +162,7 @@ pub fn new<'t>(
     #[cfg(feature = "linux-4.3")]
     perf_event_attr.set_context_switch(0);
     #[cfg(feature = "linux-4.7")]
-    perf_event_attr.set_write_backward(0);
+    perf_event_attr.set_write_backward(extra_config.write_backward as _);
     #[cfg(feature = "linux-4.12")]
     perf_event_attr.set_namespaces(0);
     #[cfg(feature = "linux-5.1")]
@@ -20,16 +20,16 @@ use crate::config::Error;
 use crate::infra::Vla;
 #[cfg(feature = "linux-4.17")]
 use crate::infra::WrapResult;
-use crate::sampling::record::Record;
-use crate::sampling::{Sampler, SamplerStat};
+use crate::sampling::record::{ParseContext, Record, RecordRef};
+use crate::sampling::{Readiness, Sampler, SamplerStat};
 use crate::syscall::bindings::*;
 use crate::syscall::{ioctl_wrapped, perf_event_open_wrapped};
-use memmap2::MmapOptions;
 #[cfg(feature = "linux-4.17")]
 use std::alloc::{alloc, Layout};
 use std::fs::File;
 use std::io;
-use std::os::fd::FromRawFd;
+use std::os::fd::{AsRawFd, FromRawFd, RawFd};
+use std::time::Duration;
 
 use crate::config::{Cpu, Process};
 use crate::tracing::Config;
@@ -60,26 +60,8 @@ impl Tracer {
             .map_err(Error::SyscallFailed)?;
         let file = unsafe { File::from_raw_fd(fd) };
 
-        let mmap = unsafe {
-            MmapOptions::new()
-                .len(page_size::get() * mmap_pages)
-                .map_mut(&file)
-        }
-        .unwrap();
-
-        let page_size = page_size::get();
-
-        let sampler = Sampler {
-            mmap,
-            file,
-            data_size: ((mmap_pages - 1) * page_size) as _,
-            data_offset: page_size as _,
-            sample_type: perf_event_attr.sample_type,
-            sample_id_all: perf_event_attr.sample_id_all() > 0,
-            regs_user_len: perf_event_attr.sample_regs_user.count_ones() as _,
-            #[cfg(feature = "linux-3.19")]
-            regs_intr_len: perf_event_attr.sample_regs_intr.count_ones() as _,
-        };
+        let sampler =
+            Sampler::from_file(file, mmap_pages, perf_event_attr).map_err(Error::SyscallFailed)?;
 
         Ok(Self { sampler })
     }
@@ -110,10 +92,29 @@ impl Tracer {
         self.sampler.refresh(refresh)
     }
 
+    /// See `Sampler::wait`
+    pub fn wait(&self, timeout: Option<Duration>) -> io::Result<Readiness> {
+        self.sampler.wait(timeout)
+    }
+
     pub fn next_record(&mut self) -> Option<Record> {
         self.sampler.next_record()
     }
 
+    pub fn next_record_ref(&mut self) -> Option<RecordRef<'_>> {
+        self.sampler.next_record_ref()
+    }
+
+    /// See `Sampler::snapshot`
+    #[cfg(feature = "linux-4.7")]
+    pub fn snapshot(&mut self) -> io::Result<Vec<Record>> {
+        self.sampler.snapshot()
+    }
+
+    pub const fn parse_context(&self) -> ParseContext {
+        self.sampler.parse_context()
+    }
+
     pub fn stat(&mut self) -> io::Result<TracerStat> {
         self.sampler.stat()
     }
@@ -177,3 +178,9 @@ impl Tracer {
         )
     }
 }
+
+impl AsRawFd for Tracer {
+    fn as_raw_fd(&self) -> RawFd {
+        self.sampler.as_raw_fd()
+    }
+}
@@ -0,0 +1,107 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+use crate::counting::Counter;
+use crate::tracing::Tracer;
+use crate::trigger::signal::SignalPipe;
+use libc::c_int;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+enum Kind {
+    Tracer(Tracer),
+    Counter {
+        counter: Counter,
+        threshold: u64,
+        base: u64,
+    },
+    Flag(Arc<AtomicBool>),
+    Signal(SignalPipe),
+}
+
+/// Condition to fire a `Trigger`, it's re-armed after each capture.
+pub struct Condition(Kind);
+
+impl Condition {
+    /// Fires when the tracer records an event, e.g. a tracepoint is hit.
+    /// While armed, the tracer is limited to one overflow by `PERF_EVENT_IOC_REFRESH`,
+    /// so it disables itself after firing.
+    pub const fn tracer(tracer: Tracer) -> Self {
+        Self(Kind::Tracer(tracer))
+    }
+
+    /// Fires when the counter increases by `threshold` since armed.
+    pub const fn counter(counter: Counter, threshold: u64) -> Self {
+        Self(Kind::Counter {
+            counter,
+            threshold,
+            base: 0,
+        })
+    }
+
+    /// Fires when the flag is set, e.g. by a signal handler.
+    /// The flag is cleared when armed and after firing.
+    pub const fn flag(flag: Arc<AtomicBool>) -> Self {
+        Self(Kind::Flag(flag))
+    }
+
+    /// Fires when the process receives the signal, e.g. `libc::SIGUSR2`.
+    /// A handler writing to a self-pipe is installed until the condition is dropped,
+    /// and fails with `AlreadyExists` if another condition watches the signal.
+    pub fn signal(sig: c_int) -> io::Result<Self> {
+        Ok(Self(Kind::Signal(SignalPipe::new(sig)?)))
+    }
+
+    pub(crate) fn arm(&mut self) -> io::Result<()> {
+        match &mut self.0 {
+            Kind::Tracer(tracer) => {
+                tracer.disable()?;
+                while tracer.next_record_ref().is_some() {}
+                tracer.refresh(1)
+            }
+            Kind::Counter { counter, base, .. } => {
+                counter.enable()?;
+                *base = counter.stat()?.event_count;
+                Ok(())
+            }
+            Kind::Flag(flag) => {
+                flag.store(false, Ordering::Relaxed);
+                Ok(())
+            }
+            Kind::Signal(pipe) => pipe.take().map(drop),
+        }
+    }
+
+    pub(crate) fn fired(&mut self) -> io::Result<bool> {
+        match &mut self.0 {
+            Kind::Tracer(tracer) => {
+                let mut fired = false;
+                while tracer.next_record_ref().is_some() {
+                    fired = true;
+                }
+                Ok(fired)
+            }
+            Kind::Counter {
+                counter,
+                threshold,
+                base,
+            } => {
+                let count = counter.stat()?.event_count;
+                Ok(count.saturating_sub(*base) >= *threshold)
+            }
+            Kind::Flag(flag) => Ok(flag.swap(false, Ordering::AcqRel)),
+            Kind::Signal(pipe) => pipe.take(),
+        }
+    }
+}
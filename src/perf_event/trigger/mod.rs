@@ -0,0 +1,226 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+mod condition;
+mod signal;
+#[cfg(test)]
+mod tests;
+
+use crate::sampling::record::Record;
+use crate::sampling::Sampler;
+use crate::tracing::Tracer;
+use std::collections::VecDeque;
+use std::io;
+use std::mem::take;
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub use condition::*;
+
+/// Where the records are captured from.
+///
+/// In overwrite mode (`write_backward`), the target keeps running and the window
+/// is cut from a snapshot, otherwise records are drained while polling.
+pub enum Target {
+    Sampler(Sampler),
+    Tracer(Tracer),
+}
+
+impl From<Sampler> for Target {
+    fn from(value: Sampler) -> Self {
+        Self::Sampler(value)
+    }
+}
+
+impl From<Tracer> for Target {
+    fn from(value: Tracer) -> Self {
+        Self::Tracer(value)
+    }
+}
+
+/// Bounds of the capture around each trigger.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Window {
+    /// Max records captured before the trigger,
+    /// the target is disabled until the trigger if this is 0 (except in overwrite mode)
+    pub pre: usize,
+    /// Max records captured after the trigger
+    pub post: usize,
+    /// The window closes after this long, or when `post` records are captured
+    /// (only the former in overwrite mode)
+    pub post_duration: Duration,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Capture {
+    /// Index of the fired condition
+    pub condition: usize,
+    /// Records before the trigger, oldest first
+    pub pre: Vec<Record>,
+    /// Records after the trigger, oldest first
+    pub post: Vec<Record>,
+}
+
+enum State {
+    Armed,
+    Fired {
+        condition: usize,
+        at: Instant,
+        /// `data_head` when fired, to split the snapshot in overwrite mode
+        mark: u64,
+    },
+}
+
+/// Start and stop capturing records of the target when any of the conditions fires.
+pub struct Trigger {
+    sampler: Sampler,
+    conditions: Vec<Condition>,
+    window: Window,
+    state: State,
+    pre: VecDeque<Record>,
+    post: Vec<Record>,
+}
+
+impl Trigger {
+    /// Enable the target as needed and arm the conditions.
+    pub fn new(
+        target: impl Into<Target>,
+        conditions: Vec<Condition>,
+        window: Window,
+    ) -> io::Result<Self> {
+        let sampler = match target.into() {
+            Target::Sampler(sampler) => sampler,
+            Target::Tracer(tracer) => tracer.sampler,
+        };
+        let mut trigger = Self {
+            sampler,
+            conditions,
+            window,
+            state: State::Armed,
+            pre: VecDeque::new(),
+            post: vec![],
+        };
+        trigger.arm()?;
+        Ok(trigger)
+    }
+
+    fn arm(&mut self) -> io::Result<()> {
+        self.state = State::Armed;
+        self.pre.clear();
+        self.post.clear();
+
+        let pre = self.window.pre;
+        let sampler = &mut self.sampler;
+        if sampler.write_backward || pre > 0 {
+            sampler.enable()?;
+        } else {
+            sampler.disable()?;
+        }
+        while sampler.next_record_ref().is_some() {}
+
+        self.conditions.iter_mut().try_for_each(Condition::arm)
+    }
+
+    fn drain_pre(&mut self) {
+        let sampler = &mut self.sampler;
+        while let Some(record) = sampler.next_record() {
+            self.pre.push_back(record);
+            if self.pre.len() > self.window.pre {
+                self.pre.pop_front();
+            }
+        }
+    }
+
+    fn fired(&mut self) -> io::Result<Option<usize>> {
+        for (i, condition) in self.conditions.iter_mut().enumerate() {
+            if condition.fired()? {
+                return Ok(Some(i));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Check the conditions and collect records without blocking,
+    /// returns the capture when the window after a trigger closes.
+    pub fn poll(&mut self) -> io::Result<Option<Capture>> {
+        if matches!(self.state, State::Armed) {
+            let Some(condition) = self.fired()? else {
+                self.drain_pre();
+                return Ok(None);
+            };
+            // records written before the condition is seen are counted as before the trigger
+            self.drain_pre();
+            let pre = self.window.pre;
+            let sampler = &mut self.sampler;
+            if !sampler.write_backward && pre == 0 {
+                sampler.enable()?;
+            }
+            self.state = State::Fired {
+                condition,
+                at: Instant::now(),
+                mark: sampler.data_head(),
+            };
+        }
+
+        let State::Fired {
+            condition,
+            at,
+            mark,
+        } = self.state
+        else {
+            unreachable!()
+        };
+        let window = self.window.clone();
+        let sampler = &mut self.sampler;
+
+        let (pre, post) = if sampler.write_backward {
+            if at.elapsed() < window.post_duration {
+                return Ok(None);
+            }
+            let (mut pre, mut post) = sampler.snapshot_since(mark)?;
+            pre.truncate(window.pre);
+            pre.reverse();
+            post.reverse();
+            post.truncate(window.post);
+            (pre, post)
+        } else {
+            while self.post.len() < window.post {
+                match sampler.next_record() {
+                    Some(record) => self.post.push(record),
+                    None => break,
+                }
+            }
+            if self.post.len() < window.post && at.elapsed() < window.post_duration {
+                return Ok(None);
+            }
+            (self.pre.drain(..).collect(), take(&mut self.post))
+        };
+
+        self.arm()?;
+        Ok(Some(Capture {
+            condition,
+            pre,
+            post,
+        }))
+    }
+
+    /// Poll every `interval` until a capture is done.
+    pub fn wait(&mut self, interval: Duration) -> io::Result<Capture> {
+        loop {
+            if let Some(capture) = self.poll()? {
+                return Ok(capture);
+            }
+            thread::sleep(interval);
+        }
+    }
+}
@@ -0,0 +1,116 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+
+use libc::c_int;
+use std::fs::File;
+use std::io;
+use std::io::Read;
+use std::mem::zeroed;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::ptr;
+use std::sync::atomic::{AtomicI32, Ordering};
+
+#[allow(clippy::declare_interior_mutable_const)]
+const NO_PIPE: AtomicI32 = AtomicI32::new(-1);
+/// Write ends of the self-pipes by signal number, -1 if the signal is not watched
+static PIPES: [AtomicI32; 65] = [NO_PIPE; 65];
+
+extern "C" fn on_signal(sig: c_int) {
+    unsafe {
+        // the interrupted code may be checking errno
+        let errno = *libc::__errno_location();
+        let fd = PIPES[sig as usize].load(Ordering::Acquire);
+        if fd >= 0 {
+            libc::write(fd, [0_u8].as_ptr() as _, 1);
+        }
+        *libc::__errno_location() = errno;
+    }
+}
+
+/// Self-pipe written by the handler of a signal, the previous handler is
+/// restored on drop. Only one pipe can watch a signal at a time.
+pub struct SignalPipe {
+    sig: c_int,
+    read: File,
+    _write: OwnedFd,
+    old_action: libc::sigaction,
+}
+
+impl SignalPipe {
+    pub fn new(sig: c_int) -> io::Result<Self> {
+        let slot = usize::try_from(sig)
+            .ok()
+            .filter(|it| (1..PIPES.len()).contains(it))
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Invalid signal: {}", sig),
+                )
+            })?;
+
+        let mut fds = [0; 2];
+        if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC | libc::O_NONBLOCK) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        let (read, write) = unsafe { (File::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) };
+
+        if PIPES[slot]
+            .compare_exchange(-1, write.as_raw_fd(), Ordering::AcqRel, Ordering::Acquire)
+            .is_err()
+        {
+            let msg = format!("Signal already watched: {}", sig);
+            return Err(io::Error::new(io::ErrorKind::AlreadyExists, msg));
+        }
+
+        let mut action: libc::sigaction = unsafe { zeroed() };
+        action.sa_sigaction = on_signal as *const () as libc::sighandler_t;
+        action.sa_flags = libc::SA_RESTART;
+        let mut old_action = unsafe { zeroed() };
+        if unsafe { libc::sigaction(sig, &action, &mut old_action) } == -1 {
+            let e = io::Error::last_os_error();
+            PIPES[slot].store(-1, Ordering::Release);
+            return Err(e);
+        }
+
+        Ok(Self {
+            sig,
+            read,
+            _write: write,
+            old_action,
+        })
+    }
+
+    /// Consume the signals received so far, returns whether there are any.
+    pub fn take(&self) -> io::Result<bool> {
+        let mut buf = [0_u8; 64];
+        let mut received = false;
+        loop {
+            match (&self.read).read(&mut buf) {
+                // the write end is open, so never EOF
+                Ok(0) => return Ok(received),
+                Ok(_) => received = true,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(received),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+impl Drop for SignalPipe {
+    fn drop(&mut self) {
+        unsafe { libc::sigaction(self.sig, &self.old_action, ptr::null_mut()) };
+        PIPES[self.sig as usize].store(-1, Ordering::Release);
+    }
+}
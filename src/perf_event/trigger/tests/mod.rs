@@ -0,0 +1,249 @@
+// Copyright (c) 2023-2024 Optimatist Technology Co., Ltd. All rights reserved.
+// DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS FILE HEADER.
+//
+// This file is part of perf-event-rs.
+//
+// Perf-event-rs is free software: you can redistribute it and/or modify it under the terms of the GNU Lesser General Public License
+// as published by the Free Software Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Perf-event-rs is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even
+// the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public License along with Perf-event-rs. If not,
+// see <https://www.gnu.org/licenses/>.
+use crate::config::{Cpu, Process};
+use crate::counting::Counter;
+use crate::sampling::record::{Record, RecordBody};
+use crate::sampling::{Config, ExtraConfig, ExtraRecord, OverflowBy, Sampler};
+use crate::tracing::Tracer;
+use crate::trigger::{Condition, Trigger, Window};
+use crate::{Event, EventScope, SoftwareEvent};
+use memmap2::MmapOptions;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const PAGE: u64 = 4096;
+
+/// Records `PERF_RECORD_MMAP` of the workload.
+fn gen_sampler(write_backward: bool) -> Sampler {
+    let scopes = EventScope::all();
+    let ev = Event::from(SoftwareEvent::Dummy);
+    let overflow_by = OverflowBy::Period(1);
+    let extra_config = ExtraConfig {
+        mmap_data: true,
+        write_backward,
+        extra_record_types: vec![ExtraRecord::Mmap],
+        ..Default::default()
+    };
+    let cfg = Config::extra_new(&ev, &scopes, &overflow_by, &extra_config);
+    let mmap_pages = 1 + 1;
+    Sampler::new(&Process::Current, &Cpu::Any, mmap_pages, &cfg).unwrap()
+}
+
+/// Map anonymous memory of `(i + 1)` pages, so the `PERF_RECORD_MMAP` records are numbered.
+fn workload(range: std::ops::Range<u64>) {
+    for i in range {
+        let len = (i + 1) * PAGE;
+        let mmap = MmapOptions::new().len(len as _).map_anon().unwrap();
+        std::hint::black_box(mmap);
+    }
+}
+
+fn mmap_numbers(records: &[Record]) -> Vec<u64> {
+    records
+        .iter()
+        .filter_map(|it| match &it.body {
+            RecordBody::Mmap(body) if body.len % PAGE == 0 => Some(body.len / PAGE - 1),
+            _ => None,
+        })
+        .collect()
+}
+
+static SIGUSR2_FLAG: OnceLock<Arc<AtomicBool>> = OnceLock::new();
+
+extern "C" fn on_sigusr2(_: libc::c_int) {
+    if let Some(flag) = SIGUSR2_FLAG.get() {
+        flag.store(true, Ordering::Release);
+    }
+}
+
+#[test]
+fn test_flag_on_signal() {
+    let flag = SIGUSR2_FLAG.get_or_init(Default::default).clone();
+    unsafe { libc::signal(libc::SIGUSR2, on_sigusr2 as *const () as libc::sighandler_t) };
+
+    let window = Window {
+        pre: 5,
+        post: 5,
+        post_duration: Duration::from_secs(60),
+    };
+    let conditions = vec![Condition::flag(flag)];
+    let mut trigger = Trigger::new(gen_sampler(false), conditions, window).unwrap();
+
+    workload(0..20);
+    assert!(trigger.poll().unwrap().is_none());
+    unsafe { libc::raise(libc::SIGUSR2) };
+    // fired, waiting for records after the trigger
+    assert!(trigger.poll().unwrap().is_none());
+    workload(20..40);
+
+    let capture = trigger.poll().unwrap().unwrap();
+    assert_eq!(capture.condition, 0);
+    assert_eq!(mmap_numbers(&capture.pre), (15..20).collect::<Vec<_>>());
+    assert_eq!(mmap_numbers(&capture.post), (20..25).collect::<Vec<_>>());
+
+    // re-armed
+    workload(40..50);
+    assert!(trigger.poll().unwrap().is_none());
+}
+
+#[test]
+fn test_signal() {
+    // SIGUSR1 is not used by other tests
+    let condition = Condition::signal(libc::SIGUSR1).unwrap();
+    let err = Condition::signal(libc::SIGUSR1).err().unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+    assert!(Condition::signal(libc::SIGKILL).is_err());
+    assert!(Condition::signal(0).is_err());
+
+    let window = Window {
+        pre: 5,
+        post: 5,
+        post_duration: Duration::from_secs(60),
+    };
+    let mut trigger = Trigger::new(gen_sampler(false), vec![condition], window).unwrap();
+
+    workload(0..20);
+    assert!(trigger.poll().unwrap().is_none());
+    unsafe { libc::raise(libc::SIGUSR1) };
+    assert!(trigger.poll().unwrap().is_none());
+    workload(20..40);
+
+    let capture = trigger.poll().unwrap().unwrap();
+    assert_eq!(capture.condition, 0);
+    assert_eq!(mmap_numbers(&capture.pre), (15..20).collect::<Vec<_>>());
+    assert_eq!(mmap_numbers(&capture.post), (20..25).collect::<Vec<_>>());
+
+    // the signal can be watched again once the condition is dropped
+    drop(trigger);
+    drop(Condition::signal(libc::SIGUSR1).unwrap());
+}
+
+#[test]
+fn test_counter_threshold() {
+    let scopes = EventScope::all();
+    let ev = Event::from(SoftwareEvent::TaskClock);
+    let mut cfg = crate::counting::Config::new(&ev, &scopes);
+    let counter = Counter::new(&Process::Current, &Cpu::Any, &mut cfg).unwrap();
+
+    // record only after the task ran for 50ms
+    let window = Window {
+        pre: 0,
+        post: 3,
+        post_duration: Duration::from_secs(60),
+    };
+    let conditions = vec![
+        Condition::flag(Default::default()),
+        Condition::counter(counter, 50_000_000),
+    ];
+    let mut trigger = Trigger::new(gen_sampler(false), conditions, window).unwrap();
+
+    // not recorded since the sampler is disabled until the trigger
+    workload(0..10);
+    assert!(trigger.poll().unwrap().is_none());
+
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_millis(100) {
+        std::hint::black_box(rand::random::<u64>());
+    }
+    assert!(trigger.poll().unwrap().is_none());
+    workload(100..110);
+
+    let capture = trigger.poll().unwrap().unwrap();
+    assert_eq!(capture.condition, 1);
+    assert!(capture.pre.is_empty());
+    assert_eq!(mmap_numbers(&capture.post), vec![100, 101, 102]);
+}
+
+/// Spawn a thread which touches new pages on each message, returns its tid.
+fn spawn_faulting_thread() -> (u32, mpsc::Sender<()>, mpsc::Receiver<()>) {
+    let (tid_tx, tid_rx) = mpsc::channel();
+    let (go_tx, go_rx) = mpsc::channel::<()>();
+    let (done_tx, done_rx) = mpsc::channel();
+    thread::spawn(move || {
+        tid_tx.send(unsafe { libc::gettid() } as u32).unwrap();
+        for _ in go_rx {
+            let mut mmap = MmapOptions::new()
+                .len(PAGE as usize * 16)
+                .map_anon()
+                .unwrap();
+            mmap.iter_mut().step_by(PAGE as _).for_each(|it| *it = 1);
+            std::hint::black_box(mmap);
+            done_tx.send(()).unwrap();
+        }
+    });
+    (tid_rx.recv().unwrap(), go_tx, done_rx)
+}
+
+#[test]
+fn test_tracer_flight_recorder() {
+    let (tid, go, done) = spawn_faulting_thread();
+    let ev = Event::from(SoftwareEvent::PageFaults);
+    let cfg = crate::tracing::Config::new(&ev, &EventScope::all());
+    let mmap_pages = 1 + 1;
+    let tracer = Tracer::new(&Process::Pid(tid), &Cpu::Any, mmap_pages, &cfg).unwrap();
+
+    let window = Window {
+        pre: 10,
+        post: 5,
+        post_duration: Duration::ZERO,
+    };
+    let conditions = vec![Condition::tracer(tracer)];
+    let mut trigger = Trigger::new(gen_sampler(true), conditions, window).unwrap();
+
+    // the ring buffer is overwritten many times before the trigger
+    workload(0..1000);
+    assert!(trigger.poll().unwrap().is_none());
+    go.send(()).unwrap();
+    done.recv().unwrap();
+
+    let capture = trigger.poll().unwrap().unwrap();
+    assert_eq!(capture.condition, 0);
+    assert_eq!(mmap_numbers(&capture.pre), (990..1000).collect::<Vec<_>>());
+    assert!(capture.post.is_empty());
+
+    // fires again after re-armed
+    assert!(trigger.poll().unwrap().is_none());
+    workload(1000..1010);
+    go.send(()).unwrap();
+    done.recv().unwrap();
+    let capture = trigger.poll().unwrap().unwrap();
+    assert_eq!(mmap_numbers(&capture.pre), (1000..1010).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_flight_recorder_post_window() {
+    let flag = Arc::new(AtomicBool::new(false));
+    let window = Window {
+        pre: 10,
+        post: 5,
+        post_duration: Duration::from_millis(100),
+    };
+    let conditions = vec![Condition::flag(flag.clone())];
+    let mut trigger = Trigger::new(gen_sampler(true), conditions, window).unwrap();
+
+    workload(0..1000);
+    flag.store(true, Ordering::Release);
+    assert!(trigger.poll().unwrap().is_none());
+    workload(1000..1020);
+
+    let capture = trigger.wait(Duration::from_millis(10)).unwrap();
+    assert_eq!(mmap_numbers(&capture.pre), (990..1000).collect::<Vec<_>>());
+    assert_eq!(
+        mmap_numbers(&capture.post),
+        (1000..1005).collect::<Vec<_>>()
+    );
+}
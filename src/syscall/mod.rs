@@ -46,3 +46,12 @@ pub unsafe fn ioctl<A>(
 ) -> c_int {
     libc::ioctl(fd, request as _, arg)
 }
+
+/// # Safety
+/// The arguments must be correct for this syscall
+pub unsafe fn prctl(
+    option: c_int, //i32
+    arg2: c_ulong, //u64
+) -> c_int {
+    libc::prctl(option, arg2, 0, 0, 0)
+}
@@ -13,11 +13,11 @@
 // see <https://www.gnu.org/licenses/>.
 
 use crate::syscall::bindings::perf_event_attr;
-use crate::syscall::{ioctl, perf_event_open};
+use crate::syscall::{ioctl, perf_event_open, prctl};
 use std::ffi::c_int;
 use std::fs::File;
 use std::io;
-use std::os::fd::AsRawFd;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
 
 pub fn ioctl_wrapped<A>(file: &File, request: impl Into<u64>, arg: Option<A>) -> io::Result<()> {
     let i32 = match arg {
@@ -43,3 +43,64 @@ pub unsafe fn perf_event_open_wrapped(
         fd => Ok(fd),
     }
 }
+
+pub fn prctl_wrapped(option: i32) -> io::Result<()> {
+    let i32 = unsafe { prctl(option, 0) };
+    match i32 {
+        -1 => Err(io::Error::last_os_error()),
+        _ => Ok(()),
+    }
+}
+
+/// Poll one fd, returns `revents` or 0 if timed out or interrupted.
+pub fn poll_wrapped(fd: RawFd, events: i16, timeout_ms: i32) -> io::Result<i16> {
+    let mut pollfd = libc::pollfd {
+        fd,
+        events,
+        revents: 0,
+    };
+    match unsafe { libc::poll(&mut pollfd, 1, timeout_ms) } {
+        -1 => match io::Error::last_os_error() {
+            e if e.kind() == io::ErrorKind::Interrupted => Ok(0),
+            e => Err(e),
+        },
+        _ => Ok(pollfd.revents),
+    }
+}
+
+pub fn epoll_create_wrapped() -> io::Result<OwnedFd> {
+    match unsafe { libc::epoll_create1(libc::EPOLL_CLOEXEC) } {
+        -1 => Err(io::Error::last_os_error()),
+        fd => Ok(unsafe { OwnedFd::from_raw_fd(fd) }),
+    }
+}
+
+pub fn epoll_ctl_wrapped(
+    epoll: &OwnedFd,
+    op: i32,
+    fd: RawFd,
+    events: u32,
+    data: u64,
+) -> io::Result<()> {
+    let mut event = libc::epoll_event { events, u64: data };
+    match unsafe { libc::epoll_ctl(epoll.as_raw_fd(), op, fd, &mut event) } {
+        -1 => Err(io::Error::last_os_error()),
+        _ => Ok(()),
+    }
+}
+
+/// Wait for events into `buf`, returns the number of events or 0 if timed out or interrupted.
+pub fn epoll_wait_wrapped(
+    epoll: &OwnedFd,
+    buf: &mut [libc::epoll_event],
+    timeout_ms: i32,
+) -> io::Result<usize> {
+    let len = buf.len().min(i32::MAX as _) as _;
+    match unsafe { libc::epoll_wait(epoll.as_raw_fd(), buf.as_mut_ptr(), len, timeout_ms) } {
+        -1 => match io::Error::last_os_error() {
+            e if e.kind() == io::ErrorKind::Interrupted => Ok(0),
+            e => Err(e),
+        },
+        n => Ok(n as _),
+    }
+}